@@ -0,0 +1,437 @@
+use std::path::Path;
+
+use rusqlite::types::{Type, ValueRef};
+use rusqlite::{params, Connection, OpenFlags, Row};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::ankigen::db_model::card::{
+    Card, CardFlag, CardQueue, CardType,
+};
+use crate::ankigen::db_model::collection::Collection;
+use crate::ankigen::db_model::deck::{Deck, DeckConfig};
+use crate::ankigen::db_model::note::{
+    tags_from_str, tags_to_string, Note,
+};
+
+/// Version of the collection schema written to `col.ver`.
+pub const SCHEMA_VERSION: usize = 11;
+
+/// Id of the `Default` deck and of the default options group, Anki
+/// refuses a collection without them.
+pub const DEFAULT_ID: usize = 1;
+
+/// Separator of the fields in `notes.flds`.
+pub const FIELD_SEPARATOR: char = '\x1f';
+
+/// Tables and indices of a schema 11 collection, as created by
+/// Anki 2.1 before the move to the newer schemas.
+pub const SCHEMA: &str = "
+create table col (
+    id              integer primary key,
+    crt             integer not null,
+    mod             integer not null,
+    scm             integer not null,
+    ver             integer not null,
+    dty             integer not null,
+    usn             integer not null,
+    ls              integer not null,
+    conf            text not null,
+    models          text not null,
+    decks           text not null,
+    dconf           text not null,
+    tags            text not null
+);
+create table notes (
+    id              integer primary key,
+    guid            text not null,
+    mid             integer not null,
+    mod             integer not null,
+    usn             integer not null,
+    tags            text not null,
+    flds            text not null,
+    sfld            integer not null,
+    csum            integer not null,
+    flags           integer not null,
+    data            text not null
+);
+create table cards (
+    id              integer primary key,
+    nid             integer not null,
+    did             integer not null,
+    ord             integer not null,
+    mod             integer not null,
+    usn             integer not null,
+    type            integer not null,
+    queue           integer not null,
+    due             integer not null,
+    ivl             integer not null,
+    factor          integer not null,
+    reps            integer not null,
+    lapses          integer not null,
+    left            integer not null,
+    odue            integer not null,
+    odid            integer not null,
+    flags           integer not null,
+    data            text not null
+);
+create table revlog (
+    id              integer primary key,
+    cid             integer not null,
+    usn             integer not null,
+    ease            integer not null,
+    ivl             integer not null,
+    lastIvl         integer not null,
+    factor          integer not null,
+    time            integer not null,
+    type            integer not null
+);
+create table graves (
+    usn             integer not null,
+    oid             integer not null,
+    type            integer not null
+);
+create index ix_notes_usn on notes (usn);
+create index ix_cards_usn on cards (usn);
+create index ix_revlog_usn on revlog (usn);
+create index ix_cards_nid on cards (nid);
+create index ix_cards_sched on cards (did, queue, due);
+create index ix_revlog_cid on revlog (cid);
+create index ix_notes_csum on notes (csum);
+";
+
+fn to_json<T: Serialize>(value: &T) -> rusqlite::Result<Value> {
+    serde_json::to_value(value).map_err(|err| {
+        rusqlite::Error::ToSqlConversionFailure(err.into())
+    })
+}
+
+/// The `models`, `decks` and `dconf` columns: a JSON object mapping
+/// the id of each entry, as a string, to the entry.
+fn id_map<T: Serialize>(
+    entries: &[(usize, T)],
+) -> rusqlite::Result<String> {
+    let mut map = Map::new();
+    for (id, entry) in entries {
+        map.insert(id.to_string(), to_json(entry)?);
+    }
+    Ok(Value::Object(map).to_string())
+}
+
+/// The decks of `collection`, with the `Default` deck added when it
+/// is missing and normal decks without options pointed at the
+/// default group.
+fn decks(collection: &Collection) -> Vec<(usize, Deck)> {
+    let mut decks = collection.decks.clone();
+    if !decks.iter().any(|(id, _)| *id == DEFAULT_ID) {
+        let mut deck = Deck::new("Default".to_string());
+        deck.id = DEFAULT_ID as i64;
+        decks.insert(0, (DEFAULT_ID, deck));
+    }
+    for (_, deck) in decks.iter_mut() {
+        if deck.filtered == 0 && deck.config_id.is_none() {
+            deck.config_id = Some(DEFAULT_ID);
+        }
+    }
+    decks
+}
+
+/// The options groups of `collection`, with the default group added
+/// when it is missing.
+fn deck_configs(collection: &Collection) -> Vec<(usize, DeckConfig)> {
+    let mut deck_configs = collection.deck_configs.clone();
+    if !deck_configs.iter().any(|(id, _)| *id == DEFAULT_ID) {
+        let mut deck_config = DeckConfig::new("Default".to_string());
+        deck_config.id = Some(DEFAULT_ID);
+        deck_configs.insert(0, (DEFAULT_ID, deck_config));
+    }
+    deck_configs
+}
+
+/// Creates the schema 11 tables in an empty database and fills them
+/// with `collection`, `notes` and `cards`, in one transaction.
+///
+/// The `ver` column is always [`SCHEMA_VERSION`], whatever the
+/// `version` of the collection says.
+pub fn write_collection(
+    conn: &mut Connection,
+    collection: &Collection,
+    notes: &[Note],
+    cards: &[Card],
+) -> rusqlite::Result<()> {
+    let tx = conn.transaction()?;
+    tx.execute_batch(SCHEMA)?;
+
+    tx.execute(
+        "insert into col values \
+         (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+        params![
+            collection.id,
+            collection.created,
+            collection.modified,
+            collection.scheme_mod_time,
+            SCHEMA_VERSION,
+            collection.dirty,
+            collection.update_seq_number,
+            collection.last_sync_time,
+            to_json(&collection.config)?.to_string(),
+            id_map(&collection.models)?,
+            id_map(&decks(collection))?,
+            id_map(&deck_configs(collection))?,
+            match collection.tags.as_str() {
+                "" => "{}",
+                tags => tags,
+            },
+        ],
+    )?;
+
+    {
+        let mut insert_note = tx.prepare(
+            "insert into notes values \
+             (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+        )?;
+        for note in notes {
+            insert_note.execute(params![
+                note.id,
+                note.global_id,
+                note.model_id,
+                note.modified,
+                note.update_seq_number,
+                tags_to_string(&note.tags),
+                note.fields.join(&FIELD_SEPARATOR.to_string()),
+                note.sort_field,
+                note.checksum,
+                note.flags,
+                note.data,
+            ])?;
+        }
+
+        let mut insert_card = tx.prepare(
+            "insert into cards values \
+             (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, \
+             ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18)",
+        )?;
+        for card in cards {
+            insert_card.execute(params![
+                card.id,
+                card.note_id,
+                card.deck_id,
+                card.ordinal,
+                card.modified,
+                card.update_seq_number,
+                card.card_type.clone() as i64,
+                card.queue.clone() as i64,
+                card.due,
+                card.interval,
+                card.factor,
+                card.reviews,
+                card.lapses,
+                card.left,
+                card.original_due,
+                card.original_deck_id,
+                card.flags.clone() as i64,
+                card.data,
+            ])?;
+        }
+    }
+
+    tx.commit()
+}
+
+/// Writes a new `collection.anki2` file at `path`. Fails if there
+/// already is a file there, collection or not, which is left as is.
+pub fn write_collection_file(
+    path: &Path,
+    collection: &Collection,
+    notes: &[Note],
+    cards: &[Card],
+) -> rusqlite::Result<()> {
+    // SQLite takes an empty file for a new database.
+    std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(path)
+        .map_err(|error| {
+            rusqlite::Error::SqliteFailure(
+                rusqlite::ffi::Error::new(
+                    rusqlite::ffi::SQLITE_CANTOPEN,
+                ),
+                Some(format!("{}: {error}", path.display())),
+            )
+        })?;
+    let mut conn = Connection::open(path)?;
+    write_collection(&mut conn, collection, notes, cards)
+}
+
+/// Everything read back from a collection database.
+#[derive(Debug, Clone)]
+pub struct CollectionData {
+    pub collection: Collection,
+    pub notes: Vec<Note>,
+    pub cards: Vec<Card>,
+}
+
+fn conversion_error(
+    idx: usize,
+    error: impl Into<Box<dyn std::error::Error + Send + Sync>>,
+) -> rusqlite::Error {
+    rusqlite::Error::FromSqlConversionFailure(
+        idx,
+        Type::Text,
+        error.into(),
+    )
+}
+
+fn from_json<T: DeserializeOwned>(
+    row: &Row,
+    idx: usize,
+) -> rusqlite::Result<T> {
+    serde_json::from_str(&row.get::<_, String>(idx)?)
+        .map_err(|err| conversion_error(idx, err))
+}
+
+/// Reads a `models`, `decks` or `dconf` column, the reverse of
+/// [`id_map`].
+fn from_id_map<T: DeserializeOwned>(
+    row: &Row,
+    idx: usize,
+) -> rusqlite::Result<Vec<(usize, T)>> {
+    from_json::<Map<String, Value>>(row, idx)?
+        .into_iter()
+        .map(|(id, entry)| {
+            let id = id
+                .parse::<usize>()
+                .map_err(|err| conversion_error(idx, err))?;
+            let entry = serde_json::from_value(entry)
+                .map_err(|err| conversion_error(idx, err))?;
+            Ok((id, entry))
+        })
+        .collect()
+}
+
+fn from_enum<T: TryFrom<i64, Error = &'static str>>(
+    row: &Row,
+    idx: usize,
+) -> rusqlite::Result<T> {
+    T::try_from(row.get::<_, i64>(idx)?)
+        .map_err(|err| conversion_error(idx, err))
+}
+
+fn read_col(row: &Row) -> rusqlite::Result<Collection> {
+    Ok(Collection {
+        id: row.get(0)?,
+        created: row.get(1)?,
+        modified: row.get(2)?,
+        scheme_mod_time: row.get(3)?,
+        version: row.get(4)?,
+        dirty: row.get(5)?,
+        update_seq_number: row.get(6)?,
+        last_sync_time: row.get(7)?,
+        config: from_json(row, 8)?,
+        models: from_id_map(row, 9)?,
+        decks: from_id_map(row, 10)?,
+        deck_configs: from_id_map(row, 11)?,
+        tags: row.get(12)?,
+    })
+}
+
+fn read_note(row: &Row) -> rusqlite::Result<Note> {
+    let tags = tags_from_str(&row.get::<_, String>(5)?);
+    let fields = row
+        .get::<_, String>(6)?
+        .split(FIELD_SEPARATOR)
+        .map(str::to_string)
+        .collect();
+    // A numeric sort field is stored as a number, `sfld` has integer
+    // affinity.
+    let sort_field = match row.get_ref(7)? {
+        ValueRef::Integer(number) => number.to_string(),
+        ValueRef::Real(number) => number.to_string(),
+        _ => row.get(7)?,
+    };
+
+    Ok(Note {
+        id: row.get(0)?,
+        global_id: row.get(1)?,
+        model_id: row.get(2)?,
+        modified: row.get(3)?,
+        update_seq_number: row.get(4)?,
+        tags,
+        fields,
+        sort_field,
+        checksum: row.get(8)?,
+        flags: row.get(9)?,
+        data: row.get(10)?,
+    })
+}
+
+fn read_card(row: &Row) -> rusqlite::Result<Card> {
+    Ok(Card {
+        id: row.get(0)?,
+        note_id: row.get(1)?,
+        deck_id: row.get(2)?,
+        ordinal: row.get(3)?,
+        modified: row.get(4)?,
+        update_seq_number: row.get(5)?,
+        card_type: from_enum::<CardType>(row, 6)?,
+        queue: from_enum::<CardQueue>(row, 7)?,
+        due: row.get(8)?,
+        interval: row.get(9)?,
+        factor: row.get(10)?,
+        reviews: row.get(11)?,
+        lapses: row.get(12)?,
+        left: row.get(13)?,
+        original_due: row.get(14)?,
+        original_deck_id: row.get(15)?,
+        flags: from_enum::<CardFlag>(row, 16)?,
+        data: row.get(17)?,
+    })
+}
+
+/// Reads the collection, its notes and its cards from a schema 11
+/// database. Notes and cards come in id order.
+pub fn read_collection(
+    conn: &Connection,
+) -> rusqlite::Result<CollectionData> {
+    let collection = conn.query_row(
+        "select id, crt, mod, scm, ver, dty, usn, ls, \
+         conf, models, decks, dconf, tags from col",
+        [],
+        read_col,
+    )?;
+
+    let notes = conn
+        .prepare(
+            "select id, guid, mid, mod, usn, tags, flds, sfld, \
+             csum, flags, data from notes order by id",
+        )?
+        .query_map([], read_note)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    let cards = conn
+        .prepare(
+            "select id, nid, did, ord, mod, usn, type, queue, due, \
+             ivl, factor, reps, lapses, left, odue, odid, flags, \
+             data from cards order by id",
+        )?
+        .query_map([], read_card)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    Ok(CollectionData {
+        collection,
+        notes,
+        cards,
+    })
+}
+
+/// Reads a `collection.anki2` file without modifying it.
+pub fn read_collection_file(
+    path: &Path,
+) -> rusqlite::Result<CollectionData> {
+    let conn = Connection::open_with_flags(
+        path,
+        OpenFlags::SQLITE_OPEN_READ_ONLY,
+    )?;
+    read_collection(&conn)
+}
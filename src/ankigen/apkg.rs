@@ -0,0 +1,176 @@
+use std::fs::File;
+use std::io::{Seek, Write};
+use std::path::Path;
+
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::ankigen::anki2::{
+    read_collection_file, write_collection_file, CollectionData,
+};
+use crate::ankigen::db_model::card::Card;
+use crate::ankigen::db_model::collection::Collection;
+use crate::ankigen::db_model::note::Note;
+use crate::ankigen::media::MediaManifest;
+
+/// Name of the collection in packages for every Anki version.
+pub const COLLECTION_ANKI2: &str = "collection.anki2";
+
+/// Name of the collection that Anki 2.1 reads in place of
+/// [`COLLECTION_ANKI2`] when the package holds both.
+pub const COLLECTION_ANKI21: &str = "collection.anki21";
+
+/// Name of the media manifest of a package.
+pub const MEDIA: &str = "media";
+
+#[derive(Debug)]
+pub enum PackageError {
+    Io(std::io::Error),
+    Sqlite(rusqlite::Error),
+    Zip(zip::result::ZipError),
+}
+
+impl std::fmt::Display for PackageError {
+    fn fmt(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+    ) -> std::fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "{error}"),
+            Self::Sqlite(error) => write!(f, "collection: {error}"),
+            Self::Zip(error) => write!(f, "package: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for PackageError {}
+
+impl From<std::io::Error> for PackageError {
+    fn from(error: std::io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+impl From<rusqlite::Error> for PackageError {
+    fn from(error: rusqlite::Error) -> Self {
+        Self::Sqlite(error)
+    }
+}
+
+impl From<zip::result::ZipError> for PackageError {
+    fn from(error: zip::result::ZipError) -> Self {
+        Self::Zip(error)
+    }
+}
+
+/// An `.apkg` file: a zip holding the collection database, the media
+/// files stored as `0`, `1`, ... and the `media` manifest mapping
+/// those numbers back to the media names.
+pub struct Package<'a> {
+    collection: &'a Collection,
+    notes: &'a [Note],
+    cards: &'a [Card],
+    media: Option<&'a MediaManifest>,
+    anki21: bool,
+}
+
+impl<'a> Package<'a> {
+    pub fn new(
+        collection: &'a Collection,
+        notes: &'a [Note],
+        cards: &'a [Card],
+    ) -> Self {
+        Self {
+            collection,
+            notes,
+            cards,
+            media: None,
+            anki21: false,
+        }
+    }
+
+    pub fn with_media(mut self, media: &'a MediaManifest) -> Self {
+        self.media = Some(media);
+        self
+    }
+
+    /// Also stores the collection as [`COLLECTION_ANKI21`]. Anki 2.1
+    /// then imports that copy, older versions still find
+    /// [`COLLECTION_ANKI2`].
+    pub fn with_anki21(mut self, anki21: bool) -> Self {
+        self.anki21 = anki21;
+        self
+    }
+
+    /// Writes the package to `path`, replacing any file there.
+    pub fn write(&self, path: &Path) -> Result<(), PackageError> {
+        self.write_to(File::create(path)?)
+    }
+
+    pub fn write_to<W: Write + Seek>(
+        &self,
+        writer: W,
+    ) -> Result<(), PackageError> {
+        let dir = tempfile::tempdir()?;
+        let collection_path = dir.path().join(COLLECTION_ANKI2);
+        write_collection_file(
+            &collection_path,
+            self.collection,
+            self.notes,
+            self.cards,
+        )?;
+        let collection = std::fs::read(&collection_path)?;
+
+        let options = SimpleFileOptions::default()
+            .compression_method(CompressionMethod::Deflated);
+        let mut zip = ZipWriter::new(writer);
+
+        zip.start_file(COLLECTION_ANKI2, options)?;
+        zip.write_all(&collection)?;
+        if self.anki21 {
+            zip.start_file(COLLECTION_ANKI21, options)?;
+            zip.write_all(&collection)?;
+        }
+
+        let files = self.media.map(MediaManifest::files);
+        for (idx, file) in
+            files.unwrap_or_default().iter().enumerate()
+        {
+            let content =
+                std::fs::read(&file.path).map_err(|err| {
+                    std::io::Error::new(
+                        err.kind(),
+                        format!("{}: {err}", file.path.display()),
+                    )
+                })?;
+            zip.start_file(idx.to_string(), options)?;
+            zip.write_all(&content)?;
+        }
+        zip.start_file(MEDIA, options)?;
+        let media = self.media.map(MediaManifest::to_json);
+        zip.write_all(media.as_deref().unwrap_or("{}").as_bytes())?;
+
+        zip.finish()?;
+        Ok(())
+    }
+}
+
+/// Reads the collection of the `.apkg` at `path`, the
+/// [`COLLECTION_ANKI21`] copy when the package has one.
+pub fn read_package(
+    path: &Path,
+) -> Result<CollectionData, PackageError> {
+    let mut archive = ZipArchive::new(File::open(path)?)?;
+    let name = match archive.index_for_name(COLLECTION_ANKI21) {
+        Some(_) => COLLECTION_ANKI21,
+        None => COLLECTION_ANKI2,
+    };
+
+    let dir = tempfile::tempdir()?;
+    let collection_path = dir.path().join(name);
+    std::io::copy(
+        &mut archive.by_name(name)?,
+        &mut File::create(&collection_path)?,
+    )?;
+    Ok(read_collection_file(&collection_path)?)
+}
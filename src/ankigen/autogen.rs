@@ -0,0 +1,748 @@
+use std::ops::Range;
+use std::path::Path;
+
+use pulldown_cmark::{Event, Parser, Tag, TagEnd};
+
+use crate::ankigen::deck::{
+    Deck, DeckError, DEFAULT_NOTE_LEVEL, SETTINGS_SECTIONS,
+};
+use crate::ankigen::metadata::{front_matter_key, Metadata};
+use crate::markdown::ast::{FrontMatterFormat, Node, Text};
+
+/// Name of the metadata entry holding the generated ids.
+pub const AUTOGEN: &str = "Autogen";
+
+/// The ids generated for a deck or a note, recorded under
+/// `- Autogen:` in its metadata so that the next build, on any
+/// machine, reuses them:
+///
+/// ```markdown
+/// - Autogen:
+///     - id: 1718000000000
+///     - guid: `f3Jx<kQ9,b`
+///     - model id: 1600000000001
+/// ```
+///
+/// An id of `0`, as in a new file, is one not generated yet. In
+/// front matter the ids go in an `autogen` mapping, or an
+/// `[autogen]` table in TOML.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Autogen {
+    pub id: Option<i64>,
+    pub guid: Option<String>,
+    pub model_id: Option<u64>,
+}
+
+fn invalid(key: &str, value: &str) -> DeckError {
+    DeckError::InvalidMetadata {
+        key: format!("{AUTOGEN}.{key}"),
+        value: value.to_string(),
+    }
+}
+
+impl Autogen {
+    pub fn from_metadata(
+        metadata: &Metadata,
+    ) -> Result<Self, DeckError> {
+        let value = |key: &str| {
+            metadata
+                .get_path(&[AUTOGEN, key])
+                .map(|entry| entry.value.as_str())
+                .filter(|value| !value.is_empty())
+        };
+        Ok(Self {
+            id: value("id")
+                .map(|id| id.parse().map_err(|_| invalid("id", id)))
+                .transpose()?,
+            guid: value("guid").map(str::to_string),
+            model_id: value("model id")
+                .map(|id| {
+                    id.parse().map_err(|_| invalid("model id", id))
+                })
+                .transpose()?,
+        })
+    }
+
+    /// The entries to write, in the order they are added to a list.
+    fn entries(&self) -> Vec<(&'static str, String)> {
+        let mut entries = vec![];
+        if let Some(id) = self.id {
+            entries.push(("id", id.to_string()));
+        }
+        if let Some(guid) = &self.guid {
+            entries.push(("guid", guid.clone()));
+        }
+        if let Some(model_id) = self.model_id {
+            entries.push(("model id", model_id.to_string()));
+        }
+        entries
+    }
+}
+
+struct Heading {
+    level: usize,
+    range: Range<usize>,
+    name: String,
+}
+
+#[derive(Clone)]
+struct Item {
+    depth: usize,
+    range: Range<usize>,
+}
+
+#[derive(Clone)]
+struct Block {
+    format: FrontMatterFormat,
+    range: Range<usize>,
+}
+
+/// The headings, list items and metadata blocks of the source, with
+/// their offsets.
+fn scan(source: &str) -> (Vec<Heading>, Vec<Item>, Vec<Block>) {
+    let mut headings = vec![];
+    let mut items = vec![];
+    let mut blocks = vec![];
+    let mut heading: Option<Heading> = None;
+    let mut depth = 0;
+    let parser = Parser::new_ext(source, Node::options());
+    for (event, range) in parser.into_offset_iter() {
+        match event {
+            Event::Start(Tag::Heading { level, .. }) => {
+                heading = Some(Heading {
+                    level: level as usize,
+                    range,
+                    name: String::new(),
+                })
+            }
+            Event::Text(text) | Event::Code(text) => {
+                if let Some(heading) = &mut heading {
+                    heading.name.push_str(&text);
+                }
+            }
+            Event::End(TagEnd::Heading(_)) => {
+                if let Some(mut heading) = heading.take() {
+                    heading.name = heading
+                        .name
+                        .trim()
+                        .trim_end_matches(':')
+                        .trim_end()
+                        .to_string();
+                    headings.push(heading);
+                }
+            }
+            Event::Start(Tag::List(_)) => depth += 1,
+            Event::End(TagEnd::List(_)) => depth -= 1,
+            Event::Start(Tag::Item) => {
+                items.push(Item { depth, range })
+            }
+            Event::Start(Tag::MetadataBlock(kind)) => {
+                blocks.push(Block {
+                    format: kind.into(),
+                    range,
+                })
+            }
+            _ => (),
+        }
+    }
+    (headings, items, blocks)
+}
+
+fn line_end(source: &str, offset: usize) -> usize {
+    source[offset..]
+        .find('\n')
+        .map_or(source.len(), |idx| offset + idx)
+}
+
+/// Where the key and the value of the `- key: value` item starting
+/// at `start` are, the value range being empty when there is none.
+fn item_entry(
+    source: &str,
+    start: usize,
+) -> (Range<usize>, Range<usize>) {
+    let line = &source[start..line_end(source, start)];
+    let item = line.trim_start();
+    let start = start + line.len() - item.len();
+    let item = item.trim_end();
+    let marker = item
+        .find(|chr: char| chr.is_whitespace())
+        .unwrap_or(item.len());
+    let key_start = start + marker + item[marker..].len()
+        - item[marker..].trim_start().len();
+    let end = start + item.len();
+    match source[key_start..end].find(':') {
+        Some(colon) => {
+            let colon = key_start + colon;
+            let value = &source[colon + 1..end];
+            let value_start = end - value.trim_start().len();
+            (key_start..colon, value_start..end)
+        }
+        None => (key_start..end, end..end),
+    }
+}
+
+fn key_is(source: &str, key: &Range<usize>, name: &str) -> bool {
+    source[key.clone()].trim().eq_ignore_ascii_case(name)
+}
+
+/// Offset of the end of the last line of `range` that is not blank.
+fn content_end(source: &str, range: &Range<usize>) -> usize {
+    range.start + source[range.clone()].trim_end().len()
+}
+
+/// The start of each line of `range` and the line, without its line
+/// break.
+fn lines(
+    source: &str,
+    range: Range<usize>,
+) -> impl Iterator<Item = (usize, &str)> {
+    let mut start = range.start;
+    std::iter::from_fn(move || {
+        if start >= range.end {
+            return None;
+        }
+        let end = line_end(source, start).min(range.end);
+        let line = (start, source[start..end].trim_end_matches('\r'));
+        start = end + 1;
+        Some(line)
+    })
+}
+
+/// The key and the value of a `key: value` or `key = value` line of
+/// front matter, as offsets into the line.
+fn block_entry(
+    format: FrontMatterFormat,
+    line: &str,
+) -> Option<(Range<usize>, Range<usize>)> {
+    let separator = match format {
+        FrontMatterFormat::Yaml => ':',
+        FrontMatterFormat::Toml => '=',
+    };
+    let colon = line.find(separator)?;
+    let key_start = line.len() - line.trim_start().len();
+    let value = line[colon + 1..].trim();
+    let value_start = match value.is_empty() {
+        true => line.len(),
+        false => colon + 1 + line[colon + 1..].find(value)?,
+    };
+    Some((key_start..colon, value_start..value_start + value.len()))
+}
+
+/// An id as written in a `- Autogen:` list: the guid, which may hold
+/// any base91 character, is inline code so that none of them is read
+/// as markdown.
+fn item_value(key: &str, value: &str) -> String {
+    match key {
+        "guid" => Text::Code(value.to_string()).to_markdown(),
+        _ => value.to_string(),
+    }
+}
+
+/// An id as written in front matter: the guid, which may hold any
+/// base91 character, is quoted.
+fn block_value(
+    format: FrontMatterFormat,
+    key: &str,
+    value: &str,
+) -> String {
+    match (key, format) {
+        ("guid", FrontMatterFormat::Yaml) => {
+            format!("'{}'", value.replace('\'', "''"))
+        }
+        ("guid", FrontMatterFormat::Toml) => {
+            format!("\"{}\"", value.replace('\\', "\\\\"))
+        }
+        _ => value.to_string(),
+    }
+}
+
+fn block_key_is(key: &str, name: &str) -> bool {
+    front_matter_key(key.trim().trim_matches(['"', '\'']))
+        .eq_ignore_ascii_case(name)
+}
+
+struct Writer<'a> {
+    source: &'a str,
+    headings: Vec<Heading>,
+    items: Vec<Item>,
+    blocks: Vec<Block>,
+    newline: &'static str,
+    edits: Vec<(Range<usize>, String)>,
+}
+
+impl Writer<'_> {
+    /// Where the section of `heading` ends: at the next heading of
+    /// the same level or above, or at the end of the file.
+    fn section_end(&self, heading: usize) -> usize {
+        let level = self.headings[heading].level;
+        self.headings[heading + 1..]
+            .iter()
+            .find(|next| next.level <= level)
+            .map_or(self.source.len(), |next| next.range.start)
+    }
+
+    /// The direct subsections of `heading`, as the AST nests them.
+    fn subsections(&self, heading: usize) -> Vec<usize> {
+        let end = self.section_end(heading);
+        let mut min_level = usize::MAX;
+        let mut sections = vec![];
+        for (idx, sub) in
+            self.headings.iter().enumerate().skip(heading + 1)
+        {
+            if sub.range.start >= end {
+                break;
+            }
+            if sub.level <= min_level {
+                min_level = sub.level;
+                sections.push(idx);
+            }
+        }
+        sections
+    }
+
+    /// The direct subsection of `heading` named one of `names`, as
+    /// [`find_section`](crate::ankigen::deck::find_section) finds it.
+    fn find_section(
+        &self,
+        heading: usize,
+        names: &[&str],
+    ) -> Option<usize> {
+        self.subsections(heading).into_iter().find(|idx| {
+            names.iter().any(|name| {
+                self.headings[*idx].name.eq_ignore_ascii_case(name)
+            })
+        })
+    }
+
+    /// The notes of the deck or subdeck `heading` then those of its
+    /// subdecks, in the order of [`Deck::notes`].
+    fn note_headings(
+        &self,
+        heading: usize,
+        note_level: usize,
+    ) -> Vec<usize> {
+        let mut notes = vec![];
+        let mut subdecks = vec![];
+        for idx in self.subsections(heading) {
+            let section = &self.headings[idx];
+            if SETTINGS_SECTIONS
+                .iter()
+                .any(|name| section.name.eq_ignore_ascii_case(name))
+            {
+                continue;
+            }
+            match section.level < note_level {
+                true => subdecks.push(idx),
+                false => notes.push(idx),
+            }
+        }
+        for subdeck in subdecks {
+            notes.extend(self.note_headings(subdeck, note_level));
+        }
+        notes
+    }
+
+    fn items_in(
+        &self,
+        range: Range<usize>,
+        depth: usize,
+    ) -> impl Iterator<Item = &Item> {
+        self.items.iter().filter(move |item| {
+            item.depth == depth
+                && item.range.start >= range.start
+                && item.range.end <= range.end
+        })
+    }
+
+    /// The whitespace starting the line of `offset`.
+    fn indent_of(&self, offset: usize) -> &str {
+        let start = self.source[..offset]
+            .rfind('\n')
+            .map_or(0, |idx| idx + 1);
+        let line = &self.source[start..line_end(self.source, start)];
+        &line[..line.len() - line.trim_start().len()]
+    }
+
+    /// Lines of new `- key: value` items, each after a newline.
+    fn new_items(
+        &self,
+        indent: &str,
+        entries: &[(&str, String)],
+    ) -> String {
+        entries
+            .iter()
+            .map(|(key, value)| {
+                let value = item_value(key, value);
+                format!("{}{indent}- {key}: {value}", self.newline)
+            })
+            .collect()
+    }
+
+    /// Updates the values of the `- Autogen:` item, adding the
+    /// entries it lacks after its last one.
+    fn update_item(
+        &mut self,
+        autogen: &Item,
+        entries: &[(&str, String)],
+    ) {
+        let children: Vec<_> = self
+            .items_in(autogen.range.clone(), autogen.depth + 1)
+            .map(|item| item.range.clone())
+            .collect();
+        let mut missing = vec![];
+        for (key, raw) in entries {
+            let value = &item_value(key, raw);
+            let found = children
+                .iter()
+                .map(|child| item_entry(self.source, child.start))
+                .find(|(found, _)| key_is(self.source, found, key));
+            match found {
+                Some((_, range))
+                    if &self.source[range.clone()] == value => {}
+                Some((key_range, range)) if range.is_empty() => {
+                    self.edits.push((
+                        key_range.end + 1..range.end,
+                        format!(" {value}"),
+                    ))
+                }
+                Some((_, range)) => {
+                    self.edits.push((range, value.clone()))
+                }
+                None => missing.push((*key, raw.clone())),
+            }
+        }
+        if missing.is_empty() {
+            return;
+        }
+
+        let (indent, after) = match children.last() {
+            Some(last) => (
+                self.indent_of(last.start).to_string(),
+                content_end(self.source, last),
+            ),
+            None => (
+                format!(
+                    "{}    ",
+                    self.indent_of(autogen.range.start)
+                ),
+                line_end(self.source, autogen.range.start),
+            ),
+        };
+        let after = self.source[..after].trim_end_matches('\r').len();
+        let lines = self.new_items(&indent, &missing);
+        self.edits.push((after..after, lines));
+    }
+
+    /// The metadata block right under `heading`, or for the deck
+    /// the front matter of the file.
+    fn block_of(&self, heading: usize) -> Option<Block> {
+        let start = match heading {
+            0 => 0,
+            _ => self.headings[heading].range.end,
+        };
+        let end = self
+            .headings
+            .get(heading + 1)
+            .map_or(self.source.len(), |next| next.range.start);
+        self.blocks
+            .iter()
+            .find(|block| {
+                block.range.start >= start && block.range.end <= end
+            })
+            .cloned()
+    }
+
+    /// Records the entries in the `autogen` mapping or `[autogen]`
+    /// table of a metadata block, adding it at the end of the block
+    /// when `create` is set. Returns whether the block has one.
+    fn update_block(
+        &mut self,
+        block: &Block,
+        entries: &[(&str, String)],
+        create: bool,
+    ) -> bool {
+        let newline = self.newline;
+        let format = block.format;
+        let content_start =
+            line_end(self.source, block.range.start) + 1;
+        let closing = self.source
+            [..content_end(self.source, &block.range)]
+            .rfind('\n')
+            .map_or(block.range.end, |idx| idx + 1);
+        let lines: Vec<_> =
+            lines(self.source, content_start..closing).collect();
+
+        let is_header = |line: &str| match format {
+            FrontMatterFormat::Yaml => {
+                !line.starts_with(char::is_whitespace)
+                    && block_entry(format, line).is_some_and(
+                        |(key, value)| {
+                            value.is_empty()
+                                && block_key_is(&line[key], AUTOGEN)
+                        },
+                    )
+            }
+            FrontMatterFormat::Toml => line
+                .trim()
+                .strip_prefix('[')
+                .and_then(|line| line.strip_suffix(']'))
+                .is_some_and(|name| block_key_is(name, AUTOGEN)),
+        };
+        let ends_table = |line: &str| match format {
+            FrontMatterFormat::Yaml => {
+                !line.is_empty()
+                    && !line.starts_with(char::is_whitespace)
+            }
+            FrontMatterFormat::Toml => {
+                line.trim_start().starts_with('[')
+            }
+        };
+
+        let Some(header) =
+            lines.iter().position(|(_, line)| is_header(line))
+        else {
+            if !create {
+                return false;
+            }
+            let (indent, before) = match format {
+                FrontMatterFormat::Yaml => ("  ", String::new()),
+                FrontMatterFormat::Toml => ("", newline.to_string()),
+            };
+            let table = match format {
+                FrontMatterFormat::Yaml => "autogen:",
+                FrontMatterFormat::Toml => "[autogen]",
+            };
+            let mut text = format!("{before}{table}{newline}");
+            for (key, value) in entries {
+                text.push_str(
+                    &self.block_line(format, indent, key, value),
+                );
+            }
+            self.edits.push((closing..closing, text));
+            return true;
+        };
+
+        let children: Vec<_> = lines[header + 1..]
+            .iter()
+            .take_while(|(_, line)| !ends_table(line))
+            .filter(|(_, line)| !line.trim().is_empty())
+            .collect();
+        let mut missing = String::new();
+        let indent = children.last().map_or(
+            match format {
+                FrontMatterFormat::Yaml => "  ",
+                FrontMatterFormat::Toml => "",
+            },
+            |(_, line)| &line[..line.len() - line.trim_start().len()],
+        );
+        for (key, value) in entries {
+            let written = block_value(format, key, value);
+            let found = children.iter().find_map(|(start, line)| {
+                let (key_range, value_range) =
+                    block_entry(format, line)?;
+                block_key_is(&line[key_range], key).then(|| {
+                    (
+                        start + value_range.start,
+                        start + value_range.end,
+                    )
+                })
+            });
+            match found {
+                Some((start, end))
+                    if self.source[start..end] == written => {}
+                Some((start, end)) => {
+                    let separator = match start == end {
+                        true => " ",
+                        false => "",
+                    };
+                    self.edits.push((
+                        start..end,
+                        format!("{separator}{written}"),
+                    ))
+                }
+                None => missing.push_str(
+                    &self.block_line(format, indent, key, value),
+                ),
+            }
+        }
+        if !missing.is_empty() {
+            let (start, line) =
+                children.last().copied().unwrap_or(&lines[header]);
+            let after = start + line.len() + newline.len();
+            self.edits.push((after..after, missing));
+        }
+        true
+    }
+
+    /// A `key: value` or `key = value` line of front matter, with its
+    /// line break.
+    fn block_line(
+        &self,
+        format: FrontMatterFormat,
+        indent: &str,
+        key: &str,
+        value: &str,
+    ) -> String {
+        let key = key.replace(' ', "_");
+        let value = block_value(format, &key, value);
+        let separator = match format {
+            FrontMatterFormat::Yaml => ":",
+            FrontMatterFormat::Toml => " =",
+        };
+        format!("{indent}{key}{separator} {value}{}", self.newline)
+    }
+
+    fn autogen_list(&self, entries: &[(&str, String)]) -> String {
+        format!("- {AUTOGEN}:{}", self.new_items("    ", entries))
+    }
+
+    /// Records `autogen` where the deck or note of `heading` keeps
+    /// it: the `autogen` of its front matter, else the `- Autogen:`
+    /// item of its metadata section. When it has neither, the ids
+    /// go in its front matter if it has some, else in its metadata
+    /// section, which is created as needed.
+    fn update(
+        &mut self,
+        heading: usize,
+        names: &[&str],
+        autogen: &Autogen,
+    ) {
+        let entries = autogen.entries();
+        if entries.is_empty() {
+            return;
+        }
+        let newline = self.newline;
+
+        let block = self.block_of(heading);
+        if let Some(block) = &block {
+            if self.update_block(block, &entries, false) {
+                return;
+            }
+        }
+        let section = self.find_section(heading, names);
+        let has_list = section.is_some_and(|section| {
+            let range = self.headings[section].range.end
+                ..self.section_end(section);
+            self.items_in(range, 1).any(|item| {
+                let (key, _) =
+                    item_entry(self.source, item.range.start);
+                key_is(self.source, &key, AUTOGEN)
+            })
+        });
+        if let (Some(block), false) = (&block, has_list) {
+            self.update_block(block, &entries, true);
+            return;
+        }
+
+        let Some(metadata) = section else {
+            let end = content_end(
+                self.source,
+                &(self.headings[heading].range.start
+                    ..self.section_end(heading)),
+            );
+            let section = format!(
+                "{newline}{newline}{} {}{newline}{newline}{}",
+                "#".repeat(self.headings[heading].level + 1),
+                names[0],
+                self.autogen_list(&entries)
+            );
+            self.edits.push((end..end, section));
+            return;
+        };
+
+        let range = self.headings[metadata].range.end
+            ..self.section_end(metadata);
+        let autogen_item = self
+            .items_in(range.clone(), 1)
+            .find(|item| {
+                let (key, _) =
+                    item_entry(self.source, item.range.start);
+                key_is(self.source, &key, AUTOGEN)
+            })
+            .cloned();
+        if let Some(item) = autogen_item {
+            self.update_item(&item, &entries);
+            return;
+        }
+
+        let end = content_end(self.source, &range);
+        let ends_list = self
+            .items_in(range.clone(), 1)
+            .any(|item| content_end(self.source, &item.range) == end);
+        let separator = match ends_list {
+            true => newline.to_string(),
+            false => format!("{newline}{newline}"),
+        };
+        let list =
+            format!("{separator}{}", self.autogen_list(&entries));
+        self.edits.push((end..end, list));
+    }
+}
+
+/// Records the generated ids of a deck file in its markdown: `deck`
+/// under the deck's `## Deck metadata` or `## Metadata`, and the
+/// ids of each note under the `Metadata` section of its heading, in
+/// the order of [`Deck::notes`].
+///
+/// Only the values of `Autogen` entries change, missing entries and
+/// sections are added after the existing content. Every other byte
+/// of `source` is kept, so the file diffs cleanly.
+pub fn write_autogen(
+    source: &str,
+    deck: &Autogen,
+    notes: &[Autogen],
+) -> String {
+    let (headings, items, blocks) = scan(source);
+    let mut writer = Writer {
+        source,
+        headings,
+        items,
+        blocks,
+        newline: match source.contains("\r\n") {
+            true => "\r\n",
+            false => "\n",
+        },
+        edits: vec![],
+    };
+
+    let h1s: Vec<_> = (0..writer.headings.len())
+        .filter(|idx| writer.headings[*idx].level == 1)
+        .collect();
+    let Some((first, rest)) = h1s.split_first() else {
+        return source.to_string();
+    };
+    let note_level = Deck::from_markdown(source)
+        .map_or(DEFAULT_NOTE_LEVEL, |deck| deck.metadata.note_level);
+    let note_headings = match note_level {
+        DEFAULT_NOTE_LEVEL => rest.to_vec(),
+        note_level => writer.note_headings(*first, note_level),
+    };
+    writer.update(*first, &["Deck metadata", "Metadata"], deck);
+    for (heading, autogen) in note_headings.into_iter().zip(notes) {
+        writer.update(heading, &["Metadata"], autogen);
+    }
+
+    let mut result = source.to_string();
+    let mut edits = writer.edits;
+    edits.sort_by_key(|(range, _)| range.start);
+    for (range, text) in edits.into_iter().rev() {
+        result.replace_range(range, &text);
+    }
+    result
+}
+
+/// [`write_autogen`] on the file at `path`, which is only written
+/// when an id changed. Returns whether it was.
+pub fn write_autogen_file(
+    path: &Path,
+    deck: &Autogen,
+    notes: &[Autogen],
+) -> std::io::Result<bool> {
+    let source = std::fs::read_to_string(path)?;
+    let updated = write_autogen(&source, deck, notes);
+    if updated == source {
+        return Ok(false);
+    }
+    std::fs::write(path, updated)?;
+    Ok(true)
+}
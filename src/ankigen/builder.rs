@@ -0,0 +1,320 @@
+use std::collections::HashSet;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::ankigen::anki2::{
+    CollectionData, DEFAULT_ID, SCHEMA_VERSION,
+};
+use crate::ankigen::cards::{generate_cards, CardError};
+use crate::ankigen::db_model::card::Card;
+use crate::ankigen::db_model::collection::Collection;
+use crate::ankigen::db_model::deck::{Deck, DeckConfig};
+use crate::ankigen::db_model::model::Model;
+use crate::ankigen::db_model::note::Note;
+
+const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+
+/// Source of the time written in a collection.
+pub trait Clock {
+    /// Milliseconds since the Unix epoch.
+    fn now_millis(&self) -> i64;
+}
+
+/// The time of the system.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now_millis(&self) -> i64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |time| time.as_millis() as i64)
+    }
+}
+
+/// A clock stopped at a time, in milliseconds, so that building the
+/// same collection twice gives the same ids and timestamps.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FixedClock(pub i64);
+
+impl Clock for FixedClock {
+    fn now_millis(&self) -> i64 {
+        self.0
+    }
+}
+
+/// An entry the builder cannot add.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BuilderError {
+    /// The id chosen by hand was already given to another entry.
+    IdInUse {
+        id: i64,
+    },
+    /// The model of the note was not added first.
+    UnknownModel {
+        id: usize,
+    },
+    /// The deck of the note was not added first.
+    UnknownDeck {
+        id: i64,
+    },
+    Card(CardError),
+}
+
+impl std::fmt::Display for BuilderError {
+    fn fmt(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+    ) -> std::fmt::Result {
+        match self {
+            Self::IdInUse { id } => {
+                write!(f, "id {id} is already used by another entry")
+            }
+            Self::UnknownModel { id } => {
+                write!(f, "no model with id {id}")
+            }
+            Self::UnknownDeck { id } => {
+                write!(f, "no deck with id {id}")
+            }
+            Self::Card(error) => write!(f, "{error}"),
+        }
+    }
+}
+
+impl std::error::Error for BuilderError {}
+
+impl From<CardError> for BuilderError {
+    fn from(error: CardError) -> Self {
+        Self::Card(error)
+    }
+}
+
+/// Assembles a [`Collection`] with its notes and cards, giving every
+/// entry without an id a millisecond id of its own and every entry a
+/// modification time from the same [`Clock`].
+///
+/// Ids are taken from the clock, one millisecond after the last one
+/// when the clock did not move, and never reuse an id added by hand,
+/// like those [`note_type_id`](crate::ankigen::note_type::note_type_id)
+/// derives from names.
+pub struct CollectionBuilder {
+    clock: Box<dyn Clock>,
+    last_id: i64,
+    used_ids: HashSet<i64>,
+    collection: Collection,
+    notes: Vec<Note>,
+    cards: Vec<Card>,
+}
+
+impl Default for CollectionBuilder {
+    fn default() -> Self {
+        Self {
+            clock: Box::new(SystemClock),
+            last_id: 0,
+            used_ids: HashSet::new(),
+            collection: Collection::new(),
+            notes: vec![],
+            cards: vec![],
+        }
+    }
+}
+
+impl CollectionBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = Box::new(clock);
+        self
+    }
+
+    /// The time of the clock, in seconds as in the `mod` of notes,
+    /// cards, models and decks.
+    pub fn now(&self) -> i64 {
+        self.clock.now_millis() / 1000
+    }
+
+    /// A new millisecond id, later than any id given so far.
+    pub fn next_id(&mut self) -> i64 {
+        let mut id = self.clock.now_millis().max(self.last_id + 1);
+        while self.used_ids.contains(&id) {
+            id += 1;
+        }
+        self.last_id = id;
+        self.used_ids.insert(id);
+        id
+    }
+
+    /// Keeps an id chosen by hand from being given again. Fails if
+    /// an entry already has it.
+    fn reserve(&mut self, id: i64) -> Result<(), BuilderError> {
+        match self.used_ids.insert(id) {
+            true => Ok(()),
+            false => Err(BuilderError::IdInUse { id }),
+        }
+    }
+
+    /// Registers `model` in `models`, replacing one of the same id,
+    /// and returns its id, a new one when it has none. Fails if the
+    /// id is one of an entry other than a model.
+    pub fn add_model(
+        &mut self,
+        mut model: Model,
+    ) -> Result<u64, BuilderError> {
+        let replaces = self
+            .collection
+            .models
+            .iter()
+            .any(|(id, _)| *id as u64 == model.model_id);
+        match model.model_id {
+            0 => model.model_id = self.next_id() as u64,
+            _ if replaces => (),
+            id => self.reserve(id as i64)?,
+        }
+        model.modification = self.now();
+        let id = model.model_id as usize;
+        let models = &mut self.collection.models;
+        models.retain(|(other, _)| *other != id);
+        models.push((id, model));
+        Ok(id as u64)
+    }
+
+    /// Registers `deck` in `decks`, replacing one of the same id,
+    /// and returns its id, a new one when it has none. Fails if the
+    /// id is one of an entry other than a deck.
+    pub fn add_deck(
+        &mut self,
+        mut deck: Deck,
+    ) -> Result<i64, BuilderError> {
+        let replaces = self
+            .collection
+            .decks
+            .iter()
+            .any(|(id, _)| *id as i64 == deck.id);
+        match deck.id {
+            0 => deck.id = self.next_id(),
+            _ if replaces => (),
+            id => self.reserve(id)?,
+        }
+        deck.modified = self.now();
+        let id = deck.id as usize;
+        let decks = &mut self.collection.decks;
+        decks.retain(|(other, _)| *other != id);
+        decks.push((id, deck));
+        Ok(id as i64)
+    }
+
+    /// Registers `config` in `dconf`, replacing one of the same id,
+    /// and returns its id, a new one when it has none. Fails if the
+    /// id is one of an entry other than a config.
+    pub fn add_deck_config(
+        &mut self,
+        mut config: DeckConfig,
+    ) -> Result<usize, BuilderError> {
+        let replaces = self
+            .collection
+            .deck_configs
+            .iter()
+            .any(|(id, _)| Some(*id) == config.id);
+        let id = match config.id {
+            Some(id) if replaces => id,
+            Some(id) => {
+                self.reserve(id as i64)?;
+                id
+            }
+            None => self.next_id() as usize,
+        };
+        config.id = Some(id);
+        config.modified = self.now() as usize;
+        let configs = &mut self.collection.deck_configs;
+        configs.retain(|(other, _)| *other != id);
+        configs.push((id, config));
+        Ok(id)
+    }
+
+    /// Adds `note` to the deck `deck_id`, an added deck or the
+    /// `Default` one, with the cards its model gives it, and returns
+    /// the id of the note.
+    ///
+    /// The note gets an id if it has none, the cards always get new
+    /// ones. The cards come in the `due` order of the note, the
+    /// `nextPos` of the collection, which then moves on. An id of
+    /// the note that is already used is an error.
+    pub fn add_note(
+        &mut self,
+        mut note: Note,
+        deck_id: i64,
+    ) -> Result<i64, BuilderError> {
+        let has_deck = deck_id == DEFAULT_ID as i64
+            || self
+                .collection
+                .decks
+                .iter()
+                .any(|(id, _)| *id as i64 == deck_id);
+        if !has_deck {
+            return Err(BuilderError::UnknownDeck { id: deck_id });
+        }
+        let model_idx = self
+            .collection
+            .models
+            .iter()
+            .position(|(id, _)| *id == note.model_id)
+            .ok_or(BuilderError::UnknownModel {
+                id: note.model_id,
+            })?;
+        match note.id {
+            0 => note.id = self.next_id(),
+            id => self.reserve(id)?,
+        }
+        note.modified = self.now();
+
+        let model = &self.collection.models[model_idx].1;
+        let due = self.collection.config.next_position;
+        let cards =
+            generate_cards(&note, model, deck_id as usize, due, 0)?;
+        for mut card in cards {
+            card.id = self.next_id();
+            self.cards.push(card);
+        }
+        self.collection.config.next_position = due + 1;
+
+        let id = note.id;
+        self.notes.push(note);
+        Ok(id)
+    }
+
+    /// The collection, created and modified now.
+    ///
+    /// `crt` is the start of the current day in UTC, the day Anki
+    /// counts due days from; `mod` and `scm` are in milliseconds. The
+    /// current deck and model are the first ones added, unless set.
+    pub fn build(mut self) -> CollectionData {
+        let now = self.clock.now_millis();
+        let collection = &mut self.collection;
+        collection.id = 1;
+        collection.created =
+            now / 1000 - (now / 1000) % SECONDS_PER_DAY;
+        collection.modified = now;
+        collection.scheme_mod_time = now;
+        collection.version = SCHEMA_VERSION;
+
+        let config = &mut collection.config;
+        if config.current_model.is_empty() {
+            if let Some((id, _)) = collection.models.first() {
+                config.current_model = id.to_string();
+            }
+        }
+        if config.active_decks.is_empty() {
+            if let Some((id, _)) = collection.decks.first() {
+                config.current_deck = *id as i64;
+                config.active_decks = vec![*id as i64];
+            }
+        }
+
+        CollectionData {
+            collection: self.collection,
+            notes: self.notes,
+            cards: self.cards,
+        }
+    }
+}
@@ -0,0 +1,155 @@
+use std::collections::BTreeSet;
+
+use crate::ankigen::db_model::card::Card;
+use crate::ankigen::db_model::model::{Model, ModelType};
+use crate::ankigen::db_model::note::Note;
+use crate::ankigen::template::{
+    cloze_fields, parse_template, renders_with_fields, TemplateError,
+};
+use crate::ankigen::util::strip_html;
+
+/// A template of the model that could not be parsed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CardError {
+    pub model: String,
+    pub template: String,
+    pub error: TemplateError,
+}
+
+impl std::fmt::Display for CardError {
+    fn fmt(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+    ) -> std::fmt::Result {
+        write!(
+            f,
+            "template {} of {}: {}",
+            self.template, self.model, self.error
+        )
+    }
+}
+
+impl std::error::Error for CardError {}
+
+/// A field is empty when nothing but whitespace is left once the
+/// HTML is stripped, e.g. `<br>` or `&nbsp;`.
+pub fn field_is_empty(field: &str) -> bool {
+    strip_html(field).trim().is_empty()
+}
+
+/// The numbers `N` of the `{{cN::...}}` deletions in `text`.
+pub fn cloze_ordinals(text: &str) -> BTreeSet<u64> {
+    let mut ordinals = BTreeSet::new();
+    let mut rest = text;
+    while let Some(idx) = rest.find("{{c") {
+        rest = &rest[idx + "{{c".len()..];
+        let digits = rest
+            .find(|chr: char| !chr.is_ascii_digit())
+            .unwrap_or(rest.len());
+        if rest[digits..].starts_with("::") {
+            if let Ok(ordinal) = rest[..digits].parse::<u64>() {
+                if ordinal > 0 {
+                    ordinals.insert(ordinal);
+                }
+            }
+        }
+    }
+    ordinals
+}
+
+fn field_value<'a>(
+    note: &'a Note,
+    model: &Model,
+    name: &str,
+) -> &'a str {
+    model
+        .fields
+        .iter()
+        .find(|field| field.name == name)
+        .and_then(|field| note.fields.get(field.ordinal))
+        .map_or("", String::as_str)
+}
+
+/// Template ordinals of the cards `note` gets.
+///
+/// A front/back note gets a card for every template whose question
+/// shows one of its non-empty fields. A cloze note gets a card for
+/// every distinct `{{cN::...}}` in the fields its template shows with
+/// the `cloze` filter, card `cN` has ordinal `N - 1`. Like in Anki,
+/// a cloze note without deletions still gets the card of `c1`.
+pub fn card_ordinals(
+    note: &Note,
+    model: &Model,
+) -> Result<Vec<u64>, CardError> {
+    let parse = |template_idx: usize| {
+        let template = &model.templates[template_idx];
+        parse_template(&template.question_format).map_err(|error| {
+            CardError {
+                model: model.name.clone(),
+                template: template.name.clone(),
+                error,
+            }
+        })
+    };
+
+    match model.model_type {
+        ModelType::FrontBack => {
+            let nonempty = |name: &str| {
+                !field_is_empty(field_value(note, model, name))
+            };
+            let mut ordinals = vec![];
+            for (idx, template) in model.templates.iter().enumerate()
+            {
+                if renders_with_fields(&parse(idx)?, &nonempty) {
+                    ordinals.push(template.ordinal as u64);
+                }
+            }
+            Ok(ordinals)
+        }
+        ModelType::Cloze => {
+            if model.templates.is_empty() {
+                return Ok(vec![]);
+            }
+            let nodes = parse(0)?;
+            let ordinals = cloze_fields(&nodes)
+                .into_iter()
+                .flat_map(|name| {
+                    cloze_ordinals(field_value(note, model, name))
+                })
+                .collect::<BTreeSet<_>>();
+            if ordinals.is_empty() {
+                return Ok(vec![0]);
+            }
+            Ok(ordinals
+                .into_iter()
+                .map(|ordinal| ordinal - 1)
+                .collect())
+        }
+    }
+}
+
+/// New cards of `note` in the deck `deck_id`, with ids counting up
+/// from `first_id`. All of them share the `due` position of the note,
+/// so its cards are introduced together, as in Anki.
+pub fn generate_cards(
+    note: &Note,
+    model: &Model,
+    deck_id: usize,
+    due: i64,
+    first_id: i64,
+) -> Result<Vec<Card>, CardError> {
+    Ok(card_ordinals(note, model)?
+        .into_iter()
+        .enumerate()
+        .map(|(idx, ordinal)| {
+            Card::new_card(
+                first_id + idx as i64,
+                note.id as usize,
+                deck_id,
+                ordinal,
+                note.modified,
+                due,
+            )
+        })
+        .collect())
+}
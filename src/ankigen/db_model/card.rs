@@ -25,6 +25,36 @@ pub enum CardQueue {
     Preview = 4,
 }
 
+impl TryFrom<i64> for CardType {
+    type Error = &'static str;
+    fn try_from(value: i64) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(CardType::New),
+            1 => Ok(CardType::Learning),
+            2 => Ok(CardType::Review),
+            3 => Ok(CardType::Relearning),
+            _ => Err("Invalid CardType value"),
+        }
+    }
+}
+
+impl TryFrom<i64> for CardQueue {
+    type Error = &'static str;
+    fn try_from(value: i64) -> Result<Self, Self::Error> {
+        match value {
+            -3 => Ok(CardQueue::UserSuspended),
+            -2 => Ok(CardQueue::Buried),
+            -1 => Ok(CardQueue::Suspended),
+            0 => Ok(CardQueue::New),
+            1 => Ok(CardQueue::Learning),
+            2 => Ok(CardQueue::Review),
+            3 => Ok(CardQueue::InLearning),
+            4 => Ok(CardQueue::Preview),
+            _ => Err("Invalid CardQueue value"),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize_repr, Deserialize_repr, PartialEq)]
 #[repr(u8)]
 pub enum CardFlag {
@@ -125,6 +155,37 @@ impl Card {
             data: String::new(),
         }
     }
+
+    /// A card that was never studied, shown in the order of `due`
+    /// among the new cards of its deck.
+    pub fn new_card(
+        id: i64,
+        note_id: usize,
+        deck_id: usize,
+        ordinal: u64,
+        modified: i64,
+        due: i64,
+    ) -> Self {
+        Self::new(
+            id,
+            note_id,
+            deck_id,
+            ordinal,
+            modified,
+            -1, // not synced yet
+            CardType::New,
+            CardQueue::New,
+            due,
+            0,
+            0,
+            0,
+            0,
+            0,
+            0,
+            0,
+            CardFlag::Null,
+        )
+    }
 }
 
 impl Table for Card {
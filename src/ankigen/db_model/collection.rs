@@ -1,7 +1,7 @@
 use serde::de::{self, MapAccess, Visitor};
 use serde::ser::SerializeMap;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
-use serde_json;
+use serde_json::{self, Map, Value};
 use serde_repr::{Deserialize_repr, Serialize_repr};
 
 use crate::ankigen::db_model::deck::{Deck, DeckConfig};
@@ -15,7 +15,11 @@ pub enum NewSpread {
     NewCardsFirst = 2,
 }
 
+/// The `conf` of the collection. As with [`DeckConfig`], `other`
+/// keeps the settings without a field, like `schedVer` or
+/// `creationOffset`.
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
 pub struct CollectionConfig {
     #[serde(rename = "curDeck")]
     pub current_deck: i64,
@@ -31,7 +35,12 @@ pub struct CollectionConfig {
     pub estimated_times: bool,
     #[serde(rename = "dueCounts")]
     pub due_counts: bool,
-    #[serde(rename = "curModel")]
+    // Id of the model, a string or a number depending on the Anki
+    // version
+    #[serde(
+        rename = "curModel",
+        deserialize_with = "deserialize_current_model"
+    )]
     pub current_model: String,
     #[serde(rename = "nextPos")]
     pub next_position: i64,
@@ -49,6 +58,8 @@ pub struct CollectionConfig {
     pub last_unburied: Option<i64>,
     #[serde(rename = "activeCols")]
     pub active_columns: Option<Vec<String>>,
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
 }
 
 impl CollectionConfig {
@@ -70,8 +81,26 @@ impl CollectionConfig {
             new_bury: true,
             last_unburied: None,
             active_columns: None,
+            other: Map::new(),
         }
     }
+
+}
+
+fn deserialize_current_model<'de, D>(
+    deserializer: D,
+) -> Result<String, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match Value::deserialize(deserializer)? {
+        Value::String(model) => Ok(model),
+        Value::Number(model) => Ok(model.to_string()),
+        Value::Null => Ok(String::new()),
+        model => Err(de::Error::custom(format!(
+            "invalid curModel {model}"
+        ))),
+    }
 }
 
 impl Default for CollectionConfig {
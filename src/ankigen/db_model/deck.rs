@@ -1,4 +1,5 @@
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::{Map, Value};
 use serde_repr::{Deserialize_repr, Serialize_repr};
 
 use super::table::Table;
@@ -63,27 +64,42 @@ impl Default for Deck {
     }
 }
 
+#[derive(Serialize_repr, Deserialize_repr, Debug, Clone, PartialEq)]
+#[repr(u8)]
+pub enum LeechAction {
+    Suspend = 0,
+    TagOnly = 1,
+}
+
+/// The `lapse` settings of a [`DeckConfig`], for forgotten cards.
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
 pub struct LapseConfig {
-    pub delays: Vec<usize>,
+    pub delays: Vec<f64>, // relearning steps, in minutes
     #[serde(rename = "leechAction")]
-    pub leech_action: usize,
+    pub leech_action: LeechAction,
     #[serde(rename = "leechFails")]
     pub leech_fails: usize,
     #[serde(rename = "minInt")]
     pub min_interval: usize,
+    // factor of the interval after a lapse
     #[serde(rename = "mult")]
-    pub interval_increase: usize,
+    pub interval_increase: f64,
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
 }
 
 impl LapseConfig {
+    /// Anki's defaults: one 10 minute relearning step, leeches
+    /// tagged at 8 lapses.
     pub fn new() -> Self {
         Self {
-            delays: vec![0],
-            leech_action: 0,
-            leech_fails: 0,
-            min_interval: 0,
-            interval_increase: 0,
+            delays: vec![10.0],
+            leech_action: LeechAction::TagOnly,
+            leech_fails: 8,
+            min_interval: 1,
+            interval_increase: 0.0,
+            other: Map::new(),
         }
     }
 }
@@ -101,30 +117,39 @@ pub enum NewCardOrder {
     Due = 1,
 }
 
+/// The `new` settings of a [`DeckConfig`].
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
 pub struct NewConfig {
     pub bury: bool,
-    pub delays: Vec<usize>,
+    pub delays: Vec<f64>, // learning steps, in minutes
     #[serde(rename = "initialFactor")]
     pub initial_factor: usize,
+    // graduating interval, easy interval and an unused one, in days
     #[serde(rename = "ints")]
     pub intervals: Vec<usize>,
     pub order: NewCardOrder,
     #[serde(rename = "perDay")]
     pub per_day: usize,
     pub separate: bool,
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
 }
 
 impl NewConfig {
+    /// Anki's defaults: 20 new cards a day, learning steps of 1 and
+    /// 10 minutes, graduating after 1 day, or 4 days when answered
+    /// easy.
     pub fn new() -> Self {
         Self {
             bury: false,
-            delays: vec![0],
-            initial_factor: 0,
-            intervals: vec![0],
-            order: NewCardOrder::Random,
-            per_day: 0,
-            separate: false,
+            delays: vec![1.0, 10.0],
+            initial_factor: 2500,
+            intervals: vec![1, 4, 7],
+            order: NewCardOrder::Due,
+            per_day: 20,
+            separate: true,
+            other: Map::new(),
         }
     }
 }
@@ -135,14 +160,17 @@ impl Default for NewConfig {
     }
 }
 
+/// The `rev` settings of a [`DeckConfig`]. Its `other` holds keys
+/// like `hardFactor`.
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
 pub struct ReviewConfig {
     pub bury: bool,
     #[serde(rename = "ease4")]
-    pub ease_factor: usize,
-    pub fuzz: usize,
+    pub ease_factor: f64, // easy bonus
+    pub fuzz: f64,
     #[serde(rename = "ivlFct")]
-    pub interval_factor: usize,
+    pub interval_factor: f64,
     #[serde(rename = "maxIvl")]
     pub max_interval: usize,
     // Unused
@@ -150,18 +178,23 @@ pub struct ReviewConfig {
     pub min_space: usize,
     #[serde(rename = "perDay")]
     pub cards_daily: usize,
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
 }
 
 impl ReviewConfig {
+    /// Anki's defaults: 200 reviews a day, intervals of at most 100
+    /// years.
     pub fn new() -> Self {
         Self {
             bury: false,
-            ease_factor: 0,
-            fuzz: 0,
-            interval_factor: 0,
-            max_interval: 0,
-            min_space: 0,
-            cards_daily: 0,
+            ease_factor: 1.3,
+            fuzz: 0.05,
+            interval_factor: 1.0,
+            max_interval: 36500,
+            min_space: 1,
+            cards_daily: 200,
+            other: Map::new(),
         }
     }
 }
@@ -178,7 +211,14 @@ impl Table for Deck {
     }
 }
 
+/// A deck option preset of `dconf`.
+///
+/// Keys without a field of their own, like `desiredRetention`, are
+/// kept in `other` and written back unchanged, so that settings
+/// from newer Anki versions survive a round trip. The `new`,
+/// `lapse` and `rev` sections do the same in their own `other`.
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
 pub struct DeckConfig {
     pub autoplay: bool,
     #[serde(rename = "dyn")]
@@ -197,26 +237,66 @@ pub struct DeckConfig {
     pub replay_question: bool,
     #[serde(rename = "rev")]
     pub review_config: ReviewConfig,
+    // 0 or 1 in Anki's JSON
+    #[serde(
+        serialize_with = "DeckConfig::serialize_timer",
+        deserialize_with = "DeckConfig::deserialize_timer"
+    )]
     pub timer: bool,
     #[serde(rename = "usn")]
-    pub update_seq_number: usize,
+    pub update_seq_number: i64,
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
 }
 
 impl DeckConfig {
+    /// An options group with the settings of Anki's `Default` group.
     pub fn new(name: String) -> Self {
         Self {
-            autoplay: false,
-            filtered: None,
+            autoplay: true,
+            filtered: Some(false),
             id: None,
             lapse_config: LapseConfig::new(),
-            max_taken: 0,
+            max_taken: 60,
             modified: 0,
             name,
             new_config: NewConfig::new(),
-            replay_question: false,
+            replay_question: true,
             review_config: ReviewConfig::new(),
             timer: false,
             update_seq_number: 0,
+            other: Map::new(),
+        }
+    }
+
+    fn serialize_timer<S>(
+        timer: &bool,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_u8(*timer as u8)
+    }
+
+    fn deserialize_timer<'de, D>(
+        deserializer: D,
+    ) -> Result<bool, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        match Value::deserialize(deserializer)? {
+            Value::Bool(timer) => Ok(timer),
+            Value::Number(timer) => Ok(timer.as_f64() != Some(0.0)),
+            timer => Err(serde::de::Error::custom(format!(
+                "invalid timer {timer}"
+            ))),
         }
     }
 }
+
+impl Default for DeckConfig {
+    fn default() -> Self {
+        Self::new("Default".to_string())
+    }
+}
@@ -48,6 +48,8 @@ pub struct ModelTemplate {
     #[serde(rename = "did")]
     pub default_deck_id: Option<u64>, // default deck id, default None
     pub name: String, // name of the template
+    #[serde(rename = "ord", default)]
+    pub ordinal: usize, // ordinal of the template, see cards.ord
     #[serde(rename = "qfmt")]
     pub question_format: String, // question format
 }
@@ -60,6 +62,7 @@ impl ModelTemplate {
             browser_question_format: String::new(),
             default_deck_id: None,
             name,
+            ordinal: 0,
             question_format,
         }
     }
@@ -175,7 +178,14 @@ impl Model {
             req: Self::default_req(),
             sort_field_index,
             tags: Self::default_tags(),
-            templates,
+            templates: templates
+                .into_iter()
+                .enumerate()
+                .map(|(ordinal, template)| ModelTemplate {
+                    ordinal,
+                    ..template
+                })
+                .collect(),
             model_type,
             update_seq_number: Self::default_usn(),
             version: Self::default_version(),
@@ -1,6 +1,12 @@
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+use crate::ankigen::db_model::model::Model;
+use crate::ankigen::util::{field_checksum, strip_html};
+
+/// Separator between the levels of a hierarchical tag, as in `lang::french::verbs`.
+pub const TAG_SEPARATOR: &str = "::";
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 pub struct NoteTag {
     pub name: String,
 }
@@ -15,12 +21,65 @@ impl NoteTag {
             return Err("Tag name cannot contain spaces".to_string());
         } else if name.len() > 255 {
             return Err("Tag name cannot be longer than 255 characters".to_string());
+        } else if name.split(TAG_SEPARATOR).any(str::is_empty) {
+            return Err("Tag name cannot have an empty level".to_string());
         }
 
         Ok(NoteTag {
             name: name.to_string(),
         })
     }
+
+    /// The levels of the tag, from the top: `lang`, `french` and `verbs` for
+    /// `lang::french::verbs`.
+    pub fn levels(&self) -> impl Iterator<Item = &str> {
+        self.name.split(TAG_SEPARATOR)
+    }
+
+    /// The tag one level up, `lang::french` for `lang::french::verbs`.
+    pub fn parent(&self) -> Option<NoteTag> {
+        self.name.rsplit_once(TAG_SEPARATOR).map(|(parent, _)| NoteTag {
+            name: parent.to_string(),
+        })
+    }
+
+    /// Whether the tag is `ancestor` or below it. Anki compares tags
+    /// case-insensitively.
+    pub fn is_under(&self, ancestor: &NoteTag) -> bool {
+        let name = self.name.to_lowercase();
+        let ancestor = ancestor.name.to_lowercase();
+        name == ancestor || name.starts_with(&format!("{ancestor}{TAG_SEPARATOR}"))
+    }
+}
+
+impl std::fmt::Display for NoteTag {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+/// The `notes.tags` column: the tag names separated by spaces, with a space at
+/// either end as Anki writes it.
+pub fn tags_to_string(tags: &[NoteTag]) -> String {
+    match tags.is_empty() {
+        true => String::new(),
+        false => format!(
+            " {} ",
+            tags.iter()
+                .map(|tag| tag.name.as_str())
+                .collect::<Vec<_>>()
+                .join(" ")
+        ),
+    }
+}
+
+/// The tags of a `notes.tags` column.
+pub fn tags_from_str(tags: &str) -> Vec<NoteTag> {
+    tags.split_whitespace()
+        .map(|name| NoteTag {
+            name: name.to_string(),
+        })
+        .collect()
 }
 
 impl Default for NoteTag {
@@ -42,12 +101,16 @@ pub struct Note {
     pub modified: i64, // time in milliseconds when the note was last modified
     #[serde(rename = "usn")]
     pub update_seq_number: i64,
-    #[serde(default)]
-    pub tags: Vec<NoteTag>, // tags associated with the note
+    #[serde(
+        default,
+        serialize_with = "Note::serialize_tags",
+        deserialize_with = "Note::deserialize_tags"
+    )]
+    pub tags: Vec<NoteTag>, // tags associated with the note, space-separated in Anki
     #[serde(rename = "flds")]
     pub fields: Vec<String>,
     #[serde(rename = "sfld")]
-    pub sort_filed: usize, // index of the field used for sorting
+    pub sort_field: String, // stripped text of the field used for sorting
     #[serde(rename = "csum")]
     pub checksum: i64, // checksum of the note, the first 8 digits of sha1 hash of the first field
     pub flags: i64,   // flags associated with the note, unused
@@ -55,6 +118,20 @@ pub struct Note {
 }
 
 impl Note {
+    fn serialize_tags<S>(tags: &[NoteTag], serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&tags_to_string(tags))
+    }
+
+    fn deserialize_tags<'de, D>(deserializer: D) -> Result<Vec<NoteTag>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Ok(tags_from_str(&String::deserialize(deserializer)?))
+    }
+
     #[allow(clippy::too_many_arguments)]
     pub fn new(
         id: i64,
@@ -64,7 +141,7 @@ impl Note {
         update_seq_number: i64,
         tags: Vec<NoteTag>,
         fields: Vec<String>,
-        sort_filed: usize,
+        sort_field: String,
         checksum: i64,
     ) -> Self {
         Note {
@@ -75,10 +152,51 @@ impl Note {
             update_seq_number,
             tags,
             fields,
-            sort_filed,
+            sort_field,
             checksum,
             flags: 0,
             data: "".to_string(),
         }
     }
+
+    /// A note of `model` with the given field values, deriving the
+    /// sort field and the checksum from them as Anki does.
+    pub fn with_model(
+        id: i64,
+        global_id: String,
+        model: &Model,
+        modified: i64,
+        tags: Vec<NoteTag>,
+        fields: Vec<String>,
+    ) -> Result<Self, String> {
+        if fields.len() != model.fields.len() {
+            return Err(format!(
+                "Model {} has {} fields, got {}",
+                model.name,
+                model.fields.len(),
+                fields.len()
+            ));
+        }
+
+        let sort_field = fields
+            .get(model.sort_field_index)
+            .map(|field| strip_html(field))
+            .unwrap_or_default();
+        let checksum = fields
+            .first()
+            .map(|field| field_checksum(field))
+            .unwrap_or_default();
+
+        Ok(Note::new(
+            id,
+            global_id,
+            model.model_id as usize,
+            modified,
+            -1, // not synced yet
+            tags,
+            fields,
+            sort_field,
+            checksum,
+        ))
+    }
 }
@@ -1,24 +1,539 @@
+use std::rc::Rc;
+
+use crate::ankigen::autogen::Autogen;
+use crate::ankigen::db_model::deck::DeckConfig;
+use crate::ankigen::db_model::model::Model;
+use crate::ankigen::db_model::note::NoteTag;
+use crate::ankigen::field_map::{FieldMap, FieldMapError};
+use crate::ankigen::guid::{GuidCollision, GuidRegistry};
 use crate::ankigen::information::SimpleInformation;
+use crate::ankigen::metadata::{
+    FrontMatterError, MetaEntry, Metadata,
+};
+use crate::ankigen::note_type::{section_note_types, NoteTypeError};
+use crate::ankigen::preset::{DeckPreset, PresetError};
+use crate::ankigen::tags::{
+    inherit, metadata_tags, tag_line, TagError, TAGS,
+};
+use crate::ankigen::workspace::DECK_SEPARATOR;
+use crate::markdown::ast::{Node, NodeType, Text};
+use crate::markdown::error::ParseError;
 
-#[allow(dead_code)]
-#[derive(Debug, Clone)]
-struct DeckAutoMeta {
-    id: u32,
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DeckAutoMeta {
+    pub id: i64,
 }
 
-#[allow(dead_code)]
-#[derive(Debug, Clone)]
-struct DeckMeta {
-    autogen: DeckAutoMeta,
+/// Heading level of the notes when the deck metadata has no
+/// `- Note level:`, the level of the deck itself.
+pub const DEFAULT_NOTE_LEVEL: usize = 1;
+
+/// Names of the sections of a deck that hold settings rather than
+/// notes or subdecks.
+pub const SETTINGS_SECTIONS: [&str; 4] =
+    ["Metadata", "Deck metadata", "Note types", "Note type"];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeckMeta {
+    pub autogen: DeckAutoMeta,
+    /// The heading level of the notes of the file, see [`Deck`].
+    pub note_level: usize,
+    pub settings: Metadata,
+    /// The tags of the deck's notes: those of the file and of the
+    /// parent decks, then the deck's own.
+    pub tags: Vec<NoteTag>,
+    /// The options group of the deck, from its `Config` setting.
+    pub preset: Option<DeckPreset>,
 }
 
-#[allow(dead_code)]
+impl Default for DeckMeta {
+    fn default() -> Self {
+        Self {
+            autogen: DeckAutoMeta::default(),
+            note_level: DEFAULT_NOTE_LEVEL,
+            settings: Metadata::default(),
+            tags: vec![],
+            preset: None,
+        }
+    }
+}
+
+/// A deck as written in markdown.
+///
+/// The first H1 of the file names the deck and the paragraphs under
+/// it describe it. A `## Metadata` or `## Deck metadata` list under
+/// that heading holds the settings and a `## Note types` section
+/// declares the deck's own note types. Every later H1 is a
+/// [`SimpleInformation`].
+///
+/// With a `- Note level:` above 1 in the deck metadata, the file
+/// holds a single deck and the heading levels give its hierarchy
+/// instead. With `- Note level: 3`, every H2 under the H1 is a
+/// subdeck and every H3 a note of the deck or subdeck it is in:
+///
+/// ```markdown
+/// # Japanese
+/// ## Lesson 1
+/// ### 水
+/// #### Meaning
+/// water
+/// ```
+///
+/// A subdeck has a description and a `Metadata` section like the
+/// deck, and the sections named in [`SETTINGS_SECTIONS`] are never
+/// subdecks nor notes.
+///
+/// Tags come from `#tag` lines, like `#japanese #lang::kanji`, and
+/// `Tags` settings. Those before the H1 or under it tag every note
+/// of the file, those of a subdeck or note its own notes.
+///
+/// A `Config` setting chooses the options group of a deck or
+/// subdeck, see [`DeckPreset`].
 #[derive(Debug, Clone)]
-struct Deck {
-    name: String,
-    description: String,
-    metadata: DeckMeta,
-    information: Vec<SimpleInformation>,
+pub struct Deck {
+    pub name: String,
+    pub description: String,
+    pub metadata: DeckMeta,
+    pub note_types: Vec<Model>,
+    pub information: Vec<SimpleInformation>,
+    pub subdecks: Vec<Deck>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DeckError {
+    Parse(ParseError),
+    FrontMatter(FrontMatterError),
+    Tag(TagError),
+    Preset(PresetError),
+    NoteType(NoteTypeError),
+    MissingName,
+    InvalidMetadata {
+        key: String,
+        value: String,
+    },
+    /// A level 1 heading after the deck in a file with notes below
+    /// level 1, which holds a single deck.
+    SecondDeck {
+        name: String,
+    },
+}
+
+impl std::fmt::Display for DeckError {
+    fn fmt(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+    ) -> std::fmt::Result {
+        match self {
+            Self::Parse(error) => write!(f, "{error}"),
+            Self::FrontMatter(error) => write!(f, "{error}"),
+            Self::Tag(error) => write!(f, "{error}"),
+            Self::Preset(error) => write!(f, "{error}"),
+            Self::NoteType(error) => write!(f, "{error}"),
+            Self::MissingName => {
+                write!(f, "no level 1 heading to name the deck")
+            }
+            Self::InvalidMetadata { key, value } => {
+                write!(
+                    f,
+                    "invalid value {value:?} for metadata {key}"
+                )
+            }
+            Self::SecondDeck { name } => write!(
+                f,
+                "level 1 heading {name} starts a second deck, notes \
+                 are below level 1"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for DeckError {}
+
+impl From<ParseError> for DeckError {
+    fn from(error: ParseError) -> Self {
+        Self::Parse(error)
+    }
+}
+
+impl From<FrontMatterError> for DeckError {
+    fn from(error: FrontMatterError) -> Self {
+        Self::FrontMatter(error)
+    }
+}
+
+impl From<TagError> for DeckError {
+    fn from(error: TagError) -> Self {
+        Self::Tag(error)
+    }
+}
+
+impl From<PresetError> for DeckError {
+    fn from(error: PresetError) -> Self {
+        Self::Preset(error)
+    }
+}
+
+impl From<NoteTypeError> for DeckError {
+    fn from(error: NoteTypeError) -> Self {
+        Self::NoteType(error)
+    }
+}
+
+/// Plain text of a heading, without a trailing colon, so that
+/// `## Metadata:` and `## metadata` name the same section.
+pub fn section_name(heading: &Node) -> String {
+    heading
+        .inline_content()
+        .iter()
+        .map(Text::to_plain_text)
+        .collect::<String>()
+        .trim()
+        .trim_end_matches(':')
+        .trim_end()
+        .to_string()
+}
+
+/// The subsection of `heading` whose name is one of `names`,
+/// compared case-insensitively.
+pub fn find_section<'a>(
+    heading: &'a Node,
+    names: &[&str],
+) -> Option<&'a Rc<Node>> {
+    heading.subnodes().iter().find(|node| {
+        matches!(node.node_type(), NodeType::Heading { .. })
+            && names.iter().any(|name| {
+                section_name(node).eq_ignore_ascii_case(name)
+            })
+    })
+}
+
+/// The subsections of `heading`.
+pub fn subsections(
+    heading: &Node,
+) -> impl Iterator<Item = &Rc<Node>> {
+    heading.subnodes().iter().filter(|node| {
+        matches!(node.node_type(), NodeType::Heading { .. })
+    })
+}
+
+/// Reads the metadata lists of a `## Metadata` section.
+pub fn section_metadata(section: &Node) -> Metadata {
+    let mut metadata = Metadata::new();
+    for node in section.subnodes() {
+        metadata.extend(Metadata::from_list(node));
+    }
+    metadata
+}
+
+fn is_heading(node: &Rc<Node>) -> bool {
+    matches!(node.node_type(), NodeType::Heading { .. })
+}
+
+/// The settings written in `nodes` before the first heading: the
+/// YAML or TOML metadata blocks, and the `#tag` lines as a `Tags`
+/// entry.
+fn block_metadata(
+    nodes: &[Rc<Node>],
+) -> Result<Metadata, FrontMatterError> {
+    let mut metadata = Metadata::new();
+    for node in nodes.iter().take_while(|node| !is_heading(node)) {
+        match tag_line(node) {
+            Some(tags) => metadata.entries.push(MetaEntry {
+                key: TAGS.to_string(),
+                value: tags.join(" "),
+                children: vec![],
+            }),
+            None => metadata.extend(Metadata::from_node(node)?),
+        }
+    }
+    Ok(metadata)
+}
+
+/// The settings of a deck, subdeck or note heading: the YAML or TOML
+/// metadata blocks and `#tag` lines at the top of its section, then
+/// the lists of its section named one of `names`. Since
+/// [`Metadata::get`] finds the first entry of a key, the lists only
+/// set the keys the blocks leave out, but tags add up.
+pub fn heading_metadata(
+    heading: &Node,
+    names: &[&str],
+) -> Result<Metadata, FrontMatterError> {
+    let mut metadata = block_metadata(heading.subnodes())?;
+    if let Some(section) = find_section(heading, names) {
+        metadata.extend(section_metadata(section));
+    }
+    Ok(metadata)
+}
+
+/// The value of the top level entry `key`, when it is not empty.
+fn setting<'a>(metadata: &'a Metadata, key: &str) -> Option<&'a str> {
+    metadata
+        .get(key)
+        .map(|entry| entry.value.as_str())
+        .filter(|value| !value.is_empty())
+}
+
+impl DeckMeta {
+    fn from_settings(settings: Metadata) -> Result<Self, DeckError> {
+        let id = Autogen::from_metadata(&settings)?.id.unwrap_or(0);
+        let note_level = match settings.get("Note level") {
+            Some(entry) => entry
+                .value
+                .parse::<usize>()
+                .ok()
+                .filter(|level| (1..=6).contains(level))
+                .ok_or_else(|| DeckError::InvalidMetadata {
+                    key: "Note level".to_string(),
+                    value: entry.value.clone(),
+                })?,
+            None => DEFAULT_NOTE_LEVEL,
+        };
+
+        Ok(Self {
+            autogen: DeckAutoMeta { id },
+            note_level,
+            tags: metadata_tags(&settings)?,
+            preset: DeckPreset::from_metadata(&settings)?,
+            settings,
+        })
+    }
+}
+
+impl Deck {
+    pub fn from_markdown(source: &str) -> Result<Self, DeckError> {
+        let nodes = Node::parse_document(source)?;
+        let mut headings = nodes.iter().filter(|node| {
+            matches!(
+                node.node_type(),
+                NodeType::Heading { level: 1, .. }
+            )
+        });
+
+        // The front matter and tags of the file come before the
+        // deck's H1.
+        let file_metadata = block_metadata(&nodes)?;
+
+        let deck_heading =
+            headings.next().ok_or(DeckError::MissingName)?;
+        let mut deck =
+            Self::from_section(deck_heading, file_metadata)?;
+
+        match deck.metadata.note_level {
+            DEFAULT_NOTE_LEVEL => {
+                for heading in headings {
+                    deck.push_note(heading)?;
+                }
+            }
+            note_level => {
+                if let Some(heading) = headings.next() {
+                    return Err(DeckError::SecondDeck {
+                        name: section_name(heading),
+                    });
+                }
+                deck.read_sections(deck_heading, note_level)?;
+            }
+        }
+        Ok(deck)
+    }
+
+    /// The deck of a heading without its notes and subdecks. The
+    /// `name` and `description` settings, from the front matter,
+    /// replace the heading and the paragraphs under it.
+    fn from_section(
+        heading: &Node,
+        mut settings: Metadata,
+    ) -> Result<Self, DeckError> {
+        let description = heading
+            .subnodes()
+            .iter()
+            .take_while(|node| {
+                !matches!(node.node_type(), NodeType::Heading { .. })
+            })
+            .filter(|node| {
+                matches!(node.node_type(), NodeType::Paragraph)
+                    && tag_line(node).is_none()
+            })
+            .map(|node| {
+                Text::children_to_markdown(&node.inline_content())
+            })
+            .collect::<Vec<_>>()
+            .join("\n\n");
+
+        settings.extend(heading_metadata(
+            heading,
+            &["Metadata", "Deck metadata"],
+        )?);
+        let name = setting(&settings, "name")
+            .map_or_else(|| section_name(heading), str::to_string);
+        let description = setting(&settings, "description")
+            .map_or(description, str::to_string);
+
+        Ok(Self {
+            name,
+            description,
+            metadata: DeckMeta::from_settings(settings)?,
+            note_types: section_note_types(heading)?,
+            information: vec![],
+            subdecks: vec![],
+        })
+    }
+
+    /// Reads the notes and subdecks of a deck's heading, the
+    /// sections at `note_level` or below being notes.
+    fn read_sections(
+        &mut self,
+        heading: &Node,
+        note_level: usize,
+    ) -> Result<(), DeckError> {
+        for section in subsections(heading) {
+            let name = section_name(section);
+            if SETTINGS_SECTIONS
+                .iter()
+                .any(|settings| settings.eq_ignore_ascii_case(&name))
+            {
+                continue;
+            }
+            match section.heading_level() {
+                Some(level) if level < note_level => {
+                    let mut subdeck =
+                        Self::from_section(section, Metadata::new())?;
+                    subdeck.metadata.note_level = note_level;
+                    subdeck.metadata.tags = inherit(
+                        &self.metadata.tags,
+                        subdeck.metadata.tags,
+                    );
+                    subdeck.read_sections(section, note_level)?;
+                    self.subdecks.push(subdeck);
+                }
+                _ => self.push_note(section)?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Adds the note of `heading`, with the tags of the deck.
+    fn push_note(&mut self, heading: &Node) -> Result<(), DeckError> {
+        let mut information =
+            SimpleInformation::from_heading(heading)?;
+        information.tags =
+            inherit(&self.metadata.tags, information.tags);
+        self.information.push(information);
+        Ok(())
+    }
+
+    /// The notes of the deck then those of its subdecks, depth
+    /// first, each with the full name of its deck, as in
+    /// `Japanese::Lesson 1`.
+    pub fn notes(&self) -> Vec<(String, &SimpleInformation)> {
+        self.notes_in(&self.name)
+    }
+
+    /// The notes as in [`Deck::notes`], for the deck named
+    /// `deck_name` rather than by its H1, as the deck of a folder of
+    /// a workspace is.
+    pub fn notes_in(
+        &self,
+        deck_name: &str,
+    ) -> Vec<(String, &SimpleInformation)> {
+        let mut notes = self
+            .information
+            .iter()
+            .map(|information| (deck_name.to_string(), information))
+            .collect::<Vec<_>>();
+        for subdeck in &self.subdecks {
+            notes.extend(subdeck.notes_in(&format!(
+                "{deck_name}{DECK_SEPARATOR}{}",
+                subdeck.name
+            )));
+        }
+        notes
+    }
+
+    /// The presets declared by the `Config` settings of the deck
+    /// and its subdecks.
+    pub fn presets(&self) -> Vec<&DeckConfig> {
+        let mut presets = vec![];
+        if let Some(DeckPreset::Declared(config)) =
+            &self.metadata.preset
+        {
+            presets.push(config.as_ref());
+        }
+        for subdeck in &self.subdecks {
+            presets.extend(subdeck.presets());
+        }
+        presets
+    }
+
+    /// The subdecks, depth first, each with its full name.
+    pub fn all_subdecks(&self) -> Vec<(String, &Deck)> {
+        let mut decks = vec![];
+        for subdeck in &self.subdecks {
+            decks.push((
+                format!(
+                    "{}{DECK_SEPARATOR}{}",
+                    self.name, subdeck.name
+                ),
+                subdeck,
+            ));
+            decks.extend(subdeck.all_subdecks().into_iter().map(
+                |(name, deck)| {
+                    (
+                        format!(
+                            "{}{DECK_SEPARATOR}{name}",
+                            self.name
+                        ),
+                        deck,
+                    )
+                },
+            ));
+        }
+        decks
+    }
+
+    /// How the notes of the deck fill the fields of `model`, after
+    /// the `- Maps:` of the deck metadata.
+    pub fn field_map(
+        &self,
+        model: &Model,
+    ) -> Result<FieldMap, FieldMapError> {
+        FieldMap::from_metadata(model, &self.metadata.settings)
+    }
+
+    /// GUIDs of the notes of [`Deck::notes`], in order, or the
+    /// collisions between them.
+    pub fn guids(&self) -> Result<Vec<String>, Vec<GuidCollision>> {
+        let mut registry = GuidRegistry::new();
+        let guids = self.register_guids(&mut registry);
+        registry.into_result().map(|_| guids)
+    }
+
+    /// Registers the GUIDs of the notes in a registry shared by all
+    /// the decks of a build, and returns them in order.
+    pub fn register_guids(
+        &self,
+        registry: &mut GuidRegistry,
+    ) -> Vec<String> {
+        register_guids(self.notes(), registry)
+    }
+}
+
+/// Registers the GUID of each note, derived from the full name of
+/// its deck, and returns them in order.
+pub fn register_guids(
+    notes: Vec<(String, &SimpleInformation)>,
+    registry: &mut GuidRegistry,
+) -> Vec<String> {
+    notes
+        .into_iter()
+        .map(|(deck_name, information)| {
+            let guid = information.guid(&deck_name);
+            registry.register(
+                &guid,
+                &format!("{deck_name} > {}", information.word),
+            );
+            guid
+        })
+        .collect()
 }
 
 #[cfg(test)]
@@ -34,7 +549,10 @@ mod tests {
     #[test]
     fn test_deck_meta() {
         let auto_meta = DeckAutoMeta { id: 1 };
-        let deck_meta = DeckMeta { autogen: auto_meta };
+        let deck_meta = DeckMeta {
+            autogen: auto_meta,
+            ..Default::default()
+        };
         assert_eq!(deck_meta.autogen.id, 1);
     }
 
@@ -45,8 +563,11 @@ mod tests {
             description: "This is a test deck".to_string(),
             metadata: DeckMeta {
                 autogen: DeckAutoMeta { id: 1 },
+                ..Default::default()
             },
+            note_types: vec![],
             information: vec![],
+            subdecks: vec![],
         };
         assert_eq!(deck.name, "Test Deck");
         assert_eq!(deck.description, "This is a test deck");
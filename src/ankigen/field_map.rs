@@ -0,0 +1,301 @@
+use crate::ankigen::db_model::model::{Model, ModelType};
+use crate::ankigen::deck::section_name;
+use crate::ankigen::information::SimpleInformation;
+use crate::ankigen::metadata::Metadata;
+use crate::markdown::html::{HtmlRenderer, ImageSource};
+use crate::markdown::util::escape_html;
+
+/// Name of the [`FieldSource::Heading`] in a `- Maps:` list.
+pub const HEADING_SOURCE: &str = "Heading";
+
+/// Name of the section holding the definitions of a word.
+pub const MEANING_SECTION: &str = "Meaning";
+
+/// Where a field of the note takes its value from.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FieldSource {
+    /// The text of the note's H1, the word.
+    Heading,
+    /// The content of the subsection with this name.
+    Section(String),
+}
+
+impl FieldSource {
+    /// Whether both take the same part of a note, section names
+    /// being compared without case as when a note is mapped.
+    pub fn is_same(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Heading, Self::Heading) => true,
+            (Self::Section(name), Self::Section(other)) => {
+                name.eq_ignore_ascii_case(other)
+            }
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldMapping {
+    pub field: String,
+    pub source: FieldSource,
+    /// Whether a note without the section gets a diagnostic. Fields
+    /// only filled from a section of the same name are optional.
+    pub required: bool,
+}
+
+/// A `- Maps:` entry naming a field the model does not have.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FieldMapError {
+    UnknownField { model: String, field: String },
+}
+
+impl std::fmt::Display for FieldMapError {
+    fn fmt(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+    ) -> std::fmt::Result {
+        match self {
+            Self::UnknownField { model, field } => write!(
+                f,
+                "Maps names field {field}, which note type {model} \
+                 does not have"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for FieldMapError {}
+
+/// A note whose sections do not match the field map. The note is
+/// still built, with the missing fields left empty.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FieldMapDiagnostic {
+    /// The note has no section for a field mapped to one.
+    MissingSection {
+        word: String,
+        field: String,
+        section: String,
+    },
+    /// No field takes the content of the section.
+    UnmappedSection { word: String, section: String },
+}
+
+impl std::fmt::Display for FieldMapDiagnostic {
+    fn fmt(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+    ) -> std::fmt::Result {
+        match self {
+            Self::MissingSection {
+                word,
+                field,
+                section,
+            } => write!(
+                f,
+                "{word} has no section {section}, field {field} is \
+                 left empty"
+            ),
+            Self::UnmappedSection { word, section } => write!(
+                f,
+                "section {section} of {word} is not mapped to a field"
+            ),
+        }
+    }
+}
+
+/// The fields of a note and what did not fit the map.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MappedFields {
+    pub fields: Vec<String>,
+    pub diagnostics: Vec<FieldMapDiagnostic>,
+}
+
+/// Which part of a [`SimpleInformation`] fills each field of a
+/// model, one mapping per field in the model's order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldMap {
+    pub mappings: Vec<FieldMapping>,
+}
+
+impl FieldMap {
+    /// The default mapping: the first field, `Front` for the stock
+    /// note types, takes the word and a field named `Back` takes the
+    /// `Meaning` section. The first field of a cloze note type takes
+    /// the `Meaning` section instead, as the clozes are written
+    /// there. Every other field takes the section of the same name,
+    /// e.g. `## Example` fills `Example`, when the note has one.
+    pub fn simple_information(model: &Model) -> Self {
+        let mappings = model
+            .fields
+            .iter()
+            .enumerate()
+            .map(|(idx, field)| {
+                let (source, required) = match idx {
+                    0 if model.model_type == ModelType::Cloze => (
+                        FieldSource::Section(
+                            MEANING_SECTION.to_string(),
+                        ),
+                        true,
+                    ),
+                    0 => (FieldSource::Heading, true),
+                    _ if field.name == "Back" => (
+                        FieldSource::Section(
+                            MEANING_SECTION.to_string(),
+                        ),
+                        true,
+                    ),
+                    _ => (
+                        FieldSource::Section(field.name.clone()),
+                        false,
+                    ),
+                };
+                FieldMapping {
+                    field: field.name.clone(),
+                    source,
+                    required,
+                }
+            })
+            .collect();
+        Self { mappings }
+    }
+
+    /// The default mapping of `model` with the entries of the
+    /// `- Maps:` list of `metadata` in place of the defaults:
+    ///
+    /// ```markdown
+    /// - Maps:
+    ///     - Back: Definition
+    ///     - Front: Heading
+    /// ```
+    ///
+    /// Each entry names a field of the model and the section that
+    /// fills it, or `Heading` for the word. An entry naming a field
+    /// the model does not have, like `Description: Meaning` for the
+    /// stock note types, applies to the field already filled from
+    /// that source, here `Back`.
+    pub fn from_metadata(
+        model: &Model,
+        metadata: &Metadata,
+    ) -> Result<Self, FieldMapError> {
+        let mut map = Self::simple_information(model);
+        let entries = metadata
+            .get("Maps")
+            .map(|maps| maps.children.as_slice())
+            .unwrap_or_default();
+        for entry in entries {
+            let source = match entry
+                .value
+                .eq_ignore_ascii_case(HEADING_SOURCE)
+            {
+                true => FieldSource::Heading,
+                false => FieldSource::Section(entry.value.clone()),
+            };
+            let mapping = map
+                .mappings
+                .iter()
+                .position(|mapping| mapping.field == entry.key)
+                .or_else(|| {
+                    map.mappings.iter().position(|mapping| {
+                        mapping.source.is_same(&source)
+                    })
+                })
+                .ok_or_else(|| FieldMapError::UnknownField {
+                    model: model.name.clone(),
+                    field: entry.key.clone(),
+                })?;
+            let mapping = &mut map.mappings[mapping];
+            mapping.source = source;
+            mapping.required = true;
+        }
+        Ok(map)
+    }
+
+    /// Renders the fields of `information` to HTML, each with its
+    /// own renderer so that the clozes of a field are numbered on
+    /// their own. Images keep the url written in the markdown, see
+    /// [`FieldMap::fields_with_images`] for the package.
+    pub fn fields(
+        &self,
+        information: &SimpleInformation,
+    ) -> MappedFields {
+        self.render_fields(information, None)
+    }
+
+    /// [`FieldMap::fields`] with the `src` of images rewritten by
+    /// `image_source`, e.g. the [`MediaManifest::image_source`] of
+    /// the file, so that they point at the media names packaged with
+    /// the deck.
+    ///
+    /// [`MediaManifest::image_source`]: crate::ankigen::media::MediaManifest::image_source
+    pub fn fields_with_images(
+        &self,
+        information: &SimpleInformation,
+        image_source: &ImageSource<'_>,
+    ) -> MappedFields {
+        self.render_fields(information, Some(image_source))
+    }
+
+    fn render_fields(
+        &self,
+        information: &SimpleInformation,
+        image_source: Option<&ImageSource<'_>>,
+    ) -> MappedFields {
+        let renderer = || match image_source {
+            Some(image_source) => {
+                HtmlRenderer::new().with_image_source(image_source)
+            }
+            None => HtmlRenderer::new(),
+        };
+        let mut diagnostics = vec![];
+        let fields = self
+            .mappings
+            .iter()
+            .map(|mapping| match &mapping.source {
+                FieldSource::Heading => escape_html(&information.word),
+                FieldSource::Section(name) => {
+                    match information.section(name) {
+                        Some(section) => {
+                            renderer().render_nodes(section.subnodes())
+                        }
+                        None => {
+                            if mapping.required {
+                                diagnostics.push(
+                                    FieldMapDiagnostic::MissingSection {
+                                        word: information.word.clone(),
+                                        field: mapping.field.clone(),
+                                        section: name.clone(),
+                                    },
+                                );
+                            }
+                            String::new()
+                        }
+                    }
+                }
+            })
+            .collect();
+
+        for section in &information.sections {
+            let name = section_name(section);
+            let mapped = self.mappings.iter().any(|mapping| {
+                matches!(
+                    &mapping.source,
+                    FieldSource::Section(source)
+                        if source.eq_ignore_ascii_case(&name)
+                )
+            });
+            if !mapped {
+                diagnostics.push(
+                    FieldMapDiagnostic::UnmappedSection {
+                        word: information.word.clone(),
+                        section: name,
+                    },
+                );
+            }
+        }
+
+        MappedFields {
+            fields,
+            diagnostics,
+        }
+    }
+}
@@ -0,0 +1,70 @@
+use std::collections::BTreeMap;
+
+/// Two notes of a build that got the same GUID, so Anki would import
+/// one over the other.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GuidCollision {
+    pub guid: String,
+    pub first: String,
+    pub second: String,
+}
+
+impl std::fmt::Display for GuidCollision {
+    fn fmt(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+    ) -> std::fmt::Result {
+        write!(
+            f,
+            "{} has the same GUID {} as {}",
+            self.second, self.guid, self.first
+        )
+    }
+}
+
+impl std::error::Error for GuidCollision {}
+
+/// The GUIDs handed out during a build, each with a description of
+/// the note it belongs to, e.g. `Deck name > hello`.
+#[derive(Debug, Clone, Default)]
+pub struct GuidRegistry {
+    owners: BTreeMap<String, String>,
+    collisions: Vec<GuidCollision>,
+}
+
+impl GuidRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records `guid` for `owner`, returns `false` and keeps the
+    /// collision if another note already has it.
+    pub fn register(&mut self, guid: &str, owner: &str) -> bool {
+        match self.owners.get(guid) {
+            Some(first) => {
+                self.collisions.push(GuidCollision {
+                    guid: guid.to_string(),
+                    first: first.clone(),
+                    second: owner.to_string(),
+                });
+                false
+            }
+            None => {
+                self.owners
+                    .insert(guid.to_string(), owner.to_string());
+                true
+            }
+        }
+    }
+
+    pub fn collisions(&self) -> &[GuidCollision] {
+        &self.collisions
+    }
+
+    pub fn into_result(self) -> Result<(), Vec<GuidCollision>> {
+        match self.collisions.is_empty() {
+            true => Ok(()),
+            false => Err(self.collisions),
+        }
+    }
+}
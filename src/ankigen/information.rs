@@ -1,7 +1,147 @@
+use std::rc::Rc;
+
+use crate::ankigen::autogen::AUTOGEN;
+use crate::ankigen::db_model::note::NoteTag;
+use crate::ankigen::deck::{
+    find_section, heading_metadata, section_name, DeckError,
+};
+use crate::ankigen::metadata::Metadata;
+use crate::ankigen::stock::StockModel;
+use crate::ankigen::tags::metadata_tags;
+use crate::ankigen::util::base91_encode;
+use crate::markdown::ast::{Node, NodeType, Text};
+
 #[derive(Debug, Clone, Default)]
 pub struct SimpleInformation {
     pub word: String,
     pub definitions: Vec<String>,
+    pub metadata: Metadata,
+    /// The tags of the note, with those of its deck when read as
+    /// part of a [`Deck`](crate::ankigen::deck::Deck).
+    pub tags: Vec<NoteTag>,
+    /// The subsections of the word's heading but `Metadata`, which a
+    /// [`FieldMap`] turns into the fields of the note.
+    ///
+    /// [`FieldMap`]: crate::ankigen::field_map::FieldMap
+    pub sections: Vec<Rc<Node>>,
+}
+
+impl SimpleInformation {
+    /// Reads a word from its H1. Each item of the lists in its
+    /// `## Meaning` section, and each paragraph there, is one
+    /// definition, kept as markdown. The metadata is that of a YAML
+    /// or TOML block right under the heading, else of the
+    /// `## Metadata` lists, and the tags are those of its `#tag`
+    /// lines and `Tags` entries.
+    pub fn from_heading(heading: &Node) -> Result<Self, DeckError> {
+        let mut definitions = vec![];
+        if let Some(meaning) = find_section(heading, &["Meaning"]) {
+            for node in meaning.subnodes() {
+                match node.node_type() {
+                    NodeType::List { .. } => definitions.extend(
+                        node.subnodes().iter().map(|item| {
+                            item.to_string().trim_end().to_string()
+                        }),
+                    ),
+                    NodeType::Paragraph => {
+                        definitions.push(Text::children_to_markdown(
+                            &node.inline_content(),
+                        ))
+                    }
+                    _ => (),
+                }
+            }
+        }
+
+        let sections = heading
+            .subnodes()
+            .iter()
+            .filter(|node| {
+                matches!(node.node_type(), NodeType::Heading { .. })
+                    && !section_name(node)
+                        .eq_ignore_ascii_case("Metadata")
+            })
+            .cloned()
+            .collect();
+
+        let metadata = heading_metadata(heading, &["Metadata"])?;
+        Ok(Self {
+            word: section_name(heading),
+            definitions,
+            tags: metadata_tags(&metadata)?,
+            metadata,
+            sections,
+        })
+    }
+
+    /// What the GUID of the note is derived from: the `id:` of the
+    /// note's metadata when it has one, else the full name of its
+    /// deck, as in `Japanese::Kanji`, and the word. The definitions
+    /// are left out so that editing them updates the same Anki note.
+    pub fn guid_key(&self, deck_name: &str) -> Vec<String> {
+        match self.metadata.get("id") {
+            Some(id) if !id.value.is_empty() => {
+                vec![id.value.clone()]
+            }
+            _ => vec![deck_name.to_string(), self.word.clone()],
+        }
+    }
+
+    /// The subsection named `name`, compared case-insensitively.
+    pub fn section(&self, name: &str) -> Option<&Rc<Node>> {
+        self.sections.iter().find(|section| {
+            section_name(section).eq_ignore_ascii_case(name)
+        })
+    }
+
+    /// The GUID recorded under `- Autogen:` by
+    /// [`write_autogen`], else the one derived from
+    /// [`SimpleInformation::guid_key`].
+    ///
+    /// [`write_autogen`]: crate::ankigen::autogen::write_autogen
+    pub fn guid(&self, deck_name: &str) -> String {
+        match self.metadata.get_path(&[AUTOGEN, "guid"]) {
+            Some(guid) if !guid.value.is_empty() => {
+                guid.value.clone()
+            }
+            _ => base91_encode(&self.guid_key(deck_name)),
+        }
+    }
+
+    /// The note type named by the note's `model` setting, if any.
+    pub fn model_name(&self) -> Option<&str> {
+        self.metadata
+            .get("Model")
+            .map(|entry| entry.value.as_str())
+            .filter(|name| !name.is_empty())
+    }
+
+    /// The stock note type named by the note's `model` setting or
+    /// its `- Templates:` metadata, else Cloze when a definition
+    /// holds a cloze, else Basic. `None` when the model or the
+    /// templates match no stock note type.
+    pub fn stock_model(&self) -> Option<StockModel> {
+        if let Some(name) = self.model_name() {
+            return StockModel::from_name(name);
+        }
+        match self.metadata.get("Templates") {
+            Some(_) => StockModel::from_metadata(&self.metadata),
+            None if self.has_cloze() => Some(StockModel::Cloze),
+            None => Some(StockModel::Basic),
+        }
+    }
+
+    /// Whether a definition holds a cloze, which makes the note a
+    /// [`ModelType::Cloze`] note.
+    ///
+    /// [`ModelType::Cloze`]: crate::ankigen::db_model::model::ModelType::Cloze
+    pub fn has_cloze(&self) -> bool {
+        self.definitions.iter().any(|definition| {
+            Node::parse_document(definition).is_ok_and(|nodes| {
+                nodes.iter().any(|node| node.has_cloze())
+            })
+        })
+    }
 }
 
 #[cfg(test)]
@@ -13,6 +153,7 @@ mod tests {
         let simple_info = SimpleInformation {
             word: "hello".to_string(),
             definitions: vec!["a greeting".to_string()],
+            ..Default::default()
         };
 
         assert_eq!(simple_info.word, "hello");
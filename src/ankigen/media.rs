@@ -0,0 +1,194 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use sha2::{Digest, Sha256};
+
+use crate::markdown::ast::{Node, NodeType, Text};
+
+/// A local file referenced from the markdown, and the name it gets
+/// in the Anki media folder.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MediaFile {
+    pub path: PathBuf,
+    pub name: String,
+}
+
+/// The media files of a package.
+///
+/// Anki keeps media in a flat folder, so every file is renamed to
+/// `<stem>-<hash>.<ext>`, where the hash is taken from the content.
+/// Diagrams with the same name in different directories then do not
+/// clash, identical files are stored once, and the name of a file
+/// stays the same from build to build as long as it is unchanged.
+#[derive(Debug, Clone, Default)]
+pub struct MediaManifest {
+    files: Vec<MediaFile>,
+    names: BTreeMap<PathBuf, usize>,
+}
+
+/// Whether an image url points to a local file rather than to the
+/// web or to inline data.
+pub fn is_local_reference(url: &str) -> bool {
+    let scheme = url
+        .split_once(':')
+        .map(|(scheme, _)| scheme)
+        .filter(|scheme| {
+            scheme.len() > 1
+                && scheme.chars().all(|chr| {
+                    chr.is_ascii_alphanumeric() || "+-.".contains(chr)
+                })
+        });
+    !url.is_empty() && !url.starts_with('#') && scheme.is_none()
+}
+
+/// Urls of all the images in `nodes`, in document order.
+pub fn image_urls(nodes: &[Rc<Node>]) -> Vec<String> {
+    fn text_urls(text: &Text, urls: &mut Vec<String>) {
+        if let Text::Image { url, .. } = text {
+            urls.push(url.clone());
+        }
+        for child in text.children() {
+            text_urls(child, urls);
+        }
+    }
+
+    fn node_urls(node: &Node, urls: &mut Vec<String>) {
+        match node.node_type() {
+            NodeType::Text(text) => text_urls(text, urls),
+            NodeType::Heading { content, .. } => {
+                for text in content {
+                    text_urls(text, urls);
+                }
+            }
+            _ => (),
+        }
+        for subnode in node.subnodes() {
+            node_urls(subnode, urls);
+        }
+    }
+
+    let mut urls = vec![];
+    for node in nodes {
+        node_urls(node, &mut urls);
+    }
+    urls
+}
+
+fn media_name(path: &Path, content: &[u8]) -> String {
+    let hash = Sha256::digest(content)
+        .iter()
+        .take(8)
+        .map(|byte| format!("{byte:02x}"))
+        .collect::<String>();
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    match path.extension() {
+        Some(ext) => {
+            format!("{stem}-{hash}.{}", ext.to_string_lossy())
+        }
+        None => format!("{stem}-{hash}"),
+    }
+}
+
+impl MediaManifest {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the file at `path` and returns its media name.
+    pub fn add(&mut self, path: &Path) -> std::io::Result<String> {
+        let path = path.canonicalize().map_err(|err| {
+            std::io::Error::new(
+                err.kind(),
+                format!("{}: {err}", path.display()),
+            )
+        })?;
+        if let Some(idx) = self.names.get(&path) {
+            return Ok(self.files[*idx].name.clone());
+        }
+
+        let content = std::fs::read(&path)?;
+        let name = media_name(&path, &content);
+        let idx = match self
+            .files
+            .iter()
+            .position(|file| file.name == name)
+        {
+            Some(idx) => idx,
+            None => {
+                self.files.push(MediaFile {
+                    path: path.clone(),
+                    name: name.clone(),
+                });
+                self.files.len() - 1
+            }
+        };
+        self.names.insert(path, idx);
+        Ok(name)
+    }
+
+    /// Adds every local image of `nodes`, relative paths are resolved
+    /// against `base_dir`, the directory of the markdown file.
+    pub fn collect(
+        &mut self,
+        nodes: &[Rc<Node>],
+        base_dir: &Path,
+    ) -> std::io::Result<()> {
+        for url in image_urls(nodes) {
+            if is_local_reference(&url) {
+                self.add(&base_dir.join(url))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Media name of an image url of a file in `base_dir`, if the
+    /// image was collected.
+    pub fn name_for(
+        &self,
+        base_dir: &Path,
+        url: &str,
+    ) -> Option<&str> {
+        let path = base_dir.join(url).canonicalize().ok()?;
+        self.names
+            .get(&path)
+            .map(|idx| self.files[*idx].name.as_str())
+    }
+
+    /// Image source for [`HtmlRenderer::with_image_source`], pointing
+    /// the images of a file in `base_dir` at their media names.
+    ///
+    /// [`HtmlRenderer::with_image_source`]: crate::markdown::html::HtmlRenderer::with_image_source
+    pub fn image_source<'a>(
+        &'a self,
+        base_dir: &'a Path,
+    ) -> impl Fn(&str) -> Option<String> + 'a {
+        move |url| self.name_for(base_dir, url).map(str::to_string)
+    }
+
+    pub fn files(&self) -> &[MediaFile] {
+        &self.files
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// The `media` map of an `.apkg`: the package stores the files as
+    /// `0`, `1`, ... and maps each number to the media name.
+    pub fn media_map(&self) -> BTreeMap<String, String> {
+        self.files
+            .iter()
+            .enumerate()
+            .map(|(idx, file)| (idx.to_string(), file.name.clone()))
+            .collect()
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(&self.media_map())
+            .expect("a string map always serializes")
+    }
+}
@@ -0,0 +1,281 @@
+use crate::markdown::ast::{FrontMatterFormat, Node, NodeType, Text};
+
+/// One item of a metadata list, `- key: value`, with the items of
+/// its sublist as children. An item without a colon, like `Simple`
+/// under `- Templates:`, has an empty value.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MetaEntry {
+    pub key: String,
+    pub value: String,
+    pub children: Vec<MetaEntry>,
+}
+
+/// Settings written as a nested markdown list under a `## Metadata`
+/// heading:
+///
+/// ```markdown
+/// - Templates:
+///     - Simple
+///     - Reverse
+/// - Autogen:
+///     - id: 0
+/// ```
+///
+/// or as YAML or TOML front matter, see
+/// [`Metadata::from_front_matter`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Metadata {
+    pub entries: Vec<MetaEntry>,
+}
+
+/// A metadata block that is not valid YAML or TOML, or not a table
+/// of settings.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FrontMatterError {
+    pub format: FrontMatterFormat,
+    pub message: String,
+}
+
+impl std::fmt::Display for FrontMatterError {
+    fn fmt(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+    ) -> std::fmt::Result {
+        let format = match self.format {
+            FrontMatterFormat::Yaml => "YAML",
+            FrontMatterFormat::Toml => "TOML",
+        };
+        write!(f, "invalid {format} front matter: {}", self.message)
+    }
+}
+
+impl std::error::Error for FrontMatterError {}
+
+/// `note_level` and `note-level` name the `Note level` of the list
+/// style.
+pub fn front_matter_key(key: &str) -> String {
+    key.replace(['_', '-'], " ")
+}
+
+/// A `true`/`yes` or `false`/`no` setting.
+pub fn parse_bool(value: &str) -> Option<bool> {
+    match value.to_ascii_lowercase().as_str() {
+        "true" | "yes" => Some(true),
+        "false" | "no" => Some(false),
+        _ => None,
+    }
+}
+
+fn yaml_scalar(value: &serde_yaml::Value) -> Option<String> {
+    match value {
+        serde_yaml::Value::Null => Some(String::new()),
+        serde_yaml::Value::Bool(value) => Some(value.to_string()),
+        serde_yaml::Value::Number(value) => Some(value.to_string()),
+        serde_yaml::Value::String(value) => Some(value.clone()),
+        _ => None,
+    }
+}
+
+fn yaml_entries(value: &serde_yaml::Value) -> Vec<MetaEntry> {
+    match value {
+        serde_yaml::Value::Mapping(mapping) => mapping
+            .iter()
+            .map(|(key, value)| {
+                let key = yaml_scalar(key).unwrap_or_default();
+                MetaEntry {
+                    key: front_matter_key(&key),
+                    value: yaml_scalar(value).unwrap_or_default(),
+                    children: yaml_entries(value),
+                }
+            })
+            .collect(),
+        serde_yaml::Value::Sequence(items) => items
+            .iter()
+            .map(|item| MetaEntry {
+                key: yaml_scalar(item).unwrap_or_default(),
+                value: String::new(),
+                children: yaml_entries(item),
+            })
+            .collect(),
+        serde_yaml::Value::Tagged(tagged) => {
+            yaml_entries(&tagged.value)
+        }
+        _ => vec![],
+    }
+}
+
+fn toml_scalar(value: &toml::Value) -> Option<String> {
+    match value {
+        toml::Value::String(value) => Some(value.clone()),
+        toml::Value::Integer(value) => Some(value.to_string()),
+        toml::Value::Float(value) => Some(value.to_string()),
+        toml::Value::Boolean(value) => Some(value.to_string()),
+        toml::Value::Datetime(value) => Some(value.to_string()),
+        _ => None,
+    }
+}
+
+fn toml_entries(value: &toml::Value) -> Vec<MetaEntry> {
+    match value {
+        toml::Value::Table(table) => table
+            .iter()
+            .map(|(key, value)| MetaEntry {
+                key: front_matter_key(key),
+                value: toml_scalar(value).unwrap_or_default(),
+                children: toml_entries(value),
+            })
+            .collect(),
+        toml::Value::Array(items) => items
+            .iter()
+            .map(|item| MetaEntry {
+                key: toml_scalar(item).unwrap_or_default(),
+                value: String::new(),
+                children: toml_entries(item),
+            })
+            .collect(),
+        _ => vec![],
+    }
+}
+
+fn plain_text(texts: &[Text]) -> String {
+    texts.iter().map(Text::to_plain_text).collect()
+}
+
+impl MetaEntry {
+    fn from_item(item: &Node) -> Self {
+        let mut text = vec![];
+        let mut children = vec![];
+        for node in item.subnodes() {
+            match node.node_type() {
+                NodeType::Text(txt) => text.push(txt.clone()),
+                NodeType::Paragraph if children.is_empty() => {
+                    text.extend(node.inline_content())
+                }
+                NodeType::List { .. } => {
+                    children.extend(Metadata::from_list(node).entries)
+                }
+                _ => (),
+            }
+        }
+
+        let text = plain_text(&text);
+        let (key, value) =
+            text.split_once(':').unwrap_or((&text, ""));
+        Self {
+            key: key.trim().to_string(),
+            value: value.trim().to_string(),
+            children,
+        }
+    }
+
+    /// The child entry named `key`, compared case-insensitively.
+    pub fn get(&self, key: &str) -> Option<&MetaEntry> {
+        self.children
+            .iter()
+            .find(|entry| entry.key.eq_ignore_ascii_case(key))
+    }
+}
+
+impl Metadata {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reads the entries of a list node, any other node gives no
+    /// entries.
+    pub fn from_list(list: &Node) -> Self {
+        let entries = match list.node_type() {
+            NodeType::List { .. } => list
+                .subnodes()
+                .iter()
+                .map(|item| MetaEntry::from_item(item))
+                .collect(),
+            _ => vec![],
+        };
+        Self { entries }
+    }
+
+    /// Reads a YAML or TOML metadata block into entries of the list
+    /// style: a table gives one entry per key, with the scalar value
+    /// as value and the items of a list or nested table as children,
+    /// so that
+    ///
+    /// ```yaml
+    /// templates: [Simple, Reverse]
+    /// autogen:
+    ///   id: 0
+    /// ```
+    ///
+    /// reads like the example of [`Metadata`]. `_` and `-` in keys
+    /// read as spaces, `note_level` is `Note level`.
+    pub fn from_front_matter(
+        format: FrontMatterFormat,
+        content: &str,
+    ) -> Result<Self, FrontMatterError> {
+        let error =
+            |message: String| FrontMatterError { format, message };
+        let entries = match format {
+            FrontMatterFormat::Yaml => {
+                let value =
+                    serde_yaml::from_str::<serde_yaml::Value>(
+                        content,
+                    )
+                    .map_err(|err| error(err.to_string()))?;
+                match value {
+                    serde_yaml::Value::Mapping(_) => {
+                        yaml_entries(&value)
+                    }
+                    serde_yaml::Value::Null => vec![],
+                    _ => {
+                        return Err(error(
+                            "not a mapping".to_string(),
+                        ))
+                    }
+                }
+            }
+            FrontMatterFormat::Toml => {
+                let table = content.parse::<toml::Table>().map_err(
+                    |err| error(err.message().to_string()),
+                )?;
+                toml_entries(&toml::Value::Table(table))
+            }
+        };
+        Ok(Self { entries })
+    }
+
+    /// Reads the metadata block of a node, any other node gives no
+    /// entries.
+    pub fn from_node(node: &Node) -> Result<Self, FrontMatterError> {
+        match node.node_type() {
+            NodeType::FrontMatter { format, content } => {
+                Self::from_front_matter(*format, content)
+            }
+            _ => Ok(Self::new()),
+        }
+    }
+
+    /// Adds the entries of another metadata list, as when a section
+    /// holds more than one list.
+    pub fn extend(&mut self, other: Metadata) {
+        self.entries.extend(other.entries);
+    }
+
+    /// The top level entry named `key`, compared case-insensitively.
+    pub fn get(&self, key: &str) -> Option<&MetaEntry> {
+        self.entries
+            .iter()
+            .find(|entry| entry.key.eq_ignore_ascii_case(key))
+    }
+
+    /// Follows `path` through the nested entries, e.g.
+    /// `["Autogen", "id"]`.
+    pub fn get_path(&self, path: &[&str]) -> Option<&MetaEntry> {
+        let (first, rest) = path.split_first()?;
+        rest.iter()
+            .try_fold(self.get(first)?, |entry, key| entry.get(key))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
@@ -1,4 +1,19 @@
+pub mod anki2;
+pub mod apkg;
+pub mod autogen;
+pub mod builder;
+pub mod cards;
 pub mod db_model;
 pub mod deck;
+pub mod field_map;
+pub mod guid;
 pub mod information;
+pub mod media;
+pub mod metadata;
+pub mod note_type;
+pub mod preset;
+pub mod stock;
+pub mod tags;
+pub mod template;
 pub mod util;
+pub mod workspace;
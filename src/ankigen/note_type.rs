@@ -0,0 +1,397 @@
+use sha2::{Digest, Sha256};
+
+use crate::ankigen::db_model::model::{
+    Model, ModelField, ModelTemplate, ModelType,
+};
+use crate::ankigen::deck::{
+    find_section, section_metadata, section_name, subsections,
+};
+use crate::ankigen::metadata::{parse_bool, MetaEntry};
+use crate::ankigen::template::{
+    cloze_fields, parse_template, referenced_fields, TemplateError,
+    SPECIAL_FIELDS,
+};
+use crate::markdown::ast::{Node, NodeType};
+use crate::markdown::error::ParseError;
+
+/// A note type declared in markdown that does not make a valid
+/// [`Model`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum NoteTypeError {
+    Parse(ParseError),
+    NoFields {
+        model: String,
+    },
+    NoTemplates {
+        model: String,
+    },
+    DuplicateField {
+        model: String,
+        field: String,
+    },
+    MissingQuestion {
+        model: String,
+        template: String,
+    },
+    Template {
+        model: String,
+        template: String,
+        error: TemplateError,
+    },
+    /// A template refers to a field the note type does not have.
+    UnknownField {
+        model: String,
+        template: String,
+        field: String,
+    },
+    NoClozeField {
+        model: String,
+    },
+    InvalidMetadata {
+        model: String,
+        key: String,
+        value: String,
+    },
+}
+
+impl std::fmt::Display for NoteTypeError {
+    fn fmt(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+    ) -> std::fmt::Result {
+        match self {
+            Self::Parse(error) => write!(f, "{error}"),
+            Self::NoFields { model } => {
+                write!(f, "note type {model} has no fields")
+            }
+            Self::NoTemplates { model } => {
+                write!(f, "note type {model} has no templates")
+            }
+            Self::DuplicateField { model, field } => {
+                write!(f, "note type {model} has two fields {field}")
+            }
+            Self::MissingQuestion { model, template } => write!(
+                f,
+                "template {template} of {model} has no question"
+            ),
+            Self::Template {
+                model,
+                template,
+                error,
+            } => write!(f, "template {template} of {model}: {error}"),
+            Self::UnknownField {
+                model,
+                template,
+                field,
+            } => write!(
+                f,
+                "template {template} of {model} refers to unknown \
+                 field {field}"
+            ),
+            Self::NoClozeField { model } => write!(
+                f,
+                "cloze note type {model} shows no field with \
+                 {{{{cloze:...}}}}"
+            ),
+            Self::InvalidMetadata { model, key, value } => write!(
+                f,
+                "invalid value {value:?} for {key} of note type {model}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for NoteTypeError {}
+
+impl From<ParseError> for NoteTypeError {
+    fn from(error: ParseError) -> Self {
+        Self::Parse(error)
+    }
+}
+
+/// Model id of a note type without an explicit `id:`, derived from
+/// its name so that it stays the same from build to build.
+pub fn note_type_id(name: &str) -> u64 {
+    let hash = Sha256::digest(name.as_bytes());
+    // 48 bits, the size of the millisecond timestamps Anki uses.
+    hash.iter()
+        .take(6)
+        .fold(0, |id, byte| (id << 8) | *byte as u64)
+}
+
+fn code_blocks(section: &Node) -> Vec<String> {
+    section
+        .subnodes()
+        .iter()
+        .filter_map(|node| match node.node_type() {
+            NodeType::CodeBlock { code, .. } => {
+                Some(code.trim_end_matches('\n').to_string())
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+struct NoteTypeParser {
+    model: String,
+}
+
+impl NoteTypeParser {
+    fn invalid(&self, key: &str, value: &str) -> NoteTypeError {
+        NoteTypeError::InvalidMetadata {
+            model: self.model.clone(),
+            key: key.to_string(),
+            value: value.to_string(),
+        }
+    }
+
+    fn field(
+        &self,
+        entry: &MetaEntry,
+        ordinal: usize,
+    ) -> Result<ModelField, NoteTypeError> {
+        let mut field = ModelField::new(entry.key.clone(), ordinal);
+        for option in &entry.children {
+            let value = option.value.as_str();
+            match option.key.to_ascii_lowercase().as_str() {
+                "font" => field.font = value.to_string(),
+                "size" => {
+                    field.font_size = value
+                        .parse()
+                        .map_err(|_| self.invalid("size", value))?
+                }
+                "rtl" => {
+                    field.right_to_left = parse_bool(value)
+                        .ok_or_else(|| self.invalid("rtl", value))?
+                }
+                "sticky" => {
+                    field.sticky =
+                        parse_bool(value).ok_or_else(|| {
+                            self.invalid("sticky", value)
+                        })?
+                }
+                key => return Err(self.invalid(key, value)),
+            }
+        }
+        Ok(field)
+    }
+
+    fn fields(
+        &self,
+        heading: &Node,
+    ) -> Result<Vec<ModelField>, NoteTypeError> {
+        let entries = find_section(heading, &["Fields"])
+            .map(|section| section_metadata(section))
+            .unwrap_or_default()
+            .entries;
+        let mut fields: Vec<ModelField> = vec![];
+        for (ordinal, entry) in entries.iter().enumerate() {
+            if fields.iter().any(|field| field.name == entry.key) {
+                return Err(NoteTypeError::DuplicateField {
+                    model: self.model.clone(),
+                    field: entry.key.clone(),
+                });
+            }
+            fields.push(self.field(entry, ordinal)?);
+        }
+        match fields.is_empty() {
+            true => Err(NoteTypeError::NoFields {
+                model: self.model.clone(),
+            }),
+            false => Ok(fields),
+        }
+    }
+
+    /// A template is a heading with `Question` and `Answer`
+    /// subsections holding a code block each, or with just the two
+    /// code blocks, question first.
+    fn template(
+        &self,
+        heading: &Node,
+    ) -> Result<ModelTemplate, NoteTypeError> {
+        let name = section_name(heading);
+        let part = |names: &[&str]| {
+            find_section(heading, names).and_then(|section| {
+                code_blocks(section).into_iter().next()
+            })
+        };
+        let blocks = code_blocks(heading);
+        let question = part(&["Question", "Front"])
+            .or_else(|| blocks.first().cloned())
+            .ok_or_else(|| NoteTypeError::MissingQuestion {
+                model: self.model.clone(),
+                template: name.clone(),
+            })?;
+        let answer = part(&["Answer", "Back"])
+            .or_else(|| blocks.get(1).cloned())
+            .unwrap_or_default();
+        Ok(ModelTemplate::new(name, question, answer))
+    }
+
+    fn templates(
+        &self,
+        heading: &Node,
+    ) -> Result<Vec<ModelTemplate>, NoteTypeError> {
+        let templates = match find_section(heading, &["Templates"]) {
+            Some(section) => subsections(section)
+                .map(|template| self.template(template))
+                .collect::<Result<Vec<_>, _>>()?,
+            None => vec![],
+        };
+        match templates.is_empty() {
+            true => Err(NoteTypeError::NoTemplates {
+                model: self.model.clone(),
+            }),
+            false => Ok(templates),
+        }
+    }
+
+    fn validate(&self, model: &Model) -> Result<(), NoteTypeError> {
+        for template in &model.templates {
+            for format in
+                [&template.question_format, &template.answer_template]
+            {
+                let nodes =
+                    parse_template(format).map_err(|error| {
+                        NoteTypeError::Template {
+                            model: self.model.clone(),
+                            template: template.name.clone(),
+                            error,
+                        }
+                    })?;
+                let unknown = referenced_fields(&nodes)
+                    .into_iter()
+                    .find(|field| {
+                        !SPECIAL_FIELDS.contains(field)
+                            && !model.fields.iter().any(
+                                |model_field| {
+                                    model_field.name == *field
+                                },
+                            )
+                    });
+                if let Some(field) = unknown {
+                    return Err(NoteTypeError::UnknownField {
+                        model: self.model.clone(),
+                        template: template.name.clone(),
+                        field: field.to_string(),
+                    });
+                }
+            }
+        }
+
+        if model.model_type == ModelType::Cloze {
+            let shows_cloze =
+                model.templates.iter().any(|template| {
+                    parse_template(&template.question_format)
+                        .is_ok_and(|nodes| {
+                            !cloze_fields(&nodes).is_empty()
+                        })
+                });
+            if !shows_cloze {
+                return Err(NoteTypeError::NoClozeField {
+                    model: self.model.clone(),
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Compiles the section of a note type to a [`Model`].
+///
+/// The heading names the note type, its subsections are:
+///
+/// - `Fields`, a list of field names, each with optional `font`,
+///   `size`, `rtl` and `sticky` settings,
+/// - `Templates`, one subsection per card template, see below,
+/// - `CSS`, a code block with the styling,
+/// - `Metadata`, with an optional `id`, `type` (`Standard` or
+///   `Cloze`) and `sort field`.
+///
+/// A template has `Question` and `Answer` subsections holding a code
+/// block each, or just the two code blocks, question first. Every
+/// `{{Field}}` of the templates must be a field of the note type.
+pub fn parse_note_type(
+    heading: &Node,
+) -> Result<Model, NoteTypeError> {
+    let parser = NoteTypeParser {
+        model: section_name(heading),
+    };
+    let fields = parser.fields(heading)?;
+    let templates = parser.templates(heading)?;
+    let css = find_section(heading, &["CSS", "Style", "Styling"])
+        .map(|section| code_blocks(section).join("\n"));
+
+    let metadata = find_section(heading, &["Metadata"])
+        .map(|section| section_metadata(section))
+        .unwrap_or_default();
+    let model_id = match metadata.get("id") {
+        Some(entry) => entry
+            .value
+            .parse::<u64>()
+            .map_err(|_| parser.invalid("id", &entry.value))?,
+        None => note_type_id(&parser.model),
+    };
+    let model_type = match metadata.get("type") {
+        Some(entry) => {
+            match entry.value.to_ascii_lowercase().as_str() {
+                "standard" | "basic" => ModelType::FrontBack,
+                "cloze" => ModelType::Cloze,
+                _ => return Err(parser.invalid("type", &entry.value)),
+            }
+        }
+        None => ModelType::FrontBack,
+    };
+    let sort_field_index = match metadata.get("sort field") {
+        Some(entry) => fields
+            .iter()
+            .position(|field| field.name == entry.value)
+            .ok_or_else(|| {
+                parser.invalid("sort field", &entry.value)
+            })?,
+        None => 0,
+    };
+
+    let model = Model::new(
+        css,
+        fields,
+        model_id,
+        None,
+        None,
+        parser.model.clone(),
+        sort_field_index,
+        templates,
+        model_type,
+    );
+    parser.validate(&model)?;
+    Ok(model)
+}
+
+/// The note types of a `models.md`, one per H1.
+pub fn parse_note_types(
+    source: &str,
+) -> Result<Vec<Model>, NoteTypeError> {
+    Node::parse_document(source)?
+        .iter()
+        .filter(|node| {
+            matches!(
+                node.node_type(),
+                NodeType::Heading { level: 1, .. }
+            )
+        })
+        .map(|heading| parse_note_type(heading))
+        .collect()
+}
+
+/// The note types declared in the `Note types` section of a deck,
+/// one per subsection.
+pub fn section_note_types(
+    deck_heading: &Node,
+) -> Result<Vec<Model>, NoteTypeError> {
+    match find_section(deck_heading, &["Note types", "Note type"]) {
+        Some(section) => subsections(section)
+            .map(|heading| parse_note_type(heading))
+            .collect(),
+        None => Ok(vec![]),
+    }
+}
@@ -0,0 +1,414 @@
+use crate::ankigen::anki2::DEFAULT_ID;
+use crate::ankigen::db_model::deck::{DeckConfig, LeechAction};
+use crate::ankigen::deck::{
+    heading_metadata, section_metadata, section_name,
+};
+use crate::ankigen::metadata::{
+    front_matter_key, parse_bool, FrontMatterError, MetaEntry,
+    Metadata,
+};
+use crate::ankigen::note_type::note_type_id;
+use crate::markdown::ast::{Node, NodeType};
+use crate::markdown::error::ParseError;
+
+/// Name of the deck setting choosing its preset.
+pub const CONFIG: &str = "Config";
+
+/// Name of the options group every Anki collection has.
+pub const DEFAULT_PRESET: &str = "Default";
+
+/// A deck option preset that does not make a valid [`DeckConfig`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum PresetError {
+    Parse(ParseError),
+    FrontMatter(FrontMatterError),
+    UnknownSetting {
+        preset: String,
+        key: String,
+    },
+    InvalidSetting {
+        preset: String,
+        key: String,
+        value: String,
+    },
+    /// A `Config` setting with settings but no name.
+    MissingName,
+    /// A deck names a preset declared nowhere.
+    UnknownPreset {
+        name: String,
+    },
+    /// Two presets of the same name with different settings.
+    DuplicatePreset {
+        name: String,
+    },
+}
+
+impl std::fmt::Display for PresetError {
+    fn fmt(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+    ) -> std::fmt::Result {
+        match self {
+            Self::Parse(error) => write!(f, "{error}"),
+            Self::FrontMatter(error) => write!(f, "{error}"),
+            Self::UnknownSetting { preset, key } => {
+                write!(f, "preset {preset} has no setting {key}")
+            }
+            Self::InvalidSetting { preset, key, value } => {
+                write!(f, "invalid {key} of preset {preset}: {value}")
+            }
+            Self::MissingName => {
+                write!(f, "{CONFIG} setting without a preset name")
+            }
+            Self::UnknownPreset { name } => {
+                write!(f, "no preset named {name}")
+            }
+            Self::DuplicatePreset { name } => {
+                write!(f, "preset {name} is declared twice")
+            }
+        }
+    }
+}
+
+impl std::error::Error for PresetError {}
+
+impl From<ParseError> for PresetError {
+    fn from(error: ParseError) -> Self {
+        Self::Parse(error)
+    }
+}
+
+impl From<FrontMatterError> for PresetError {
+    fn from(error: FrontMatterError) -> Self {
+        Self::FrontMatter(error)
+    }
+}
+
+/// Id of the options group of a preset, derived from its name like
+/// [`note_type_id`]. The `Default` preset is Anki's own group.
+pub fn preset_id(name: &str) -> usize {
+    match name.eq_ignore_ascii_case(DEFAULT_PRESET) {
+        true => DEFAULT_ID,
+        false => note_type_id(name) as usize,
+    }
+}
+
+/// The words of a setting, from its value, separated by spaces or
+/// commas, and from the items of its list.
+fn words(entry: &MetaEntry) -> Vec<String> {
+    entry
+        .value
+        .split(|chr: char| chr.is_whitespace() || chr == ',')
+        .filter(|word| !word.is_empty())
+        .map(str::to_string)
+        .chain(entry.children.iter().map(|child| child.key.clone()))
+        .collect()
+}
+
+/// A learning step, in minutes: `10`, `30s`, `10m`, `1.5h` or `2d`.
+fn parse_minutes(step: &str) -> Option<f64> {
+    let (number, unit) =
+        match step.find(|chr: char| chr.is_alphabetic()) {
+            Some(idx) => step.split_at(idx),
+            None => (step, "m"),
+        };
+    let factor = match unit {
+        "s" => 1.0 / 60.0,
+        "m" => 1.0,
+        "h" => 60.0,
+        "d" => 24.0 * 60.0,
+        _ => return None,
+    };
+    number
+        .parse::<f64>()
+        .ok()
+        .filter(|number| number.is_finite() && *number >= 0.0)
+        .map(|number| number * factor)
+}
+
+/// An interval, in days: `4` or `4d`.
+fn parse_days(value: &str) -> Option<usize> {
+    value.strip_suffix('d').unwrap_or(value).parse().ok()
+}
+
+struct PresetParser<'a> {
+    config: &'a mut DeckConfig,
+}
+
+impl PresetParser<'_> {
+    fn invalid(&self, entry: &MetaEntry) -> PresetError {
+        PresetError::InvalidSetting {
+            preset: self.config.name.clone(),
+            key: entry.key.clone(),
+            value: entry.value.clone(),
+        }
+    }
+
+    fn number(
+        &self,
+        entry: &MetaEntry,
+    ) -> Result<usize, PresetError> {
+        entry.value.parse().map_err(|_| self.invalid(entry))
+    }
+
+    fn days(&self, entry: &MetaEntry) -> Result<usize, PresetError> {
+        parse_days(&entry.value).ok_or_else(|| self.invalid(entry))
+    }
+
+    fn bool(&self, entry: &MetaEntry) -> Result<bool, PresetError> {
+        parse_bool(&entry.value).ok_or_else(|| self.invalid(entry))
+    }
+
+    fn steps(
+        &self,
+        entry: &MetaEntry,
+    ) -> Result<Vec<f64>, PresetError> {
+        words(entry)
+            .iter()
+            .map(|step| parse_minutes(step))
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(|| self.invalid(entry))
+    }
+
+    fn apply(
+        &mut self,
+        entry: &MetaEntry,
+    ) -> Result<(), PresetError> {
+        let key = front_matter_key(&entry.key).to_ascii_lowercase();
+        match key.as_str() {
+            "name" => (),
+            "new per day" | "new cards per day" => {
+                self.config.new_config.per_day = self.number(entry)?
+            }
+            "reviews per day" | "maximum reviews per day" => {
+                self.config.review_config.cards_daily =
+                    self.number(entry)?
+            }
+            "learning steps" => {
+                self.config.new_config.delays = self.steps(entry)?
+            }
+            "relearning steps" => {
+                self.config.lapse_config.delays = self.steps(entry)?
+            }
+            "graduating interval" => {
+                self.config.new_config.intervals[0] =
+                    self.days(entry)?
+            }
+            "easy interval" => {
+                self.config.new_config.intervals[1] =
+                    self.days(entry)?
+            }
+            "maximum interval" | "max interval" => {
+                self.config.review_config.max_interval =
+                    self.days(entry)?
+            }
+            "leech threshold" => {
+                self.config.lapse_config.leech_fails =
+                    self.number(entry)?
+            }
+            "leech action" => {
+                self.config.lapse_config.leech_action =
+                    match entry.value.to_ascii_lowercase().as_str() {
+                        "suspend" | "suspend card" => {
+                            LeechAction::Suspend
+                        }
+                        "tag" | "tag only" => LeechAction::TagOnly,
+                        _ => return Err(self.invalid(entry)),
+                    }
+            }
+            "bury new" | "bury new siblings" => {
+                self.config.new_config.bury = self.bool(entry)?
+            }
+            "bury reviews" | "bury review siblings" => {
+                self.config.review_config.bury = self.bool(entry)?
+            }
+            _ => {
+                return Err(PresetError::UnknownSetting {
+                    preset: self.config.name.clone(),
+                    key: entry.key.clone(),
+                })
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The options group named `name`: Anki's defaults with the
+/// `entries` in their place. The settings are
+///
+/// - `new per day` and `reviews per day`,
+/// - `learning steps` and `relearning steps`, in minutes or with a
+///   `s`, `m`, `h` or `d` unit, as in `30s 10m 1d`,
+/// - `graduating interval`, `easy interval` and `maximum interval`,
+///   in days,
+/// - `leech threshold`, and `leech action`, `suspend` or `tag only`,
+/// - `bury new` and `bury reviews`, `true` or `false`.
+pub fn preset_from_entries(
+    name: &str,
+    entries: &[MetaEntry],
+) -> Result<DeckConfig, PresetError> {
+    let mut config = DeckConfig::new(name.to_string());
+    config.id = Some(preset_id(name));
+    let mut parser = PresetParser {
+        config: &mut config,
+    };
+    for entry in entries {
+        parser.apply(entry)?;
+    }
+    Ok(config)
+}
+
+/// Compiles the section of a preset: the heading names it, the
+/// settings of [`preset_from_entries`] are in front matter or a
+/// list right under it, or in a `Metadata` section.
+pub fn parse_preset(
+    heading: &Node,
+) -> Result<DeckConfig, PresetError> {
+    let mut metadata = heading_metadata(heading, &["Metadata"])?;
+    metadata.extend(section_metadata(heading));
+    preset_from_entries(&section_name(heading), &metadata.entries)
+}
+
+/// The presets of a `presets.md`, one per H1.
+pub fn parse_presets(
+    source: &str,
+) -> Result<Vec<DeckConfig>, PresetError> {
+    Node::parse_document(source)?
+        .iter()
+        .filter(|node| {
+            matches!(
+                node.node_type(),
+                NodeType::Heading { level: 1, .. }
+            )
+        })
+        .map(|heading| parse_preset(heading))
+        .collect()
+}
+
+/// The preset chosen by the `Config` setting of a deck.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DeckPreset {
+    /// `config: Slow`, a preset declared in a `presets.md` or by
+    /// another deck.
+    Named(String),
+    /// A `Config` setting with settings of its own, which declares
+    /// the preset:
+    ///
+    /// ```yaml
+    /// config:
+    ///   name: Slow
+    ///   new_per_day: 5
+    /// ```
+    Declared(Box<DeckConfig>),
+}
+
+impl DeckPreset {
+    /// The preset of a deck's settings, `None` without a `Config`
+    /// setting.
+    pub fn from_metadata(
+        metadata: &Metadata,
+    ) -> Result<Option<Self>, PresetError> {
+        let Some(entry) = metadata.get(CONFIG) else {
+            return Ok(None);
+        };
+        let name = match entry.value.is_empty() {
+            true => entry
+                .get("name")
+                .map(|name| name.value.clone())
+                .unwrap_or_default(),
+            false => entry.value.clone(),
+        };
+        if name.is_empty() {
+            return Err(PresetError::MissingName);
+        }
+        let has_settings = entry
+            .children
+            .iter()
+            .any(|child| !child.key.eq_ignore_ascii_case("name"));
+        Ok(Some(match has_settings {
+            true => Self::Declared(Box::new(preset_from_entries(
+                &name,
+                &entry.children,
+            )?)),
+            false => Self::Named(name),
+        }))
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            Self::Named(name) => name,
+            Self::Declared(config) => &config.name,
+        }
+    }
+
+    /// The `conf` of the deck, the id of its options group.
+    pub fn config_id(&self) -> usize {
+        preset_id(self.name())
+    }
+}
+
+/// The presets of a collection, starting with Anki's `Default`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Presets {
+    pub configs: Vec<DeckConfig>,
+}
+
+impl Default for Presets {
+    fn default() -> Self {
+        let mut config = DeckConfig::new(DEFAULT_PRESET.to_string());
+        config.id = Some(DEFAULT_ID);
+        Self {
+            configs: vec![config],
+        }
+    }
+}
+
+impl Presets {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&DeckConfig> {
+        self.configs
+            .iter()
+            .find(|config| config.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Adds a preset. A second declaration of a preset must have
+    /// the same settings; one of `Default` replaces Anki's defaults.
+    pub fn add(
+        &mut self,
+        config: DeckConfig,
+    ) -> Result<(), PresetError> {
+        let default = DeckConfig {
+            id: Some(DEFAULT_ID),
+            ..DeckConfig::new(DEFAULT_PRESET.to_string())
+        };
+        match self.configs.iter_mut().find(|other| {
+            other.name.eq_ignore_ascii_case(&config.name)
+        }) {
+            Some(other) if *other == config => (),
+            Some(other) if *other == default => *other = config,
+            Some(_) => {
+                return Err(PresetError::DuplicatePreset {
+                    name: config.name,
+                })
+            }
+            None => self.configs.push(config),
+        }
+        Ok(())
+    }
+
+    /// The options group of a deck's preset, once the presets
+    /// it may name are added.
+    pub fn resolve(
+        &self,
+        preset: &DeckPreset,
+    ) -> Result<&DeckConfig, PresetError> {
+        self.get(preset.name()).ok_or_else(|| {
+            PresetError::UnknownPreset {
+                name: preset.name().to_string(),
+            }
+        })
+    }
+}
@@ -0,0 +1,270 @@
+use crate::ankigen::db_model::model::{
+    Model, ModelField, ModelTemplate, ModelType,
+};
+use crate::ankigen::metadata::Metadata;
+
+/// Anki's stock note types.
+///
+/// Each has a fixed model id, so that every import updates the same
+/// note type instead of adding a copy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StockModel {
+    Basic,
+    BasicAndReversed,
+    BasicOptionalReversed,
+    BasicTypeIn,
+    Cloze,
+}
+
+pub const BASIC_MODEL_ID: u64 = 1_600_000_000_001;
+pub const BASIC_AND_REVERSED_MODEL_ID: u64 = 1_600_000_000_002;
+pub const BASIC_OPTIONAL_REVERSED_MODEL_ID: u64 = 1_600_000_000_003;
+pub const BASIC_TYPE_IN_MODEL_ID: u64 = 1_600_000_000_004;
+pub const CLOZE_MODEL_ID: u64 = 1_600_000_000_005;
+
+const ANSWER_SEPARATOR: &str = "\n\n<hr id=answer>\n\n";
+
+/// A template named in `- Templates:` metadata.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum TemplateKind {
+    Simple,
+    Reverse,
+    OptionalReverse,
+    TypeIn,
+    Cloze,
+}
+
+impl TemplateKind {
+    fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "simple" | "basic" | "front" => Some(Self::Simple),
+            "reverse" | "reversed" => Some(Self::Reverse),
+            "optional reverse" | "optional reversed" => {
+                Some(Self::OptionalReverse)
+            }
+            "type" | "type-in" | "type in" => Some(Self::TypeIn),
+            "cloze" => Some(Self::Cloze),
+            _ => None,
+        }
+    }
+}
+
+fn fields(names: &[&str]) -> Vec<ModelField> {
+    names
+        .iter()
+        .enumerate()
+        .map(|(ordinal, name)| {
+            ModelField::new(name.to_string(), ordinal)
+        })
+        .collect()
+}
+
+fn template(
+    name: &str,
+    question: &str,
+    answer: &str,
+) -> ModelTemplate {
+    ModelTemplate::new(
+        name.to_string(),
+        question.to_string(),
+        answer.to_string(),
+    )
+}
+
+fn front_template() -> ModelTemplate {
+    template(
+        "Card 1",
+        "{{Front}}",
+        &format!("{{{{FrontSide}}}}{ANSWER_SEPARATOR}{{{{Back}}}}"),
+    )
+}
+
+fn reverse_template(question: &str) -> ModelTemplate {
+    template(
+        "Card 2",
+        question,
+        &format!("{{{{FrontSide}}}}{ANSWER_SEPARATOR}{{{{Front}}}}"),
+    )
+}
+
+impl StockModel {
+    pub const ALL: [StockModel; 5] = [
+        Self::Basic,
+        Self::BasicAndReversed,
+        Self::BasicOptionalReversed,
+        Self::BasicTypeIn,
+        Self::Cloze,
+    ];
+
+    pub fn model_id(&self) -> u64 {
+        match self {
+            Self::Basic => BASIC_MODEL_ID,
+            Self::BasicAndReversed => BASIC_AND_REVERSED_MODEL_ID,
+            Self::BasicOptionalReversed => {
+                BASIC_OPTIONAL_REVERSED_MODEL_ID
+            }
+            Self::BasicTypeIn => BASIC_TYPE_IN_MODEL_ID,
+            Self::Cloze => CLOZE_MODEL_ID,
+        }
+    }
+
+    /// The name Anki gives the note type.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Basic => "Basic",
+            Self::BasicAndReversed => "Basic (and reversed card)",
+            Self::BasicOptionalReversed => {
+                "Basic (optional reversed card)"
+            }
+            Self::BasicTypeIn => "Basic (type in the answer)",
+            Self::Cloze => "Cloze",
+        }
+    }
+
+    /// The stock model Anki names `name`, compared
+    /// case-insensitively.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|model| model.name().eq_ignore_ascii_case(name))
+    }
+
+    /// The stock model with the templates listed in `- Templates:`
+    /// metadata: `Simple` alone is Basic, `Simple` and `Reverse` is
+    /// Basic (and reversed card), `Simple` and `Optional reverse` is
+    /// Basic (optional reversed card), `Type` is Basic (type in the
+    /// answer) and `Cloze` is Cloze. Names are compared
+    /// case-insensitively, other combinations give `None`.
+    pub fn from_templates<S: AsRef<str>>(
+        names: &[S],
+    ) -> Option<Self> {
+        let mut kinds = names
+            .iter()
+            .map(|name| TemplateKind::from_name(name.as_ref()))
+            .collect::<Option<Vec<_>>>()?;
+        kinds.sort();
+        kinds.dedup();
+        match kinds.as_slice() {
+            [TemplateKind::Simple] => Some(Self::Basic),
+            [TemplateKind::Simple, TemplateKind::Reverse] => {
+                Some(Self::BasicAndReversed)
+            }
+            [TemplateKind::Simple, TemplateKind::OptionalReverse] => {
+                Some(Self::BasicOptionalReversed)
+            }
+            [TemplateKind::TypeIn] => Some(Self::BasicTypeIn),
+            [TemplateKind::Cloze] => Some(Self::Cloze),
+            _ => None,
+        }
+    }
+
+    /// The stock model of a note's `- Templates:` metadata, `None`
+    /// when it has none or they match no stock model.
+    pub fn from_metadata(metadata: &Metadata) -> Option<Self> {
+        let templates = metadata.get("Templates")?;
+        let names = match templates.children.is_empty() {
+            true => templates
+                .value
+                .split(',')
+                .map(str::to_string)
+                .collect::<Vec<_>>(),
+            false => templates
+                .children
+                .iter()
+                .map(|entry| entry.key.clone())
+                .collect(),
+        };
+        Self::from_templates(&names)
+    }
+
+    pub fn model(&self) -> Model {
+        let (field_names, templates, model_type): (&[&str], _, _) =
+            match self {
+                Self::Basic => (
+                    &["Front", "Back"],
+                    vec![front_template()],
+                    ModelType::FrontBack,
+                ),
+                Self::BasicAndReversed => (
+                    &["Front", "Back"],
+                    vec![front_template(), reverse_template("{{Back}}")],
+                    ModelType::FrontBack,
+                ),
+                Self::BasicOptionalReversed => (
+                    &["Front", "Back", "Add Reverse"],
+                    vec![
+                        front_template(),
+                        reverse_template(
+                            "{{#Add Reverse}}{{Back}}{{/Add Reverse}}",
+                        ),
+                    ],
+                    ModelType::FrontBack,
+                ),
+                Self::BasicTypeIn => (
+                    &["Front", "Back"],
+                    vec![template(
+                        "Card 1",
+                        "{{Front}}\n\n{{type:Back}}",
+                        &format!(
+                            "{{{{Front}}}}{ANSWER_SEPARATOR}{{{{type:Back}}}}"
+                        ),
+                    )],
+                    ModelType::FrontBack,
+                ),
+                Self::Cloze => (
+                    &["Text", "Back Extra"],
+                    vec![template(
+                        "Cloze",
+                        "{{cloze:Text}}",
+                        "{{cloze:Text}}<br>\n{{Back Extra}}",
+                    )],
+                    ModelType::Cloze,
+                ),
+            };
+
+        let css = match self {
+            Self::Cloze => format!(
+                "{}\n\n.cloze {{\n    font-weight: bold;\n    color: blue;\n}}\n\
+                 .nightMode .cloze {{\n    color: lightblue;\n}}",
+                Model::default_css()
+            ),
+            _ => Model::default_css(),
+        };
+
+        let mut model = Model::new(
+            Some(css),
+            fields(field_names),
+            self.model_id(),
+            None,
+            None,
+            self.name().to_string(),
+            0,
+            templates,
+            model_type,
+        );
+        // Which fields a card needs to be generated, as older Anki
+        // versions computed it. Newer ones work it out themselves and
+        // cloze note types never had it.
+        model.req = match self {
+            Self::Basic | Self::BasicTypeIn => {
+                vec![(0, "any".to_string(), vec![0])]
+            }
+            Self::BasicAndReversed => vec![
+                (0, "any".to_string(), vec![0]),
+                (1, "any".to_string(), vec![1]),
+            ],
+            Self::BasicOptionalReversed => vec![
+                (0, "any".to_string(), vec![0]),
+                (1, "all".to_string(), vec![1, 2]),
+            ],
+            Self::Cloze => vec![],
+        };
+        model
+    }
+}
+
+/// Anki's stock Cloze note type: the deletions are written in
+/// `Text`, `Back Extra` is shown on the answer only.
+pub fn cloze_model() -> Model {
+    StockModel::Cloze.model()
+}
@@ -0,0 +1,106 @@
+use crate::ankigen::db_model::note::NoteTag;
+use crate::ankigen::metadata::{MetaEntry, Metadata};
+use crate::markdown::ast::{Node, NodeType, Text};
+
+/// Name of the metadata entry listing tags.
+pub const TAGS: &str = "Tags";
+
+/// A tag Anki would not accept, see [`NoteTag::new`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct TagError {
+    pub tag: String,
+    pub reason: String,
+}
+
+impl std::fmt::Display for TagError {
+    fn fmt(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+    ) -> std::fmt::Result {
+        write!(f, "invalid tag {}: {}", self.tag, self.reason)
+    }
+}
+
+impl std::error::Error for TagError {}
+
+/// The tags of a paragraph made only of `#tag` words, like
+/// `#french #lang::verbs`, without their `#`. `None` for any other
+/// node.
+pub fn tag_line(node: &Node) -> Option<Vec<String>> {
+    if !matches!(node.node_type(), NodeType::Paragraph) {
+        return None;
+    }
+    let text = node
+        .inline_content()
+        .iter()
+        .map(Text::to_plain_text)
+        .collect::<String>();
+    let words = text.split_whitespace().collect::<Vec<_>>();
+    let is_tag_line = !words.is_empty()
+        && words
+            .iter()
+            .all(|word| word.len() > 1 && word.starts_with('#'));
+    is_tag_line.then(|| {
+        words.iter().map(|word| word[1..].to_string()).collect()
+    })
+}
+
+/// The tag of an item of a `- Tags:` list, which the list style
+/// reads as `key: value` when it holds a `::`.
+fn child_tag(child: &MetaEntry) -> String {
+    match child.value.is_empty() {
+        true => child.key.clone(),
+        false => format!("{}:{}", child.key, child.value),
+    }
+}
+
+/// The tags of every `tags` entry of `metadata`: the words of its
+/// value, separated by spaces or commas, and its children, so that
+/// `tags: a, b` in front matter, `- Tags: a b` and a `- Tags:` list
+/// give the same tags. A leading `#` is dropped.
+pub fn metadata_tags(
+    metadata: &Metadata,
+) -> Result<Vec<NoteTag>, TagError> {
+    let names = metadata
+        .entries
+        .iter()
+        .filter(|entry| entry.key.eq_ignore_ascii_case(TAGS))
+        .flat_map(|entry| {
+            entry
+                .value
+                .split(|chr: char| chr.is_whitespace() || chr == ',')
+                .map(str::to_string)
+                .chain(entry.children.iter().map(child_tag))
+        })
+        .map(|name| name.trim().trim_start_matches('#').to_string())
+        .filter(|name| !name.is_empty());
+
+    let mut tags = vec![];
+    for name in names {
+        let tag = NoteTag::new(&name).map_err(|reason| TagError {
+            tag: name.clone(),
+            reason,
+        })?;
+        tags = inherit(&tags, vec![tag]);
+    }
+    Ok(tags)
+}
+
+/// The tags of a parent deck followed by the `own` tags of a subdeck
+/// or note that it does not have already. Like Anki, tags differing
+/// only by case are the same tag.
+pub fn inherit(
+    parent: &[NoteTag],
+    own: Vec<NoteTag>,
+) -> Vec<NoteTag> {
+    let mut tags = parent.to_vec();
+    for tag in own {
+        if !tags
+            .iter()
+            .any(|other| other.name.eq_ignore_ascii_case(&tag.name))
+        {
+            tags.push(tag);
+        }
+    }
+    tags
+}
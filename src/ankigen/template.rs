@@ -0,0 +1,217 @@
+/// A card template, `qfmt` or `afmt`, split on its `{{...}}` tags.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TemplateNode {
+    Text(String),
+    /// `{{Field}}` or `{{filter:Field}}`, the filters in the order
+    /// they are written.
+    Replacement {
+        field: String,
+        filters: Vec<String>,
+    },
+    /// `{{#Field}}...{{/Field}}`, shown when the field is not empty.
+    Conditional {
+        field: String,
+        children: Vec<TemplateNode>,
+    },
+    /// `{{^Field}}...{{/Field}}`, shown when the field is empty.
+    NegatedConditional {
+        field: String,
+        children: Vec<TemplateNode>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TemplateError {
+    /// A `{{` without its `}}`.
+    Unterminated { offset: usize },
+    /// A `{{#Field}}` or `{{^Field}}` without its `{{/Field}}`.
+    UnclosedConditional { field: String },
+    /// A `{{/Field}}` that closes nothing, or another field.
+    UnexpectedClose { field: String },
+}
+
+impl std::fmt::Display for TemplateError {
+    fn fmt(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+    ) -> std::fmt::Result {
+        match self {
+            Self::Unterminated { offset } => {
+                write!(f, "unterminated {{{{ at byte {offset}")
+            }
+            Self::UnclosedConditional { field } => {
+                write!(f, "missing {{{{/{field}}}}}")
+            }
+            Self::UnexpectedClose { field } => {
+                write!(f, "unexpected {{{{/{field}}}}}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for TemplateError {}
+
+/// Replacements filled in by Anki rather than from the note's
+/// fields.
+pub const SPECIAL_FIELDS: [&str; 7] = [
+    "FrontSide",
+    "Tags",
+    "Type",
+    "Deck",
+    "Subdeck",
+    "Card",
+    "CardFlag",
+];
+
+enum Token<'a> {
+    Text(&'a str),
+    Tag(&'a str),
+}
+
+fn tokenize(template: &str) -> Result<Vec<Token<'_>>, TemplateError> {
+    let mut tokens = vec![];
+    let mut offset = 0;
+    while let Some(start) = template[offset..].find("{{") {
+        let start = offset + start;
+        if start > offset {
+            tokens.push(Token::Text(&template[offset..start]));
+        }
+        let end = template[start..]
+            .find("}}")
+            .ok_or(TemplateError::Unterminated { offset: start })?;
+        tokens.push(Token::Tag(
+            template[start + 2..start + end].trim(),
+        ));
+        offset = start + end + 2;
+    }
+    if offset < template.len() {
+        tokens.push(Token::Text(&template[offset..]));
+    }
+    Ok(tokens)
+}
+
+fn parse_tokens<'a>(
+    tokens: &mut impl Iterator<Item = Token<'a>>,
+    closing: Option<&str>,
+) -> Result<Vec<TemplateNode>, TemplateError> {
+    let mut nodes = vec![];
+    while let Some(token) = tokens.next() {
+        let tag = match token {
+            Token::Text(text) => {
+                nodes.push(TemplateNode::Text(text.to_string()));
+                continue;
+            }
+            Token::Tag(tag) => tag,
+        };
+
+        if let Some(field) = tag.strip_prefix('#') {
+            let field = field.trim().to_string();
+            nodes.push(TemplateNode::Conditional {
+                children: parse_tokens(tokens, Some(&field))?,
+                field,
+            });
+        } else if let Some(field) = tag.strip_prefix('^') {
+            let field = field.trim().to_string();
+            nodes.push(TemplateNode::NegatedConditional {
+                children: parse_tokens(tokens, Some(&field))?,
+                field,
+            });
+        } else if let Some(field) = tag.strip_prefix('/') {
+            let field = field.trim();
+            return match closing {
+                Some(closing) if closing == field => Ok(nodes),
+                _ => Err(TemplateError::UnexpectedClose {
+                    field: field.to_string(),
+                }),
+            };
+        } else {
+            let mut parts =
+                tag.split(':').map(str::trim).collect::<Vec<_>>();
+            let field = parts.pop().unwrap_or_default().to_string();
+            nodes.push(TemplateNode::Replacement {
+                field,
+                filters: parts
+                    .into_iter()
+                    .map(str::to_string)
+                    .collect(),
+            });
+        }
+    }
+
+    match closing {
+        Some(field) => Err(TemplateError::UnclosedConditional {
+            field: field.to_string(),
+        }),
+        None => Ok(nodes),
+    }
+}
+
+pub fn parse_template(
+    template: &str,
+) -> Result<Vec<TemplateNode>, TemplateError> {
+    parse_tokens(&mut tokenize(template)?.into_iter(), None)
+}
+
+/// Whether the template shows any of the note's fields, given which
+/// fields are not empty. This is Anki's rule for generating a card:
+/// the text around the fields and the special fields do not count.
+pub fn renders_with_fields(
+    nodes: &[TemplateNode],
+    nonempty: &dyn Fn(&str) -> bool,
+) -> bool {
+    nodes.iter().any(|node| match node {
+        TemplateNode::Text(_) => false,
+        TemplateNode::Replacement { field, .. } => {
+            !SPECIAL_FIELDS.contains(&field.as_str())
+                && nonempty(field)
+        }
+        TemplateNode::Conditional { field, children } => {
+            nonempty(field) && renders_with_fields(children, nonempty)
+        }
+        TemplateNode::NegatedConditional { field, children } => {
+            !nonempty(field)
+                && renders_with_fields(children, nonempty)
+        }
+    })
+}
+
+/// Every field name the template refers to, conditionals included,
+/// in order of appearance.
+pub fn referenced_fields(nodes: &[TemplateNode]) -> Vec<&str> {
+    let mut fields = vec![];
+    for node in nodes {
+        match node {
+            TemplateNode::Text(_) => (),
+            TemplateNode::Replacement { field, .. } => {
+                fields.push(field.as_str())
+            }
+            TemplateNode::Conditional { field, children }
+            | TemplateNode::NegatedConditional { field, children } => {
+                fields.push(field.as_str());
+                fields.extend(referenced_fields(children));
+            }
+        }
+    }
+    fields
+}
+
+/// Fields shown through the `cloze` filter, the ones a cloze note
+/// takes its deletions from.
+pub fn cloze_fields(nodes: &[TemplateNode]) -> Vec<&str> {
+    let mut fields = vec![];
+    for node in nodes {
+        match node {
+            TemplateNode::Text(_) => (),
+            TemplateNode::Replacement { field, filters } => {
+                if filters.iter().any(|filter| filter == "cloze") {
+                    fields.push(field.as_str());
+                }
+            }
+            TemplateNode::Conditional { children, .. }
+            | TemplateNode::NegatedConditional { children, .. } => {
+                fields.extend(cloze_fields(children))
+            }
+        }
+    }
+    fields
+}
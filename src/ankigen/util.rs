@@ -1,3 +1,4 @@
+use sha1::Sha1;
 use sha2::{Digest, Sha256};
 
 const BASE91: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789!#$%&'()*+,-./:;<=>?@[\\]^_`{|}~";
@@ -15,7 +16,7 @@ pub fn base91_encode(data: &[String]) -> String {
     let hash_bytes = hash.finalize()[..8].to_vec();
     let mut hash_int = 0;
     for byte in hash_bytes {
-        hash_int = hash_int << 8;
+        hash_int <<= 8;
         hash_int += byte as u64;
     }
 
@@ -29,3 +30,104 @@ pub fn base91_encode(data: &[String]) -> String {
 
     encoded.chars().rev().collect::<String>()
 }
+
+/// Value of the `src` attribute of an `<img>` tag, without quotes.
+fn image_source(tag: &str) -> Option<&str> {
+    let lower = tag.to_ascii_lowercase();
+    if !lower.starts_with("<img") {
+        return None;
+    }
+    let start = lower.find("src=")? + "src=".len();
+    let rest = &tag[start..];
+    match rest.chars().next()? {
+        quote @ ('"' | '\'') => rest[1..].split(quote).next(),
+        _ => rest
+            .split(|chr: char| chr.is_whitespace() || chr == '>')
+            .next(),
+    }
+}
+
+fn decode_entity(entity: &str) -> Option<char> {
+    match entity {
+        "amp" => Some('&'),
+        "lt" => Some('<'),
+        "gt" => Some('>'),
+        "quot" => Some('"'),
+        "apos" => Some('\''),
+        "nbsp" => Some(' '),
+        _ => {
+            let code = entity.strip_prefix('#')?;
+            let code = match code.strip_prefix(['x', 'X']) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                None => code.parse::<u32>().ok()?,
+            };
+            char::from_u32(code)
+        }
+    }
+}
+
+fn decode_entities(text: &str) -> String {
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(idx) = rest.find('&') {
+        decoded.push_str(&rest[..idx]);
+        rest = &rest[idx..];
+        let chr =
+            rest[1..].find(';').filter(|end| *end <= 10).and_then(
+                |end| Some((decode_entity(&rest[1..=end])?, end)),
+            );
+        match chr {
+            Some((chr, end)) => {
+                decoded.push(chr);
+                rest = &rest[end + 2..];
+            }
+            None => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+/// Plain text of a note field the way Anki computes it for the sort
+/// field and the checksum: comments, styles, scripts and tags are
+/// removed, images are replaced by their file name padded with
+/// spaces and entities are decoded.
+pub fn strip_html(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(idx) = rest.find('<') {
+        text.push_str(&rest[..idx]);
+        rest = &rest[idx..];
+        let lower = rest.to_ascii_lowercase();
+        let end = if lower.starts_with("<!--") {
+            lower.find("-->").map(|end| end + "-->".len())
+        } else if lower.starts_with("<style") {
+            lower.find("</style>").map(|end| end + "</style>".len())
+        } else if lower.starts_with("<script") {
+            lower.find("</script>").map(|end| end + "</script>".len())
+        } else {
+            lower.find('>').map(|end| end + 1)
+        };
+        let Some(end) = end else {
+            break;
+        };
+        if let Some(src) = image_source(&rest[..end]) {
+            text.push_str(&format!(" {src} "));
+        }
+        rest = &rest[end..];
+    }
+    text.push_str(rest);
+    decode_entities(&text)
+}
+
+/// The `csum` of a note: the first 4 bytes of the SHA-1 of the
+/// stripped first field, which is the first 8 hex digits as a
+/// number. Anki finds duplicate notes with it.
+pub fn field_checksum(field: &str) -> i64 {
+    let digest = Sha1::digest(strip_html(field).as_bytes());
+    u32::from_be_bytes([digest[0], digest[1], digest[2], digest[3]])
+        as i64
+}
@@ -0,0 +1,499 @@
+use std::path::{Path, PathBuf};
+
+use crate::ankigen::db_model;
+use crate::ankigen::db_model::model::Model;
+use crate::ankigen::deck::{register_guids, Deck, DeckError};
+use crate::ankigen::guid::{GuidCollision, GuidRegistry};
+use crate::ankigen::information::SimpleInformation;
+use crate::ankigen::note_type::{parse_note_types, NoteTypeError};
+use crate::ankigen::preset::{parse_presets, PresetError, Presets};
+
+/// The file describing the deck of its folder.
+pub const DECK_FILE: &str = "deck.md";
+
+/// The file declaring note types, read in any folder.
+pub const MODELS_FILE: &str = "models.md";
+
+/// The file declaring deck option presets, read in any folder.
+pub const PRESETS_FILE: &str = "presets.md";
+
+/// The file of ignore patterns at the root of a workspace.
+pub const IGNORE_FILE: &str = ".ankiignore";
+
+/// Separator between the parts of a nested deck name, as in
+/// `Japanese::Kanji`.
+pub const DECK_SEPARATOR: &str = "::";
+
+#[derive(Debug)]
+pub enum WorkspaceError {
+    Io {
+        path: PathBuf,
+        error: std::io::Error,
+    },
+    Deck {
+        path: PathBuf,
+        error: DeckError,
+    },
+    NoteType {
+        path: PathBuf,
+        error: NoteTypeError,
+    },
+    Preset {
+        path: PathBuf,
+        error: PresetError,
+    },
+    /// Two folders give decks of the same name.
+    DuplicateDeck {
+        name: String,
+        first: PathBuf,
+        second: PathBuf,
+    },
+}
+
+impl std::fmt::Display for WorkspaceError {
+    fn fmt(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+    ) -> std::fmt::Result {
+        match self {
+            Self::Io { path, error } => {
+                write!(f, "{}: {error}", path.display())
+            }
+            Self::Deck { path, error } => {
+                write!(f, "{}: {error}", path.display())
+            }
+            Self::NoteType { path, error } => {
+                write!(f, "{}: {error}", path.display())
+            }
+            Self::Preset { path, error } => {
+                write!(f, "{}: {error}", path.display())
+            }
+            Self::DuplicateDeck {
+                name,
+                first,
+                second,
+            } => write!(
+                f,
+                "{} and {} are both deck {name}",
+                first.display(),
+                second.display()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for WorkspaceError {}
+
+/// A markdown file of a workspace and what it holds.
+#[derive(Debug, Clone)]
+pub struct SourceFile {
+    pub path: PathBuf,
+    pub deck: Deck,
+}
+
+/// A folder of a workspace, turned into a deck.
+#[derive(Debug, Clone)]
+pub struct WorkspaceDeck {
+    /// The full name, with the names of the parent decks.
+    pub name: String,
+    pub path: PathBuf,
+    /// The `deck.md` of the folder, if any, then its other markdown
+    /// files, ordered by name.
+    pub files: Vec<SourceFile>,
+}
+
+impl WorkspaceDeck {
+    /// The `deck.md` of the folder.
+    pub fn deck_file(&self) -> Option<&SourceFile> {
+        self.files.first().filter(|file| {
+            file.path.file_name() == Some(DECK_FILE.as_ref())
+        })
+    }
+
+    /// The notes of every file of the folder, in order, without
+    /// those of the [`WorkspaceDeck::file_subdecks`].
+    pub fn information(
+        &self,
+    ) -> impl Iterator<Item = &SimpleInformation> {
+        self.files.iter().flat_map(|file| &file.deck.information)
+    }
+
+    /// The notes of every file of the folder and of their subdecks,
+    /// as in [`Deck::notes`], each with its full name in the
+    /// workspace rather than the H1 of its file.
+    pub fn notes(&self) -> Vec<(String, &SimpleInformation)> {
+        self.files
+            .iter()
+            .flat_map(|file| file.deck.notes_in(&self.name))
+            .collect()
+    }
+
+    /// The subdecks written as headings in the files of the folder,
+    /// see [`Deck::all_subdecks`], named after the folder's deck
+    /// rather than the first H1 of their file.
+    pub fn file_subdecks(&self) -> Vec<(String, &Deck)> {
+        self.files
+            .iter()
+            .flat_map(|file| {
+                file.deck.all_subdecks().into_iter().map(
+                    |(name, deck)| {
+                        let name = &name[file.deck.name.len()..];
+                        (format!("{}{name}", self.name), deck)
+                    },
+                )
+            })
+            .collect()
+    }
+
+    /// The deck as stored in the collection, with the description,
+    /// the generated id and the preset of the `deck.md`.
+    pub fn to_db_deck(&self) -> db_model::deck::Deck {
+        let mut deck = db_model::deck::Deck::new(self.name.clone());
+        if let Some(file) = self.deck_file() {
+            deck.description = file.deck.description.clone();
+            deck.id = file.deck.metadata.autogen.id;
+            deck.config_id = file
+                .deck
+                .metadata
+                .preset
+                .as_ref()
+                .map(|preset| preset.config_id());
+        }
+        deck
+    }
+}
+
+/// A folder tree of markdown decks.
+#[derive(Debug, Clone)]
+pub struct Workspace {
+    pub root: PathBuf,
+    /// Every deck, each parent before its subdecks and the subdecks
+    /// ordered by folder name.
+    pub decks: Vec<WorkspaceDeck>,
+    /// The note types of all the `models.md` files.
+    pub note_types: Vec<Model>,
+    /// The presets of all the `presets.md` files and those the decks
+    /// declare.
+    pub presets: Presets,
+    /// Notes of the whole workspace that got the same GUID, like a
+    /// word defined in two files of one folder. The load does not
+    /// fail on them, the caller decides which note to keep.
+    pub guid_collisions: Vec<GuidCollision>,
+}
+
+impl Workspace {
+    /// The notes of every deck, in the order of the decks.
+    pub fn notes(&self) -> Vec<(String, &SimpleInformation)> {
+        self.decks.iter().flat_map(WorkspaceDeck::notes).collect()
+    }
+
+    /// Registers the GUIDs of all the notes in one registry, keyed
+    /// on their full deck names, and returns them in order.
+    pub fn register_guids(
+        &self,
+        registry: &mut GuidRegistry,
+    ) -> Vec<String> {
+        register_guids(self.notes(), registry)
+    }
+}
+
+/// Whether `text` matches the glob `pattern`: `*` and `?` match
+/// within a path component, `**` across components.
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    fn matches(pattern: &[u8], text: &[u8]) -> bool {
+        match pattern {
+            [] => text.is_empty(),
+            [b'*', b'*'] => true,
+            [b'*', b'*', rest @ ..] => {
+                let rest = rest.strip_prefix(b"/").unwrap_or(rest);
+                (0..=text.len()).any(|idx| {
+                    (idx == 0 || text[idx - 1] == b'/')
+                        && matches(rest, &text[idx..])
+                })
+            }
+            [b'*', rest @ ..] => (0..=text.len())
+                .take_while(|idx| *idx == 0 || text[idx - 1] != b'/')
+                .any(|idx| matches(rest, &text[idx..])),
+            [b'?', rest @ ..] => match text {
+                [chr, text @ ..] if *chr != b'/' => {
+                    matches(rest, text)
+                }
+                _ => false,
+            },
+            [chr, rest @ ..] => match text {
+                [first, text @ ..] if first == chr => {
+                    matches(rest, text)
+                }
+                _ => false,
+            },
+        }
+    }
+    matches(pattern.as_bytes(), text.as_bytes())
+}
+
+/// Reads a workspace: the folder at the root is the parent deck,
+/// every folder below it a subdeck.
+///
+/// A deck is named after the first H1 of the `deck.md` of its folder,
+/// else after the folder, and a subdeck's name is prefixed with the
+/// name of its parent: `foo/` under a root deck `Parent` gives
+/// `Parent::foo`. The notes of a deck are those of all the markdown
+/// files of its folder, `deck.md` first. `models.md` files declare
+/// note types and `presets.md` files deck option presets instead.
+/// Every preset a deck names must be declared somewhere in the
+/// workspace.
+///
+/// Hidden files and folders are skipped, as are the paths matching
+/// a pattern given to [`WorkspaceLoader::with_ignore`] or written in
+/// the [`IGNORE_FILE`] of the root. A pattern with a `/` matches the
+/// path relative to the root, one without matches any file or folder
+/// name, and a trailing `/` only matches folders. Lines of the
+/// ignore file starting with `#` are comments. Symbolic links to
+/// folders are not followed, so a link back up the tree cannot make
+/// the walk endless.
+///
+/// Files and folders are read in the order of their names, so the
+/// same tree always gives the same decks in the same order.
+pub struct WorkspaceLoader {
+    root: PathBuf,
+    ignore: Vec<String>,
+}
+
+impl WorkspaceLoader {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            ignore: vec![],
+        }
+    }
+
+    pub fn with_ignore(mut self, pattern: &str) -> Self {
+        self.ignore.push(pattern.to_string());
+        self
+    }
+
+    fn is_ignored(
+        &self,
+        relative: &str,
+        name: &str,
+        is_dir: bool,
+    ) -> bool {
+        name.starts_with('.')
+            || self.ignore.iter().any(|pattern| {
+                let (pattern, dir_only) =
+                    match pattern.strip_suffix('/') {
+                        Some(pattern) => (pattern, true),
+                        None => (pattern.as_str(), false),
+                    };
+                if dir_only && !is_dir {
+                    return false;
+                }
+                match pattern.strip_prefix('/') {
+                    Some(pattern) => glob_match(pattern, relative),
+                    None if pattern.contains('/') => {
+                        glob_match(pattern, relative)
+                    }
+                    None => glob_match(pattern, name),
+                }
+            })
+    }
+
+    pub fn load(mut self) -> Result<Workspace, WorkspaceError> {
+        let ignore_path = self.root.join(IGNORE_FILE);
+        if ignore_path.is_file() {
+            let patterns = read(&ignore_path)?;
+            self.ignore.extend(
+                patterns
+                    .lines()
+                    .map(str::trim)
+                    .filter(|line| {
+                        !line.is_empty() && !line.starts_with('#')
+                    })
+                    .map(str::to_string),
+            );
+        }
+
+        let mut workspace = Workspace {
+            root: self.root.clone(),
+            decks: vec![],
+            note_types: vec![],
+            presets: Presets::new(),
+            guid_collisions: vec![],
+        };
+        self.load_folder(&self.root, "", None, &mut workspace)?;
+        resolve_presets(&mut workspace)?;
+        let mut registry = GuidRegistry::new();
+        workspace.register_guids(&mut registry);
+        workspace.guid_collisions = registry.collisions().to_vec();
+        Ok(workspace)
+    }
+
+    fn load_folder(
+        &self,
+        path: &Path,
+        relative: &str,
+        parent: Option<&str>,
+        workspace: &mut Workspace,
+    ) -> Result<(), WorkspaceError> {
+        let mut entries = std::fs::read_dir(path)
+            .and_then(|entries| {
+                entries
+                    .map(|entry| entry.map(|entry| entry.path()))
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .map_err(|error| WorkspaceError::Io {
+                path: path.to_path_buf(),
+                error,
+            })?;
+        entries.sort();
+
+        let mut files = vec![];
+        let mut folders = vec![];
+        for entry in entries {
+            let name = entry
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            let entry_relative = match relative {
+                "" => name.clone(),
+                relative => format!("{relative}/{name}"),
+            };
+            let is_dir = entry
+                .symlink_metadata()
+                .is_ok_and(|metadata| metadata.is_dir());
+            if self.is_ignored(&entry_relative, &name, is_dir) {
+                continue;
+            }
+            if is_dir {
+                folders.push((entry, entry_relative));
+            } else if name == MODELS_FILE {
+                let source = read(&entry)?;
+                let note_types =
+                    parse_note_types(&source).map_err(|error| {
+                        WorkspaceError::NoteType {
+                            path: entry.clone(),
+                            error,
+                        }
+                    })?;
+                workspace.note_types.extend(note_types);
+            } else if name == PRESETS_FILE {
+                let source = read(&entry)?;
+                let error = |error| WorkspaceError::Preset {
+                    path: entry.clone(),
+                    error,
+                };
+                for preset in parse_presets(&source).map_err(error)? {
+                    workspace.presets.add(preset).map_err(error)?;
+                }
+            } else if entry.extension().is_some_and(|ext| ext == "md")
+            {
+                let source = read(&entry)?;
+                let deck = Deck::from_markdown(&source).map_err(
+                    |error| WorkspaceError::Deck {
+                        path: entry.clone(),
+                        error,
+                    },
+                )?;
+                files.push(SourceFile { path: entry, deck });
+            }
+        }
+        // `deck.md` first, the sort keeps the others by name.
+        files.sort_by_key(|file| {
+            file.path.file_name() != Some(DECK_FILE.as_ref())
+        });
+
+        let folder_name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let own_name = files
+            .first()
+            .filter(|file| {
+                file.path.file_name() == Some(DECK_FILE.as_ref())
+            })
+            .map_or(folder_name, |file| file.deck.name.clone());
+        let name = match parent {
+            Some(parent) => {
+                format!("{parent}{DECK_SEPARATOR}{own_name}")
+            }
+            None => own_name,
+        };
+
+        if let Some(other) =
+            workspace.decks.iter().find(|deck| deck.name == name)
+        {
+            return Err(WorkspaceError::DuplicateDeck {
+                name,
+                first: other.path.clone(),
+                second: path.to_path_buf(),
+            });
+        }
+
+        let position = workspace.decks.len();
+        let has_files = !files.is_empty();
+        workspace.decks.push(WorkspaceDeck {
+            name: name.clone(),
+            path: path.to_path_buf(),
+            files,
+        });
+        for (folder, folder_relative) in folders {
+            self.load_folder(
+                &folder,
+                &folder_relative,
+                Some(&name),
+                workspace,
+            )?;
+        }
+        // A folder without markdown, below it or in it, is no deck.
+        if !has_files && workspace.decks.len() == position + 1 {
+            workspace.decks.pop();
+        }
+        Ok(())
+    }
+}
+
+/// Adds the presets the decks declare, then checks that those they
+/// name exist.
+fn resolve_presets(
+    workspace: &mut Workspace,
+) -> Result<(), WorkspaceError> {
+    let files = workspace.decks.iter().flat_map(|deck| &deck.files);
+    for file in files.clone() {
+        for preset in file.deck.presets() {
+            workspace.presets.add(preset.clone()).map_err(
+                |error| WorkspaceError::Preset {
+                    path: file.path.clone(),
+                    error,
+                },
+            )?;
+        }
+    }
+    for file in files {
+        let decks = std::iter::once(&file.deck).chain(
+            file.deck
+                .all_subdecks()
+                .into_iter()
+                .map(|(_, deck)| deck),
+        );
+        for deck in decks {
+            if let Some(preset) = &deck.metadata.preset {
+                workspace.presets.resolve(preset).map_err(
+                    |error| WorkspaceError::Preset {
+                        path: file.path.clone(),
+                        error,
+                    },
+                )?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn read(path: &Path) -> Result<String, WorkspaceError> {
+    std::fs::read_to_string(path).map_err(|error| {
+        WorkspaceError::Io {
+            path: path.to_path_buf(),
+            error,
+        }
+    })
+}
@@ -1,35 +1,182 @@
-use std::{rc::Rc, usize};
+use std::{ops::Range, rc::Rc};
 
-#[derive(Debug, Clone)]
+pub use pulldown_cmark::Alignment;
+
+use crate::markdown::cloze::{extract_clozes, HINT_SEPARATOR};
+use crate::markdown::error::{Location, ParseError};
+use crate::markdown::html::HtmlRenderer;
+
+/// Inline content. Formatting spans hold their content as children,
+/// so they can nest to any depth, e.g. `**bold _and italic_**`.
+#[derive(Debug, Clone, PartialEq)]
 pub enum Text {
     Plain(String),
-    Italic(String),
-    Bold(String),
-    Strikethrough(String),
+    Italic(Vec<Text>),
+    Bold(Vec<Text>),
+    Strikethrough(Vec<Text>),
+    Code(String),
+    /// A raw inline HTML tag, e.g. `<br>`, passed through to the
+    /// field.
+    Html(String),
+    Link {
+        url: String,
+        title: String,
+        children: Vec<Text>,
+    },
+    Image {
+        url: String,
+        title: String,
+        alt: Vec<Text>,
+    },
+    /// `==text==` or `{{cN::text::hint}}`, a blank to fill in. Without
+    /// an explicit ordinal the cloze is numbered when rendered.
+    Cloze {
+        ordinal: Option<u64>,
+        children: Vec<Text>,
+        hint: Option<String>,
+    },
     SoftBrake,
     HardBrake,
 }
 
+fn link_destination(url: &str, title: &str) -> String {
+    let url = if url.contains([' ', '(', ')']) {
+        format!("<{url}>")
+    } else {
+        url.to_string()
+    };
+    match title {
+        "" => url,
+        title => format!("{url} \"{}\"", title.replace('"', "\\\"")),
+    }
+}
+
 impl Text {
+    /// The formatted content of a span, empty for leaf text.
+    pub fn children(&self) -> &[Text] {
+        match self {
+            Self::Italic(children)
+            | Self::Bold(children)
+            | Self::Strikethrough(children)
+            | Self::Link { children, .. }
+            | Self::Cloze { children, .. } => children,
+            Self::Image { alt, .. } => alt,
+            _ => &[],
+        }
+    }
+
+    /// The text with all formatting dropped.
+    pub fn to_plain_text(&self) -> String {
+        match self {
+            Self::Plain(txt) | Self::Code(txt) => txt.to_string(),
+            Self::SoftBrake | Self::HardBrake => " ".to_string(),
+            Self::Html(_) => String::new(),
+            span => span
+                .children()
+                .iter()
+                .map(Text::to_plain_text)
+                .collect(),
+        }
+    }
+
     pub fn to_markdown(&self) -> String {
         match self {
-            Self::Plain(txt) => txt.to_string(),
-            Self::Italic(txt) => {
+            Self::Plain(txt) | Self::Html(txt) => txt.to_string(),
+            Self::Italic(children) => {
                 let separator = "_";
-                format!("{separator}{}{separator}", txt)
+                format!(
+                    "{separator}{}{separator}",
+                    Self::children_to_markdown(children)
+                )
             }
-            Self::Bold(txt) => {
+            Self::Bold(children) => {
                 let separator = "**";
-                format!("{separator}{}{separator}", txt)
+                format!(
+                    "{separator}{}{separator}",
+                    Self::children_to_markdown(children)
+                )
             }
-            Self::Strikethrough(txt) => {
+            Self::Strikethrough(children) => {
                 let separator = "~~";
-                format!("{separator}{}{separator}", txt)
+                format!(
+                    "{separator}{}{separator}",
+                    Self::children_to_markdown(children)
+                )
+            }
+            Self::Code(code) => {
+                // The fence has to be longer than any backtick run in
+                // the code, and padded if the code touches a backtick.
+                let longest_run = code
+                    .split(|chr| chr != '`')
+                    .map(str::len)
+                    .max()
+                    .unwrap_or(0);
+                let separator = "`".repeat(longest_run + 1);
+                let padding =
+                    if code.starts_with('`') || code.ends_with('`') {
+                        " "
+                    } else {
+                        ""
+                    };
+                format!(
+                    "{separator}{padding}{code}{padding}{separator}"
+                )
+            }
+            Self::Link {
+                url,
+                title,
+                children,
+            } => format!(
+                "[{}]({})",
+                Self::children_to_markdown(children),
+                link_destination(url, title)
+            ),
+            Self::Image { url, title, alt } => format!(
+                "![{}]({})",
+                Self::children_to_markdown(alt),
+                link_destination(url, title)
+            ),
+            Self::Cloze {
+                ordinal,
+                children,
+                hint,
+            } => {
+                let hint = hint
+                    .as_ref()
+                    .map(|hint| format!("{HINT_SEPARATOR}{hint}"))
+                    .unwrap_or_default();
+                let children = Self::children_to_markdown(children);
+                match ordinal {
+                    Some(ordinal) => {
+                        format!(
+                            "{{{{c{ordinal}::{children}{hint}}}}}"
+                        )
+                    }
+                    None => format!("=={children}{hint}=="),
+                }
             }
             Self::SoftBrake => "\n".to_string(),
             Self::HardBrake => "\\\n".to_string(),
         }
     }
+
+    /// Whether this text is a cloze or holds one.
+    pub fn has_cloze(&self) -> bool {
+        matches!(self, Self::Cloze { .. })
+            || self.children().iter().any(Text::has_cloze)
+    }
+
+    pub fn to_html(&self) -> String {
+        HtmlRenderer::new().render_text(self)
+    }
+
+    pub fn children_to_markdown(children: &[Text]) -> String {
+        children.iter().map(Text::to_markdown).collect()
+    }
+
+    pub fn children_to_html(children: &[Text]) -> String {
+        HtmlRenderer::new().render_texts(children)
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -37,7 +184,67 @@ pub enum NodeType {
     Document,
     Text(Text),
     Paragraph,
-    Heading { level: usize, content: Vec<Text> },
+    Heading {
+        level: usize,
+        content: Vec<Text>,
+    },
+    List {
+        ordered: bool,
+        start: Option<u64>,
+    },
+    ListItem,
+    CodeBlock {
+        info: Option<String>,
+        code: String,
+    },
+    Table {
+        alignments: Vec<Alignment>,
+    },
+    TableRow {
+        header: bool,
+    },
+    TableCell,
+    /// A metadata block, `---` for YAML or `+++` for TOML, at the
+    /// top of the file or of a section.
+    FrontMatter {
+        format: FrontMatterFormat,
+        content: String,
+    },
+    /// A block of raw HTML, passed through to the field.
+    Html(String),
+    /// A horizontal rule, `***` or `---` after a blank line.
+    Rule,
+    /// A `>` blockquote, with its blocks as subnodes.
+    BlockQuote,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FrontMatterFormat {
+    Yaml,
+    Toml,
+}
+
+impl From<pulldown_cmark::MetadataBlockKind> for FrontMatterFormat {
+    fn from(kind: pulldown_cmark::MetadataBlockKind) -> Self {
+        match kind {
+            pulldown_cmark::MetadataBlockKind::YamlStyle => {
+                Self::Yaml
+            }
+            pulldown_cmark::MetadataBlockKind::PlusesStyle => {
+                Self::Toml
+            }
+        }
+    }
+}
+
+impl FrontMatterFormat {
+    /// The line opening and closing the block.
+    pub fn delimiter(&self) -> &'static str {
+        match self {
+            Self::Yaml => "---",
+            Self::Toml => "+++",
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -46,104 +253,382 @@ pub struct Node {
     subnodes: Vec<Rc<Node>>,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 enum Tag {
     Italic,
     Bold,
     Strikethrough,
     Paragraph,
     Heading(usize),
+    List(Option<u64>),
+    Item,
+    CodeBlock(Option<String>),
+    Link { url: String, title: String },
+    Image { url: String, title: String },
+    Table(Vec<Alignment>),
+    TableHead,
+    TableRow,
+    TableCell,
+    FrontMatter(FrontMatterFormat),
+    BlockQuote,
+    HtmlBlock,
 }
 
+/// A markdown event together with its byte range in the source.
+pub type SourceEvent<'a> = (pulldown_cmark::Event<'a>, Range<usize>);
+
+/// Events as produced by
+/// [`pulldown_cmark::Parser::into_offset_iter`].
+pub type Events<'a, 'e> = dyn Iterator<Item = SourceEvent<'a>> + 'e;
+
 impl Tag {
-    fn from_start(tag: pulldown_cmark::Tag) -> Self {
+    fn from_start(tag: &pulldown_cmark::Tag) -> Option<Self> {
         match tag {
-            pulldown_cmark::Tag::Emphasis => Self::Italic,
-            pulldown_cmark::Tag::Strong => Self::Bold,
-            pulldown_cmark::Tag::Strikethrough => Self::Strikethrough,
+            pulldown_cmark::Tag::Emphasis => Some(Self::Italic),
+            pulldown_cmark::Tag::Strong => Some(Self::Bold),
+            pulldown_cmark::Tag::Strikethrough => {
+                Some(Self::Strikethrough)
+            }
             pulldown_cmark::Tag::Heading { level, .. } => {
-                Self::Heading(level as usize)
+                Some(Self::Heading(*level as usize))
+            }
+            pulldown_cmark::Tag::Paragraph => Some(Self::Paragraph),
+            pulldown_cmark::Tag::List(start) => {
+                Some(Self::List(*start))
+            }
+            pulldown_cmark::Tag::Item => Some(Self::Item),
+            pulldown_cmark::Tag::Link {
+                dest_url, title, ..
+            } => Some(Self::Link {
+                url: dest_url.to_string(),
+                title: title.to_string(),
+            }),
+            pulldown_cmark::Tag::Image {
+                dest_url, title, ..
+            } => Some(Self::Image {
+                url: dest_url.to_string(),
+                title: title.to_string(),
+            }),
+            pulldown_cmark::Tag::Table(alignments) => {
+                Some(Self::Table(alignments.clone()))
+            }
+            pulldown_cmark::Tag::TableHead => Some(Self::TableHead),
+            pulldown_cmark::Tag::TableRow => Some(Self::TableRow),
+            pulldown_cmark::Tag::TableCell => Some(Self::TableCell),
+            pulldown_cmark::Tag::MetadataBlock(kind) => {
+                Some(Self::FrontMatter((*kind).into()))
             }
-            pulldown_cmark::Tag::Paragraph => Self::Paragraph,
-            _ => todo!(),
+            pulldown_cmark::Tag::BlockQuote(_) => {
+                Some(Self::BlockQuote)
+            }
+            pulldown_cmark::Tag::HtmlBlock => Some(Self::HtmlBlock),
+            pulldown_cmark::Tag::CodeBlock(kind) => {
+                Some(Self::CodeBlock(match kind {
+                    pulldown_cmark::CodeBlockKind::Fenced(info)
+                        if !info.trim().is_empty() =>
+                    {
+                        Some(info.trim().to_string())
+                    }
+                    _ => None,
+                }))
+            }
+            _ => None,
         }
     }
 
-    fn from_end(tag_end: pulldown_cmark::TagEnd) -> Self {
-        match tag_end {
-            pulldown_cmark::TagEnd::Emphasis => Self::Italic,
-            pulldown_cmark::TagEnd::Strong => Self::Bold,
-            pulldown_cmark::TagEnd::Strikethrough => {
-                Self::Strikethrough
+    fn is_closed_by(&self, tag_end: pulldown_cmark::TagEnd) -> bool {
+        match (self, tag_end) {
+            (Self::Italic, pulldown_cmark::TagEnd::Emphasis) => true,
+            (Self::Bold, pulldown_cmark::TagEnd::Strong) => true,
+            (
+                Self::Strikethrough,
+                pulldown_cmark::TagEnd::Strikethrough,
+            ) => true,
+            (Self::Paragraph, pulldown_cmark::TagEnd::Paragraph) => {
+                true
+            }
+            (
+                Self::Heading(level),
+                pulldown_cmark::TagEnd::Heading(end_level),
+            ) => *level == end_level as usize,
+            (Self::List(_), pulldown_cmark::TagEnd::List(_)) => true,
+            (Self::Item, pulldown_cmark::TagEnd::Item) => true,
+            (
+                Self::CodeBlock(_),
+                pulldown_cmark::TagEnd::CodeBlock,
+            ) => true,
+            (Self::Link { .. }, pulldown_cmark::TagEnd::Link) => true,
+            (Self::Image { .. }, pulldown_cmark::TagEnd::Image) => {
+                true
             }
-            _ => todo!(),
+            (Self::Table(_), pulldown_cmark::TagEnd::Table) => true,
+            (Self::TableHead, pulldown_cmark::TagEnd::TableHead) => {
+                true
+            }
+            (Self::TableRow, pulldown_cmark::TagEnd::TableRow) => {
+                true
+            }
+            (Self::TableCell, pulldown_cmark::TagEnd::TableCell) => {
+                true
+            }
+            (
+                Self::FrontMatter(_),
+                pulldown_cmark::TagEnd::MetadataBlock(_),
+            ) => true,
+            (
+                Self::BlockQuote,
+                pulldown_cmark::TagEnd::BlockQuote(_),
+            ) => true,
+            (Self::HtmlBlock, pulldown_cmark::TagEnd::HtmlBlock) => {
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn is_same_kind(&self, other: &Self) -> bool {
+        std::mem::discriminant(self) == std::mem::discriminant(other)
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Italic => "emphasis",
+            Self::Bold => "strong emphasis",
+            Self::Strikethrough => "strikethrough",
+            Self::Paragraph => "paragraph",
+            Self::Heading(_) => "heading",
+            Self::List(_) => "list",
+            Self::Item => "list item",
+            Self::CodeBlock(_) => "code block",
+            Self::Link { .. } => "link",
+            Self::Image { .. } => "image",
+            Self::Table(_) => "table",
+            Self::TableHead => "table head",
+            Self::TableRow => "table row",
+            Self::TableCell => "table cell",
+            Self::FrontMatter(_) => "metadata block",
+            Self::BlockQuote => "blockquote",
+            Self::HtmlBlock => "html block",
         }
     }
 }
 
-impl Node {
-    fn parse_text_event(
-        events: &mut dyn Iterator<Item = pulldown_cmark::Event>,
-        tag: Tag,
-    ) -> Result<Self, &'static str> {
-        let txt_events = events
-            .take_while(|event| match event {
-                pulldown_cmark::Event::End(tag_end) => {
-                    Tag::from_end(*tag_end) != tag
-                }
-                _ => true,
-            })
-            .collect::<Vec<_>>();
-        let text_str = txt_events
-            .iter()
-            .filter_map(|event| match event {
-                pulldown_cmark::Event::Text(txt) => {
-                    Some(txt.to_string())
+fn tag_name(tag: &pulldown_cmark::Tag) -> &'static str {
+    match tag {
+        pulldown_cmark::Tag::FootnoteDefinition(_) => {
+            "footnote definition"
+        }
+        pulldown_cmark::Tag::DefinitionList
+        | pulldown_cmark::Tag::DefinitionListTitle
+        | pulldown_cmark::Tag::DefinitionListDefinition => {
+            "definition list"
+        }
+        pulldown_cmark::Tag::Superscript => "superscript",
+        pulldown_cmark::Tag::Subscript => "subscript",
+        tag => match Tag::from_start(tag) {
+            Some(tag) => tag.name(),
+            None => "tag",
+        },
+    }
+}
+
+fn event_name(event: &pulldown_cmark::Event) -> &'static str {
+    match event {
+        pulldown_cmark::Event::Start(tag) => tag_name(tag),
+        pulldown_cmark::Event::End(_) => "end tag",
+        pulldown_cmark::Event::Text(_) => "text",
+        pulldown_cmark::Event::Code(_) => "inline code",
+        pulldown_cmark::Event::InlineMath(_) => "inline math",
+        pulldown_cmark::Event::DisplayMath(_) => "display math",
+        pulldown_cmark::Event::Html(_) => "html",
+        pulldown_cmark::Event::InlineHtml(_) => "inline html",
+        pulldown_cmark::Event::FootnoteReference(_) => {
+            "footnote reference"
+        }
+        pulldown_cmark::Event::SoftBreak => "soft break",
+        pulldown_cmark::Event::HardBreak => "hard break",
+        pulldown_cmark::Event::Rule => "horizontal rule",
+        pulldown_cmark::Event::TaskListMarker(_) => {
+            "task list marker"
+        }
+    }
+}
+
+fn node_name(node_type: &NodeType) -> &'static str {
+    match node_type {
+        NodeType::Document => "document",
+        NodeType::Text(_) => "text",
+        NodeType::Paragraph => "paragraph",
+        NodeType::Heading { .. } => "heading",
+        NodeType::List { .. } => "list",
+        NodeType::ListItem => "list item",
+        NodeType::CodeBlock { .. } => "code block",
+        NodeType::Table { .. } => "table",
+        NodeType::TableRow { .. } => "table row",
+        NodeType::TableCell => "table cell",
+        NodeType::FrontMatter { .. } => "metadata block",
+        NodeType::Html(_) => "html block",
+        NodeType::Rule => "horizontal rule",
+        NodeType::BlockQuote => "blockquote",
+    }
+}
+
+/// Collects the events up to the end of `tag`, consuming the end
+/// event. Tags of the same kind nested inside `tag` (lists in list
+/// items, for example) are kept whole.
+fn take_until_end<'a>(
+    events: &mut Events<'a, '_>,
+    tag: &Tag,
+    range: &Range<usize>,
+    source: &str,
+) -> Result<Vec<SourceEvent<'a>>, ParseError> {
+    let mut depth = 0usize;
+    let mut taken = vec![];
+    for (event, event_range) in &mut *events {
+        match &event {
+            pulldown_cmark::Event::Start(start)
+                if Tag::from_start(start)
+                    .is_some_and(|start| start.is_same_kind(tag)) =>
+            {
+                depth += 1;
+            }
+            pulldown_cmark::Event::End(tag_end)
+                if tag.is_closed_by(*tag_end) =>
+            {
+                if depth == 0 {
+                    return Ok(taken);
                 }
-                _ => None,
-            })
-            .collect::<Vec<_>>();
-        if txt_events.len() != text_str.len() {
-            return Err("Not all events are text events");
+                depth -= 1;
+            }
+            _ => (),
         }
-        let text = text_str.join("");
-        match tag {
-            Tag::Italic => Ok(Self {
-                node_type: NodeType::Text(Text::Italic(text)),
-                subnodes: vec![],
-            }),
-            Tag::Bold => Ok(Self {
-                node_type: NodeType::Text(Text::Bold(text)),
-                subnodes: vec![],
-            }),
-            Tag::Strikethrough => Ok(Self {
-                node_type: NodeType::Text(Text::Strikethrough(text)),
-                subnodes: vec![],
+        taken.push((event, event_range));
+    }
+    Err(ParseError::Unclosed {
+        construct: tag.name(),
+        location: Location::new(source, range.clone()),
+    })
+}
+
+impl Node {
+    /// The markdown extensions the AST understands. Smart punctuation
+    /// is left out on purpose, note fields keep the quotes as written.
+    pub fn options() -> pulldown_cmark::Options {
+        pulldown_cmark::Options::ENABLE_TABLES
+            | pulldown_cmark::Options::ENABLE_STRIKETHROUGH
+            | pulldown_cmark::Options::ENABLE_YAML_STYLE_METADATA_BLOCKS
+            | pulldown_cmark::Options::ENABLE_PLUSES_DELIMITED_METADATA_BLOCKS
+    }
+
+    /// Parses a whole markdown document, every top level node of
+    /// the result is a heading (with its section as subnodes) or a
+    /// block found before the first heading.
+    pub fn parse_document(
+        source: &str,
+    ) -> Result<Vec<Rc<Self>>, ParseError> {
+        let parser =
+            pulldown_cmark::Parser::new_ext(source, Self::options());
+        Self::parse_nodes(&mut parser.into_offset_iter(), source)
+    }
+
+    fn expect_text_nodes(
+        nodes: &[Rc<Self>],
+        context: &'static str,
+        range: &Range<usize>,
+        source: &str,
+    ) -> Result<(), ParseError> {
+        Self::expect_nodes(
+            nodes,
+            |node_type| matches!(node_type, NodeType::Text(_)),
+            context,
+            range,
+            source,
+        )
+    }
+
+    fn expect_nodes(
+        nodes: &[Rc<Self>],
+        is_expected: fn(&NodeType) -> bool,
+        context: &'static str,
+        range: &Range<usize>,
+        source: &str,
+    ) -> Result<(), ParseError> {
+        match nodes.iter().find(|node| !is_expected(&node.node_type))
+        {
+            Some(node) => Err(ParseError::Unexpected {
+                construct: node_name(&node.node_type),
+                context,
+                location: Location::new(source, range.clone()),
             }),
-            _ => Err("Not a text event"),
+            None => Ok(()),
         }
     }
 
-    fn parse_paragraph(
-        events: &mut dyn Iterator<Item = pulldown_cmark::Event>,
-    ) -> Result<Self, &'static str> {
-        let text_events = events
-            .take_while(|event| match event {
-                pulldown_cmark::Event::End(tag_end) => {
-                    Tag::from_end(*tag_end) != Tag::Paragraph
-                }
-                _ => true,
+    fn text_content(nodes: &[Rc<Self>]) -> Vec<Text> {
+        nodes
+            .iter()
+            .filter_map(|node| match &node.node_type {
+                NodeType::Text(txt) => Some(txt.clone()),
+                _ => None,
             })
-            .collect::<Vec<_>>();
+            .collect()
+    }
+
+    fn parse_text_event(
+        events: &mut Events<'_, '_>,
+        tag: Tag,
+        range: &Range<usize>,
+        source: &str,
+    ) -> Result<Self, ParseError> {
+        let txt_events = take_until_end(events, &tag, range, source)?;
         let txt_nodes =
-            Self::parse_nodes(&mut text_events.into_iter())?;
+            Self::parse_nodes(&mut txt_events.into_iter(), source)?;
 
-        for node in &txt_nodes {
-            match node.node_type {
-                NodeType::Text(_) => (),
-                _ => return Err("Non text node was found"),
-            }
-        }
+        Self::expect_text_nodes(
+            &txt_nodes,
+            tag.name(),
+            range,
+            source,
+        )?;
+
+        let children = Self::text_content(&txt_nodes);
+        let text = match tag {
+            Tag::Italic => Text::Italic(children),
+            Tag::Bold => Text::Bold(children),
+            Tag::Link { url, title } => Text::Link {
+                url,
+                title,
+                children,
+            },
+            Tag::Image { url, title } => Text::Image {
+                url,
+                title,
+                alt: children,
+            },
+            _ => Text::Strikethrough(children),
+        };
+        Ok(Self {
+            node_type: NodeType::Text(text),
+            subnodes: vec![],
+        })
+    }
+
+    fn parse_paragraph(
+        events: &mut Events<'_, '_>,
+        range: &Range<usize>,
+        source: &str,
+    ) -> Result<Self, ParseError> {
+        let text_events =
+            take_until_end(events, &Tag::Paragraph, range, source)?;
+        let txt_nodes =
+            Self::parse_nodes(&mut text_events.into_iter(), source)?;
+
+        Self::expect_text_nodes(
+            &txt_nodes,
+            "paragraph",
+            range,
+            source,
+        )?;
 
         Ok(Self {
             node_type: NodeType::Paragraph,
@@ -152,124 +637,367 @@ impl Node {
     }
 
     fn parse_heading(
-        events: &mut dyn Iterator<Item = pulldown_cmark::Event>,
-        tag: Tag,
-    ) -> Result<Self, &'static str> {
-        let text_nodes = events.take_while(|event| match event {
-            pulldown_cmark::Event::End(tag_end) => {
-                Tag::from_end(*tag_end) != tag
-            }
-            _ => true,
-        });
+        events: &mut Events<'_, '_>,
+        level: usize,
+        range: &Range<usize>,
+        source: &str,
+    ) -> Result<Self, ParseError> {
+        let text_nodes = take_until_end(
+            events,
+            &Tag::Heading(level),
+            range,
+            source,
+        )?;
         let text_nodes =
-            Self::parse_nodes(&mut text_nodes.into_iter())?;
-
-        for node in &text_nodes {
-            match node.node_type {
-                NodeType::Text(_) => (),
-                _ => return Err("Non text node was found"),
-            }
-        }
+            Self::parse_nodes(&mut text_nodes.into_iter(), source)?;
 
-        let level = match tag {
-            Tag::Heading(level) => Some(level),
-            _ => None,
-        }
-        .ok_or("Not headinh tag")?;
+        Self::expect_text_nodes(
+            &text_nodes,
+            "heading",
+            range,
+            source,
+        )?;
 
         Ok(Self {
             node_type: NodeType::Heading {
                 level,
-                content: text_nodes
-                    .iter()
-                    .filter_map(|node| match &node.node_type {
-                        NodeType::Text(txt) => Some(txt.clone()),
-                        _ => None,
-                    })
-                    .collect(),
+                content: Self::text_content(&text_nodes),
             },
             subnodes: vec![],
         })
     }
 
-    fn parse_tag(
-        events: &mut dyn Iterator<Item = pulldown_cmark::Event>,
+    fn parse_list(
+        events: &mut Events<'_, '_>,
+        start: Option<u64>,
+        range: &Range<usize>,
+        source: &str,
+    ) -> Result<Self, ParseError> {
+        let item_events =
+            take_until_end(events, &Tag::List(start), range, source)?;
+        let items =
+            Self::parse_nodes(&mut item_events.into_iter(), source)?;
+
+        if let Some(node) = items.iter().find(|node| {
+            !matches!(node.node_type, NodeType::ListItem)
+        }) {
+            return Err(ParseError::Unexpected {
+                construct: node_name(&node.node_type),
+                context: "list",
+                location: Location::new(source, range.clone()),
+            });
+        }
+
+        Ok(Self {
+            node_type: NodeType::List {
+                ordered: start.is_some(),
+                start,
+            },
+            subnodes: items,
+        })
+    }
+
+    fn parse_list_item(
+        events: &mut Events<'_, '_>,
+        range: &Range<usize>,
+        source: &str,
+    ) -> Result<Self, ParseError> {
+        let item_events =
+            take_until_end(events, &Tag::Item, range, source)?;
+        let content =
+            Self::parse_nodes(&mut item_events.into_iter(), source)?;
+
+        Ok(Self {
+            node_type: NodeType::ListItem,
+            subnodes: content,
+        })
+    }
+
+    fn parse_table(
+        events: &mut Events<'_, '_>,
+        alignments: Vec<Alignment>,
+        range: &Range<usize>,
+        source: &str,
+    ) -> Result<Self, ParseError> {
+        let row_events = take_until_end(
+            events,
+            &Tag::Table(alignments.clone()),
+            range,
+            source,
+        )?;
+        let rows =
+            Self::parse_nodes(&mut row_events.into_iter(), source)?;
+
+        Self::expect_nodes(
+            &rows,
+            |node_type| {
+                matches!(node_type, NodeType::TableRow { .. })
+            },
+            "table",
+            range,
+            source,
+        )?;
+
+        Ok(Self {
+            node_type: NodeType::Table { alignments },
+            subnodes: rows,
+        })
+    }
+
+    fn parse_table_row(
+        events: &mut Events<'_, '_>,
         tag: Tag,
-    ) -> Result<Self, &'static str> {
-        match tag {
-            Tag::Italic | Tag::Bold | Tag::Strikethrough => {
-                Self::parse_text_event(events, tag)
+        range: &Range<usize>,
+        source: &str,
+    ) -> Result<Self, ParseError> {
+        let cell_events =
+            take_until_end(events, &tag, range, source)?;
+        let cells =
+            Self::parse_nodes(&mut cell_events.into_iter(), source)?;
+
+        Self::expect_nodes(
+            &cells,
+            |node_type| matches!(node_type, NodeType::TableCell),
+            tag.name(),
+            range,
+            source,
+        )?;
+
+        Ok(Self {
+            node_type: NodeType::TableRow {
+                header: tag == Tag::TableHead,
+            },
+            subnodes: cells,
+        })
+    }
+
+    fn parse_table_cell(
+        events: &mut Events<'_, '_>,
+        range: &Range<usize>,
+        source: &str,
+    ) -> Result<Self, ParseError> {
+        let text_events =
+            take_until_end(events, &Tag::TableCell, range, source)?;
+        let txt_nodes =
+            Self::parse_nodes(&mut text_events.into_iter(), source)?;
+
+        Self::expect_text_nodes(
+            &txt_nodes,
+            "table cell",
+            range,
+            source,
+        )?;
+
+        Ok(Self {
+            node_type: NodeType::TableCell,
+            subnodes: txt_nodes,
+        })
+    }
+
+    /// The text of a code, metadata or HTML block, which holds
+    /// nothing else.
+    fn parse_literal(
+        events: &mut Events<'_, '_>,
+        tag: &Tag,
+        range: &Range<usize>,
+        source: &str,
+    ) -> Result<String, ParseError> {
+        let literal_events =
+            take_until_end(events, tag, range, source)?;
+        let mut literal = String::new();
+        for (event, event_range) in literal_events {
+            match event {
+                pulldown_cmark::Event::Text(txt)
+                | pulldown_cmark::Event::Html(txt) => {
+                    literal.push_str(&txt)
+                }
+                event => {
+                    return Err(ParseError::Unexpected {
+                        construct: event_name(&event),
+                        context: tag.name(),
+                        location: Location::new(source, event_range),
+                    })
+                }
             }
-            Tag::Paragraph => Self::parse_paragraph(events),
-            Tag::Heading(_) => Self::parse_heading(events, tag),
         }
+        Ok(literal)
     }
 
-    pub fn parse_nodes(
-        events: &mut dyn Iterator<Item = pulldown_cmark::Event>,
-    ) -> Result<Vec<Rc<Self>>, &'static str> {
-        let mut nodes = vec![];
+    fn parse_code_block(
+        events: &mut Events<'_, '_>,
+        info: Option<String>,
+        range: &Range<usize>,
+        source: &str,
+    ) -> Result<Self, ParseError> {
+        let code = Self::parse_literal(
+            events,
+            &Tag::CodeBlock(info.clone()),
+            range,
+            source,
+        )?;
 
-        let mut open_headings = Vec::<Rc<Self>>::new();
+        Ok(Self {
+            node_type: NodeType::CodeBlock { info, code },
+            subnodes: vec![],
+        })
+    }
 
-        fn push_node(
-            node: Node,
-            mut nodes: Vec<Rc<Node>>,
-            mut open_headings: Vec<Rc<Node>>,
-        ) -> Result<(Vec<Rc<Node>>, Vec<Rc<Node>>), &'static str>
-        {
-            let push_level = match &node.node_type {
-                NodeType::Heading { level, .. } => Some(*level),
-                _ => None,
-            };
+    fn parse_front_matter(
+        events: &mut Events<'_, '_>,
+        format: FrontMatterFormat,
+        range: &Range<usize>,
+        source: &str,
+    ) -> Result<Self, ParseError> {
+        let content = Self::parse_literal(
+            events,
+            &Tag::FrontMatter(format),
+            range,
+            source,
+        )?;
 
-            let rc_node = Rc::new(node);
+        Ok(Self {
+            node_type: NodeType::FrontMatter { format, content },
+            subnodes: vec![],
+        })
+    }
 
-            while let Some(n) = open_headings.last() {
-                match &n.node_type {
-                    NodeType::Heading { level: lvl, .. } => {
-                        if *lvl >= push_level.unwrap_or(usize::MAX) {
-                            open_headings.pop();
-                        } else {
-                            break;
-                        }
-                    }
-                    _ => break,
-                }
+    fn parse_html_block(
+        events: &mut Events<'_, '_>,
+        range: &Range<usize>,
+        source: &str,
+    ) -> Result<Self, ParseError> {
+        let html = Self::parse_literal(
+            events,
+            &Tag::HtmlBlock,
+            range,
+            source,
+        )?;
+
+        Ok(Self {
+            node_type: NodeType::Html(html),
+            subnodes: vec![],
+        })
+    }
+
+    fn parse_block_quote(
+        events: &mut Events<'_, '_>,
+        range: &Range<usize>,
+        source: &str,
+    ) -> Result<Self, ParseError> {
+        let block_events =
+            take_until_end(events, &Tag::BlockQuote, range, source)?;
+        let blocks =
+            Self::parse_nodes(&mut block_events.into_iter(), source)?;
+
+        Ok(Self {
+            node_type: NodeType::BlockQuote,
+            subnodes: blocks,
+        })
+    }
+
+    fn parse_tag(
+        events: &mut Events<'_, '_>,
+        tag: Tag,
+        range: &Range<usize>,
+        source: &str,
+    ) -> Result<Self, ParseError> {
+        match tag {
+            Tag::Italic
+            | Tag::Bold
+            | Tag::Strikethrough
+            | Tag::Link { .. }
+            | Tag::Image { .. } => {
+                Self::parse_text_event(events, tag, range, source)
+            }
+            Tag::Paragraph => {
+                Self::parse_paragraph(events, range, source)
+            }
+            Tag::Heading(level) => {
+                Self::parse_heading(events, level, range, source)
+            }
+            Tag::List(start) => {
+                Self::parse_list(events, start, range, source)
+            }
+            Tag::Item => Self::parse_list_item(events, range, source),
+            Tag::CodeBlock(info) => {
+                Self::parse_code_block(events, info, range, source)
+            }
+            Tag::Table(alignments) => {
+                Self::parse_table(events, alignments, range, source)
             }
+            Tag::TableHead | Tag::TableRow => {
+                Self::parse_table_row(events, tag, range, source)
+            }
+            Tag::TableCell => {
+                Self::parse_table_cell(events, range, source)
+            }
+            Tag::FrontMatter(format) => Self::parse_front_matter(
+                events, format, range, source,
+            ),
+            Tag::BlockQuote => {
+                Self::parse_block_quote(events, range, source)
+            }
+            Tag::HtmlBlock => {
+                Self::parse_html_block(events, range, source)
+            }
+        }
+    }
 
-            match open_headings.len() {
-                0 => nodes.push(rc_node),
-                size => Rc::get_mut(&mut open_headings[size - 1])
-                    .ok_or("Cannot get mut from ref counted")?
-                    .subnodes
-                    .push(rc_node),
-            };
+    /// The level of a heading, `None` for any other node.
+    pub fn heading_level(&self) -> Option<usize> {
+        match self.node_type {
+            NodeType::Heading { level, .. } => Some(level),
+            _ => None,
+        }
+    }
 
-            if push_level.is_some() {
-                match open_headings.len() {
-                    0usize => {
-                        todo!();
-                    }
-                    _ => {
-                        let size = open_headings.len();
-                        let sub_size =
-                            open_headings[size - 1].subnodes.len();
-                        open_headings.push(
-                            open_headings[size - 1].subnodes
-                                [sub_size - 1]
-                                .clone(),
-                        )
-                    }
-                }
+    /// Closes the open headings of `level` or deeper, attaching each
+    /// one to its parent heading, or to `nodes` at the top level.
+    fn close_headings(
+        level: usize,
+        nodes: &mut Vec<Rc<Self>>,
+        open_headings: &mut Vec<Self>,
+    ) {
+        while open_headings
+            .last()
+            .and_then(Self::heading_level)
+            .is_some_and(|lvl| lvl >= level)
+        {
+            let heading = Rc::new(open_headings.pop().unwrap());
+            match open_headings.last_mut() {
+                Some(parent) => parent.subnodes.push(heading),
+                None => nodes.push(heading),
             }
-            Ok((nodes, open_headings))
         }
+    }
+
+    /// Parses `events` into nodes. A heading takes every following
+    /// node as a subnode until a heading of the same or a higher
+    /// level starts.
+    pub fn parse_nodes(
+        events: &mut Events<'_, '_>,
+        source: &str,
+    ) -> Result<Vec<Rc<Self>>, ParseError> {
+        let mut nodes = vec![];
+
+        let mut open_headings = Vec::<Self>::new();
 
-        while let Some(event) = events.next() {
+        while let Some((event, range)) = events.next() {
             let node = match event {
                 pulldown_cmark::Event::Start(tag) => {
-                    Self::parse_tag(events, Tag::from_start(tag))?
+                    match Tag::from_start(&tag) {
+                        Some(tag) => Self::parse_tag(
+                            events, tag, &range, source,
+                        )?,
+                        None => {
+                            return Err(ParseError::Unsupported {
+                                construct: tag_name(&tag),
+                                location: Location::new(
+                                    source, range,
+                                ),
+                            })
+                        }
+                    }
                 }
                 pulldown_cmark::Event::Text(txt) => Self {
                     node_type: NodeType::Text(Text::Plain(
@@ -277,13 +1005,88 @@ impl Node {
                     )),
                     subnodes: vec![],
                 },
-                _ => todo!(),
+                pulldown_cmark::Event::Code(code) => Self {
+                    node_type: NodeType::Text(Text::Code(
+                        code.to_string(),
+                    )),
+                    subnodes: vec![],
+                },
+                pulldown_cmark::Event::SoftBreak => Self {
+                    node_type: NodeType::Text(Text::SoftBrake),
+                    subnodes: vec![],
+                },
+                pulldown_cmark::Event::HardBreak => Self {
+                    node_type: NodeType::Text(Text::HardBrake),
+                    subnodes: vec![],
+                },
+                pulldown_cmark::Event::InlineHtml(html) => Self {
+                    node_type: NodeType::Text(Text::Html(
+                        html.to_string(),
+                    )),
+                    subnodes: vec![],
+                },
+                pulldown_cmark::Event::Rule => Self {
+                    node_type: NodeType::Rule,
+                    subnodes: vec![],
+                },
+                event => {
+                    return Err(ParseError::Unsupported {
+                        construct: event_name(&event),
+                        location: Location::new(source, range),
+                    })
+                }
             };
-            (nodes, open_headings) =
-                push_node(node, nodes, open_headings)?;
+
+            match node.heading_level() {
+                Some(level) => {
+                    Self::close_headings(
+                        level,
+                        &mut nodes,
+                        &mut open_headings,
+                    );
+                    open_headings.push(node);
+                }
+                None => match open_headings.last_mut() {
+                    Some(heading) => {
+                        heading.subnodes.push(Rc::new(node))
+                    }
+                    None => nodes.push(Rc::new(node)),
+                },
+            }
         }
+        Self::close_headings(0, &mut nodes, &mut open_headings);
 
-        Ok(nodes)
+        Ok(Self::extract_cloze_nodes(nodes))
+    }
+
+    /// Applies [`extract_clozes`] to every run of sibling text nodes.
+    fn extract_cloze_nodes(nodes: Vec<Rc<Self>>) -> Vec<Rc<Self>> {
+        let mut result = vec![];
+        let mut run = vec![];
+        let flush = |run: &mut Vec<Text>,
+                     result: &mut Vec<Rc<Self>>| {
+            result.extend(
+                extract_clozes(std::mem::take(run)).into_iter().map(
+                    |text| {
+                        Rc::new(Self {
+                            node_type: NodeType::Text(text),
+                            subnodes: vec![],
+                        })
+                    },
+                ),
+            )
+        };
+        for node in nodes {
+            match &node.node_type {
+                NodeType::Text(text) => run.push(text.clone()),
+                _ => {
+                    flush(&mut run, &mut result);
+                    result.push(node);
+                }
+            }
+        }
+        flush(&mut run, &mut result);
+        result
     }
 
     fn write_indented(
@@ -291,8 +1094,6 @@ impl Node {
         f: &mut std::fmt::Formatter<'_>,
         level: usize,
     ) -> std::fmt::Result {
-        println!("{}", level);
-        println!("{:#?}", self.node_type);
         match &self.node_type {
             NodeType::Document => {
                 for node in &self.subnodes {
@@ -334,9 +1135,218 @@ impl Node {
                     node.write_indented(f, level)?;
                 }
             }
+            NodeType::List { start, .. } => {
+                for (index, item) in self.subnodes.iter().enumerate()
+                {
+                    let marker = match start {
+                        Some(start) => {
+                            format!("{}.", start + index as u64)
+                        }
+                        None => "-".to_string(),
+                    };
+                    write!(
+                        f,
+                        "{:indent$}{marker} ",
+                        "",
+                        indent = level * 2
+                    )?;
+                    item.write_indented(f, level + 1)?;
+                }
+            }
+            NodeType::ListItem => {
+                // The leading inline content goes on the marker line,
+                // any block after it is indented under the item.
+                let mut on_marker_line = true;
+                for node in &self.subnodes {
+                    match (&node.node_type, on_marker_line) {
+                        (NodeType::Text(_), true) => {
+                            node.write_indented(f, 0)?
+                        }
+                        (NodeType::Paragraph, true) => {
+                            node.write_indented(f, 0)?;
+                            writeln!(f)?;
+                            on_marker_line = false;
+                        }
+                        (NodeType::Paragraph, false) => {
+                            write!(
+                                f,
+                                "{:indent$}",
+                                "",
+                                indent = level * 2
+                            )?;
+                            node.write_indented(f, 0)?;
+                            writeln!(f)?;
+                        }
+                        (_, true) => {
+                            writeln!(f)?;
+                            on_marker_line = false;
+                            node.write_indented(f, level)?;
+                        }
+                        (_, false) => {
+                            node.write_indented(f, level)?
+                        }
+                    }
+                }
+                if on_marker_line {
+                    writeln!(f)?;
+                }
+            }
+            NodeType::Table { alignments } => {
+                for row in &self.subnodes {
+                    row.write_indented(f, level)?;
+                    if matches!(
+                        row.node_type,
+                        NodeType::TableRow { header: true }
+                    ) {
+                        let delimiters = alignments
+                            .iter()
+                            .map(|alignment| match alignment {
+                                Alignment::None => "---",
+                                Alignment::Left => ":--",
+                                Alignment::Center => ":-:",
+                                Alignment::Right => "--:",
+                            })
+                            .collect::<Vec<_>>();
+                        writeln!(
+                            f,
+                            "{:indent$}| {} |",
+                            "",
+                            delimiters.join(" | "),
+                            indent = level * 2
+                        )?;
+                    }
+                }
+            }
+            NodeType::TableRow { .. } => {
+                let cells = self
+                    .subnodes
+                    .iter()
+                    .map(|cell| {
+                        Text::children_to_markdown(
+                            &Self::text_content(&cell.subnodes),
+                        )
+                        .replace('|', "\\|")
+                    })
+                    .collect::<Vec<_>>();
+                writeln!(
+                    f,
+                    "{:indent$}| {} |",
+                    "",
+                    cells.join(" | "),
+                    indent = level * 2
+                )?;
+            }
+            NodeType::TableCell => {
+                for node in &self.subnodes {
+                    node.write_indented(f, level)?;
+                }
+            }
+            NodeType::CodeBlock { info, code } => {
+                let longest_run = code
+                    .split(|chr| chr != '`')
+                    .map(str::len)
+                    .max()
+                    .unwrap_or(0);
+                let fence = "`".repeat(longest_run.max(2) + 1);
+                writeln!(
+                    f,
+                    "{:indent$}{fence}{}",
+                    "",
+                    info.as_deref().unwrap_or(""),
+                    indent = level * 2
+                )?;
+                for line in code.lines() {
+                    writeln!(
+                        f,
+                        "{:indent$}{line}",
+                        "",
+                        indent = level * 2
+                    )?;
+                }
+                writeln!(
+                    f,
+                    "{:indent$}{fence}",
+                    "",
+                    indent = level * 2
+                )?;
+            }
+            NodeType::FrontMatter { format, content } => {
+                let delimiter = format.delimiter();
+                write!(f, "{delimiter}\n{content}")?;
+                if !content.is_empty() && !content.ends_with('\n') {
+                    writeln!(f)?;
+                }
+                writeln!(f, "{delimiter}")?;
+            }
+            NodeType::Html(html) => {
+                for line in html.lines() {
+                    writeln!(
+                        f,
+                        "{:indent$}{line}",
+                        "",
+                        indent = level * 2
+                    )?;
+                }
+            }
+            // `---` would start a metadata block at the top of a file.
+            NodeType::Rule => {
+                writeln!(f, "{:indent$}***", "", indent = level * 2)?;
+            }
+            NodeType::BlockQuote => {
+                let quoted = self
+                    .subnodes
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<String>();
+                for line in quoted.lines() {
+                    let line = format!("> {line}");
+                    writeln!(
+                        f,
+                        "{:indent$}{}",
+                        "",
+                        line.trim_end(),
+                        indent = level * 2
+                    )?;
+                }
+            }
         }
         Ok(())
     }
+
+    pub fn to_html(&self) -> String {
+        HtmlRenderer::new().render_node(self)
+    }
+
+    /// The inline content of a heading, or of a paragraph, list item
+    /// or table cell.
+    pub fn inline_content(&self) -> Vec<Text> {
+        match &self.node_type {
+            NodeType::Heading { content, .. } => content.clone(),
+            NodeType::Text(text) => vec![text.clone()],
+            _ => Self::text_content(&self.subnodes),
+        }
+    }
+
+    /// Whether the node or any node below it holds a cloze.
+    pub fn has_cloze(&self) -> bool {
+        let in_content = match &self.node_type {
+            NodeType::Heading { content, .. } => {
+                content.iter().any(Text::has_cloze)
+            }
+            NodeType::Text(text) => text.has_cloze(),
+            _ => false,
+        };
+        in_content
+            || self.subnodes.iter().any(|node| node.has_cloze())
+    }
+
+    pub fn node_type(&self) -> &NodeType {
+        &self.node_type
+    }
+
+    pub fn subnodes(&self) -> &[Rc<Node>] {
+        &self.subnodes
+    }
 }
 
 impl std::fmt::Display for Node {
@@ -366,7 +1376,7 @@ mod tests {
                 subnodes: vec![],
             }]
             .into_iter()
-            .map(|n| Rc::new(n))
+            .map(Rc::new)
             .collect(),
         };
 
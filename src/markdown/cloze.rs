@@ -0,0 +1,223 @@
+use std::collections::VecDeque;
+
+use crate::markdown::ast::Text;
+
+/// Separator between the text of a cloze and its hint, as in
+/// `{{c1::Paris::capital}}` or `==Paris::capital==`. Only the last
+/// one counts, so `==std::vec::Vec::type==` hides `std::vec::Vec`.
+pub const HINT_SEPARATOR: &str = "::";
+
+/// An opening marker found in plain text.
+struct Opening {
+    start: usize,
+    end: usize,
+    ordinal: Option<u64>,
+    closer: &'static str,
+}
+
+/// The first `==` or `{{cN::` in `txt` at or after `from`. A `==`
+/// only opens a cloze at the start of a word, after whitespace or
+/// at the start of `txt` when `at_boundary`, and when text that is
+/// not whitespace follows, so `a == b` and `a==b` stay as is.
+fn find_opening(
+    txt: &str,
+    from: usize,
+    at_boundary: bool,
+) -> Option<Opening> {
+    let mut offset = from;
+    while offset < txt.len() {
+        let rest = &txt[offset..];
+        let idx = rest.find(['=', '{'])?;
+        let start = offset + idx;
+        let rest = &txt[start..];
+
+        if let Some(after) = rest.strip_prefix("==") {
+            let boundary = match start {
+                0 => at_boundary,
+                _ => txt[..start].ends_with(char::is_whitespace),
+            };
+            if boundary && !after.starts_with(['=', ' ', '\t', '\n'])
+            {
+                return Some(Opening {
+                    start,
+                    end: start + 2,
+                    ordinal: None,
+                    closer: "==",
+                });
+            }
+            offset = start + rest.len()
+                - after.trim_start_matches('=').len();
+            continue;
+        }
+
+        if let Some(after) = rest.strip_prefix("{{c") {
+            let digits = after
+                .find(|chr: char| !chr.is_ascii_digit())
+                .unwrap_or(after.len());
+            if after[digits..].starts_with("::") {
+                if let Ok(ordinal) = after[..digits].parse::<u64>() {
+                    return Some(Opening {
+                        start,
+                        end: start + "{{c".len() + digits + 2,
+                        ordinal: Some(ordinal),
+                        closer: "}}",
+                    });
+                }
+            }
+        }
+        offset = start + 1;
+    }
+    None
+}
+
+/// Where `closer` ends the cloze in `txt`. A `==` right after
+/// whitespace does not close it.
+fn find_closer(txt: &str, closer: &str) -> Option<usize> {
+    let mut offset = 0;
+    while let Some(idx) = txt[offset..].find(closer) {
+        let idx = offset + idx;
+        if closer != "==" || !txt[..idx].ends_with([' ', '\t', '\n'])
+        {
+            return Some(idx);
+        }
+        offset = idx + closer.len();
+    }
+    None
+}
+
+fn push_plain(texts: &mut Vec<Text>, txt: &str) {
+    if txt.is_empty() {
+        return;
+    }
+    match texts.last_mut() {
+        Some(Text::Plain(last)) => last.push_str(txt),
+        _ => texts.push(Text::Plain(txt.to_string())),
+    }
+}
+
+/// Splits the hint off the last plain text of a cloze.
+fn cloze(ordinal: Option<u64>, mut children: Vec<Text>) -> Text {
+    let mut hint = None;
+    if let Some(Text::Plain(last)) = children.last_mut() {
+        if let Some((txt, hint_txt)) =
+            last.rsplit_once(HINT_SEPARATOR)
+        {
+            hint = Some(hint_txt.to_string());
+            *last = txt.to_string();
+            if last.is_empty() {
+                children.pop();
+            }
+        }
+    }
+    Text::Cloze {
+        ordinal,
+        children,
+        hint,
+    }
+}
+
+/// Turns the `==text==` and `{{cN::text}}` of sibling inline texts
+/// into [`Text::Cloze`]s. A cloze may span formatted text, e.g.
+/// `==**Paris**==`, but it starts and ends in plain text. Markers
+/// that are never closed are left as text.
+pub fn extract_clozes(texts: Vec<Text>) -> Vec<Text> {
+    let mut items = vec![];
+    for text in texts {
+        match text {
+            Text::Plain(txt) => push_plain(&mut items, &txt),
+            text => items.push(text),
+        }
+    }
+
+    let mut result = vec![];
+    let mut items = VecDeque::from(items);
+    while let Some(item) = items.pop_front() {
+        let Text::Plain(txt) = item else {
+            result.push(item);
+            continue;
+        };
+
+        // Siblings are merged, so only a line break or the start of
+        // the run comes before a plain text at a word boundary.
+        let at_boundary = matches!(
+            result.last(),
+            None | Some(Text::SoftBrake | Text::HardBrake)
+        );
+        let mut from = 0;
+        let mut found = None;
+        while let Some(opening) =
+            find_opening(&txt, from, at_boundary)
+        {
+            let after = &txt[opening.end..];
+            from = opening.end;
+            // The cloze closes in the same text...
+            if let Some(idx) = find_closer(after, opening.closer) {
+                if idx > 0 {
+                    let children =
+                        vec![Text::Plain(after[..idx].to_string())];
+                    found = Some((opening, children, 0, idx));
+                    break;
+                }
+                continue;
+            }
+            // ...or in a later plain text.
+            let later =
+                items.iter().enumerate().find_map(|(pos, item)| {
+                    match item {
+                        Text::Plain(later) => {
+                            find_closer(later, opening.closer)
+                                .map(|idx| (pos, idx))
+                        }
+                        _ => None,
+                    }
+                });
+            if let Some((pos, idx)) = later {
+                let mut children = vec![];
+                push_plain(&mut children, after);
+                children.extend(items.iter().take(pos).cloned());
+                if let Text::Plain(later) = &items[pos] {
+                    push_plain(&mut children, &later[..idx]);
+                }
+                found = Some((opening, children, pos + 1, idx));
+                break;
+            }
+        }
+
+        let Some((opening, children, consumed, idx)) = found else {
+            push_plain(&mut result, &txt);
+            continue;
+        };
+
+        push_plain(&mut result, &txt[..opening.start]);
+        result.push(cloze(opening.ordinal, children));
+        let rest = match consumed {
+            0 => txt[opening.end + idx + opening.closer.len()..]
+                .to_string(),
+            consumed => {
+                let last = items.drain(..consumed).next_back();
+                match last {
+                    Some(Text::Plain(last)) => {
+                        last[idx + opening.closer.len()..].to_string()
+                    }
+                    _ => String::new(),
+                }
+            }
+        };
+        if !rest.is_empty() {
+            items.push_front(Text::Plain(rest));
+        }
+    }
+    result
+}
+
+/// Anki's field syntax for a cloze, `{{cN::html::hint}}`.
+pub fn cloze_to_field(
+    ordinal: u64,
+    html: &str,
+    hint: Option<&str>,
+) -> String {
+    match hint {
+        Some(hint) => format!("{{{{c{ordinal}::{html}::{hint}}}}}"),
+        None => format!("{{{{c{ordinal}::{html}}}}}"),
+    }
+}
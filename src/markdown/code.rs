@@ -0,0 +1,137 @@
+use crate::markdown::util::escape_html;
+
+/// Prefix of the CSS classes emitted by syntax highlighting, so they
+/// cannot clash with the classes of the card templates.
+pub const HIGHLIGHT_CLASS_PREFIX: &str = "hl-";
+
+/// Language of a fenced code block, the first word of its info
+/// string, e.g. `rust` for ```` ```rust,ignore ````.
+pub fn info_language(info: &str) -> Option<&str> {
+    info.split(|chr: char| chr.is_whitespace() || chr == ',')
+        .next()
+        .filter(|language| !language.is_empty())
+}
+
+/// Renders a code block as `<pre><code class="language-x">`.
+///
+/// With the `highlight` feature the code is tokenized at build time
+/// and wrapped in classed spans, see `highlight_css` for the
+/// matching stylesheet. Otherwise the code is only escaped.
+pub fn code_block_to_html(info: Option<&str>, code: &str) -> String {
+    let language = info.and_then(info_language);
+    let class = language
+        .map(|language| {
+            format!(" class=\"language-{}\"", escape_html(language))
+        })
+        .unwrap_or_default();
+    format!(
+        "<pre><code{class}>{}</code></pre>",
+        highlight(language, code)
+    )
+}
+
+#[cfg(not(feature = "highlight"))]
+fn highlight(_language: Option<&str>, code: &str) -> String {
+    escape_html(code)
+}
+
+#[cfg(feature = "highlight")]
+fn highlight(language: Option<&str>, code: &str) -> String {
+    use syntect::html::{ClassStyle, ClassedHTMLGenerator};
+    use syntect::util::LinesWithEndings;
+
+    let syntax_set = syntax_set();
+    let Some(syntax) = language
+        .and_then(|lang| syntax_set.find_syntax_by_token(lang))
+    else {
+        return escape_html(code);
+    };
+
+    let mut generator = ClassedHTMLGenerator::new_with_class_style(
+        syntax,
+        syntax_set,
+        ClassStyle::SpacedPrefixed {
+            prefix: HIGHLIGHT_CLASS_PREFIX,
+        },
+    );
+    for line in LinesWithEndings::from(code) {
+        if generator
+            .parse_html_for_line_which_includes_newline(line)
+            .is_err()
+        {
+            return escape_html(code);
+        }
+    }
+    generator.finalize()
+}
+
+#[cfg(feature = "highlight")]
+fn syntax_set() -> &'static syntect::parsing::SyntaxSet {
+    static SYNTAX_SET: std::sync::OnceLock<
+        syntect::parsing::SyntaxSet,
+    > = std::sync::OnceLock::new();
+    SYNTAX_SET.get_or_init(
+        syntect::parsing::SyntaxSet::load_defaults_newlines,
+    )
+}
+
+/// Static stylesheet for the highlighted code, meant to be appended
+/// to a model's CSS. `theme` is one of syntect's default themes,
+/// e.g. `InspiredGitHub` or `base16-ocean.dark`.
+#[cfg(feature = "highlight")]
+pub fn highlight_css(theme: &str) -> Option<String> {
+    use syntect::highlighting::ThemeSet;
+    use syntect::html::{css_for_theme_with_class_style, ClassStyle};
+
+    let themes = ThemeSet::load_defaults();
+    css_for_theme_with_class_style(
+        themes.themes.get(theme)?,
+        ClassStyle::SpacedPrefixed {
+            prefix: HIGHLIGHT_CLASS_PREFIX,
+        },
+    )
+    .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_info_language() {
+        assert_eq!(info_language("rust"), Some("rust"));
+        assert_eq!(info_language("rust,ignore"), Some("rust"));
+        assert_eq!(info_language("python title=x"), Some("python"));
+        assert_eq!(info_language(""), None);
+    }
+
+    #[test]
+    fn test_code_block_without_language() {
+        assert_eq!(
+            code_block_to_html(None, "a < b\n"),
+            "<pre><code>a &lt; b\n</code></pre>"
+        );
+    }
+
+    #[cfg(not(feature = "highlight"))]
+    #[test]
+    fn test_code_block_with_language() {
+        assert_eq!(
+            code_block_to_html(Some("rust"), "let x = 1;\n"),
+            "<pre><code class=\"language-rust\">let x = 1;\n</code></pre>"
+        );
+    }
+
+    #[cfg(feature = "highlight")]
+    #[test]
+    fn test_code_block_highlighted() {
+        let html = code_block_to_html(Some("rust"), "let x = 1;\n");
+        assert!(
+            html.starts_with("<pre><code class=\"language-rust\">")
+        );
+        assert!(html.contains("<span class=\"hl-"));
+        assert!(highlight_css("InspiredGitHub")
+            .unwrap()
+            .contains(".hl-"));
+    }
+}
@@ -0,0 +1,129 @@
+use std::ops::Range;
+
+/// Where in the markdown source a construct was found.
+///
+/// `line` and `column` are 1-based, the column counts characters.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Location {
+    pub range: Range<usize>,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Location {
+    pub fn new(source: &str, range: Range<usize>) -> Self {
+        let offset = range.start.min(source.len());
+        let before = &source[..offset];
+        let line = before.matches('\n').count() + 1;
+        let line_start = before.rfind('\n').map_or(0, |idx| idx + 1);
+        let column = before[line_start..].chars().count() + 1;
+
+        Self {
+            range,
+            line,
+            column,
+        }
+    }
+}
+
+impl std::fmt::Display for Location {
+    fn fmt(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+    ) -> std::fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+/// Error produced while turning markdown events into
+/// [`Node`](super::ast::Node)s.
+///
+/// It displays as `line:column: message`, so prefixing the file
+/// name gives the usual `deck.md:14:3: unsupported blockquote`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseError {
+    /// A markdown construct the AST has no node for.
+    Unsupported {
+        construct: &'static str,
+        location: Location,
+    },
+    /// A construct that is valid markdown but not allowed where it
+    /// was found, e.g. a list inside a heading.
+    Unexpected {
+        construct: &'static str,
+        context: &'static str,
+        location: Location,
+    },
+    /// A start tag whose end tag never came.
+    Unclosed {
+        construct: &'static str,
+        location: Location,
+    },
+}
+
+impl ParseError {
+    pub fn location(&self) -> &Location {
+        match self {
+            Self::Unsupported { location, .. } => location,
+            Self::Unexpected { location, .. } => location,
+            Self::Unclosed { location, .. } => location,
+        }
+    }
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+    ) -> std::fmt::Result {
+        match self {
+            Self::Unsupported {
+                construct,
+                location,
+            } => write!(f, "{location}: unsupported {construct}"),
+            Self::Unexpected {
+                construct,
+                context,
+                location,
+            } => write!(
+                f,
+                "{location}: unexpected {construct} in {context}"
+            ),
+            Self::Unclosed {
+                construct,
+                location,
+            } => write!(f, "{location}: unclosed {construct}"),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_location() {
+        let source = "# Deck\n\nsome *text*\n";
+        let location = Location::new(source, 13..19);
+        assert_eq!(location.line, 3);
+        assert_eq!(location.column, 6);
+    }
+
+    #[test]
+    fn test_display() {
+        let error = ParseError::Unsupported {
+            construct: "blockquote",
+            location: Location {
+                range: 0..1,
+                line: 14,
+                column: 3,
+            },
+        };
+        assert_eq!(
+            format!("deck.md:{error}"),
+            "deck.md:14:3: unsupported blockquote"
+        );
+    }
+}
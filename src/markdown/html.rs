@@ -0,0 +1,395 @@
+use std::cell::Cell;
+use std::rc::Rc;
+
+use crate::markdown::ast::{Alignment, Node, NodeType, Text};
+use crate::markdown::cloze::cloze_to_field;
+use crate::markdown::code::code_block_to_html;
+use crate::markdown::util::escape_html;
+
+/// Renders the markdown AST to HTML for Anki note fields.
+///
+/// Text is escaped, soft and hard breaks become `<br>` and no
+/// whitespace is added between the elements. The output depends on
+/// nothing but the nodes, so an unchanged note renders to
+/// byte-identical fields and Anki does not see it as modified.
+///
+/// Clozes become Anki's `{{cN::text::hint}}`. A cloze without an
+/// ordinal gets one more than the highest ordinal rendered before
+/// it, so a field should be rendered with a single renderer.
+///
+/// Raw HTML of the markdown is passed through [`sanitize_html`].
+#[derive(Default)]
+pub struct HtmlRenderer<'a> {
+    image_source: Option<&'a ImageSource<'a>>,
+    last_cloze: Cell<u64>,
+}
+
+/// Maps the url of an image to the `src` it gets in the HTML.
+pub type ImageSource<'a> = dyn Fn(&str) -> Option<String> + 'a;
+
+/// Elements removed from raw HTML together with their content. Anki
+/// runs the scripts of a field, and a style or frame would reach
+/// beyond the field into the whole card.
+const DROPPED_ELEMENTS: [&str; 6] =
+    ["script", "style", "iframe", "frame", "object", "embed"];
+
+/// Url schemes that run code when a link is followed or an image
+/// is loaded.
+const SCRIPT_SCHEMES: [&str; 2] = ["javascript:", "vbscript:"];
+
+/// Length of the tag at the start of `html`, up to and including the
+/// `>` that is not inside a quoted attribute value.
+fn tag_len(html: &str) -> Option<usize> {
+    let mut quote = None;
+    for (idx, chr) in html.char_indices() {
+        match (quote, chr) {
+            (None, '"' | '\'') => quote = Some(chr),
+            (Some(open), chr) if chr == open => quote = None,
+            (None, '>') => return Some(idx + 1),
+            _ => (),
+        }
+    }
+    None
+}
+
+/// Name of an attribute and its source, `name="value"` or `name`.
+fn attributes(tag: &str) -> Vec<(String, &str)> {
+    let mut attributes = vec![];
+    let mut rest = tag;
+    loop {
+        rest = rest.trim_start_matches(|chr: char| {
+            chr.is_whitespace() || chr == '/'
+        });
+        let name_len = rest
+            .find(|chr: char| {
+                chr.is_whitespace() || "=/>".contains(chr)
+            })
+            .unwrap_or(rest.len());
+        if name_len == 0 {
+            return attributes;
+        }
+        let mut len = name_len;
+        let after = rest[len..].trim_start();
+        if let Some(value) = after.strip_prefix('=') {
+            let value = value.trim_start();
+            let value_len = match value.chars().next() {
+                Some(quote @ ('"' | '\'')) => value[1..]
+                    .find(quote)
+                    .map_or(value.len(), |end| end + 2),
+                _ => value
+                    .find(|chr: char| {
+                        chr.is_whitespace() || chr == '>'
+                    })
+                    .unwrap_or(value.len()),
+            };
+            len = rest.len() - value.len() + value_len;
+        }
+        attributes.push((
+            rest[..name_len].to_ascii_lowercase(),
+            &rest[..len],
+        ));
+        rest = &rest[len..];
+    }
+}
+
+/// Whether an attribute may stay: no event handlers like `onclick`
+/// and no urls with a [`SCRIPT_SCHEMES`] scheme, also when the colon
+/// is written as an entity.
+fn is_safe_attribute(name: &str, source: &str) -> bool {
+    let value = source
+        .split_once('=')
+        .map_or("", |(_, value)| value)
+        .chars()
+        .filter(|chr| {
+            !chr.is_whitespace()
+                && !chr.is_control()
+                && !"\"'".contains(*chr)
+        })
+        .collect::<String>()
+        .to_ascii_lowercase()
+        .replace("&colon;", ":")
+        .replace("&#58;", ":")
+        .replace("&#x3a;", ":");
+    !name.starts_with("on")
+        && !SCRIPT_SCHEMES
+            .iter()
+            .any(|scheme| value.starts_with(scheme))
+}
+
+/// Raw HTML made safe for an Anki field. Scripts, styles and
+/// frames are removed with their content, event handler attributes
+/// and script urls are removed from the other tags, and a `<` that
+/// does not start a tag or a comment is escaped. Anything else is
+/// kept as written, so `<br>` or `<span class="jp">` reach the
+/// field.
+pub fn sanitize_html(html: &str) -> String {
+    let mut sanitized = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(idx) = rest.find('<') {
+        sanitized.push_str(&rest[..idx]);
+        rest = &rest[idx..];
+
+        if rest.starts_with("<!--") {
+            let len =
+                rest.find("-->").map_or(rest.len(), |end| end + 3);
+            sanitized.push_str(&rest[..len]);
+            rest = &rest[len..];
+            continue;
+        }
+        let body = rest[1..].strip_prefix('/').unwrap_or(&rest[1..]);
+        let name_len = body
+            .find(|chr: char| {
+                !chr.is_ascii_alphanumeric() && chr != '-'
+            })
+            .unwrap_or(body.len());
+        let len = tag_len(rest).filter(|_| {
+            body.starts_with(|chr: char| chr.is_ascii_alphabetic())
+        });
+        let Some(len) = len else {
+            sanitized.push_str("&lt;");
+            rest = &rest[1..];
+            continue;
+        };
+        let name = body[..name_len].to_ascii_lowercase();
+        let tag = &rest[..len];
+        rest = &rest[len..];
+
+        if DROPPED_ELEMENTS.contains(&name.as_str()) {
+            if !tag.starts_with("</") {
+                let closer = format!("</{name}");
+                if let Some(end) =
+                    rest.to_ascii_lowercase().find(&closer)
+                {
+                    rest = &rest[end..];
+                }
+            }
+            continue;
+        }
+        if tag.starts_with("</") {
+            sanitized.push_str(&format!("</{}>", &body[..name_len]));
+            continue;
+        }
+        let inner = &tag[1 + name_len..len - 1];
+        sanitized.push('<');
+        sanitized.push_str(&body[..name_len]);
+        for (attribute, source) in attributes(inner) {
+            if is_safe_attribute(&attribute, source) {
+                sanitized.push(' ');
+                sanitized.push_str(source);
+            }
+        }
+        if inner.trim_end().ends_with('/') {
+            sanitized.push_str(" /");
+        }
+        sanitized.push('>');
+    }
+    sanitized.push_str(rest);
+    sanitized
+}
+
+fn title_attribute(title: &str) -> String {
+    match title {
+        "" => String::new(),
+        title => format!(" title=\"{}\"", escape_html(title)),
+    }
+}
+
+fn alignment_attribute(
+    alignment: Option<&Alignment>,
+) -> &'static str {
+    match alignment {
+        Some(Alignment::Left) => " style=\"text-align: left\"",
+        Some(Alignment::Center) => " style=\"text-align: center\"",
+        Some(Alignment::Right) => " style=\"text-align: right\"",
+        Some(Alignment::None) | None => "",
+    }
+}
+
+impl<'a> HtmlRenderer<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Rewrites the `src` of images, e.g. to the names of the files
+    /// in the media folder. Urls mapped to `None` are kept as is.
+    pub fn with_image_source(
+        mut self,
+        image_source: &'a ImageSource<'a>,
+    ) -> Self {
+        self.image_source = Some(image_source);
+        self
+    }
+
+    pub fn render_text(&self, text: &Text) -> String {
+        match text {
+            Text::Plain(txt) => escape_html(txt),
+            Text::Italic(children) => {
+                format!("<em>{}</em>", self.render_texts(children))
+            }
+            Text::Bold(children) => format!(
+                "<strong>{}</strong>",
+                self.render_texts(children)
+            ),
+            Text::Strikethrough(children) => {
+                format!("<del>{}</del>", self.render_texts(children))
+            }
+            Text::Code(code) => {
+                format!("<code>{}</code>", escape_html(code))
+            }
+            Text::Html(html) => sanitize_html(html),
+            Text::Link {
+                url,
+                title,
+                children,
+            } => format!(
+                "<a href=\"{}\"{}>{}</a>",
+                escape_html(url),
+                title_attribute(title),
+                self.render_texts(children)
+            ),
+            Text::Image { url, title, alt } => {
+                let src = self
+                    .image_source
+                    .and_then(|image_source| image_source(url))
+                    .unwrap_or_else(|| url.clone());
+                format!(
+                    "<img src=\"{}\" alt=\"{}\"{}>",
+                    escape_html(&src),
+                    escape_html(
+                        &alt.iter()
+                            .map(Text::to_plain_text)
+                            .collect::<String>()
+                    ),
+                    title_attribute(title)
+                )
+            }
+            Text::Cloze {
+                ordinal,
+                children,
+                hint,
+            } => {
+                let ordinal =
+                    ordinal.unwrap_or(self.last_cloze.get() + 1);
+                self.last_cloze
+                    .set(self.last_cloze.get().max(ordinal));
+                cloze_to_field(
+                    ordinal,
+                    &self.render_texts(children),
+                    hint.as_deref().map(escape_html).as_deref(),
+                )
+            }
+            Text::SoftBrake | Text::HardBrake => "<br>".to_string(),
+        }
+    }
+
+    pub fn render_texts(&self, texts: &[Text]) -> String {
+        texts.iter().map(|text| self.render_text(text)).collect()
+    }
+
+    pub fn render_nodes(&self, nodes: &[Rc<Node>]) -> String {
+        nodes.iter().map(|node| self.render_node(node)).collect()
+    }
+
+    pub fn render_node(&self, node: &Node) -> String {
+        let subnodes = node.subnodes();
+        match node.node_type() {
+            NodeType::Document | NodeType::TableCell => {
+                self.render_nodes(subnodes)
+            }
+            NodeType::Text(text) => self.render_text(text),
+            NodeType::Paragraph => {
+                format!("<p>{}</p>", self.render_nodes(subnodes))
+            }
+            NodeType::Heading { level, content } => format!(
+                "<h{level}>{}</h{level}>{}",
+                self.render_texts(content),
+                self.render_nodes(subnodes)
+            ),
+            NodeType::List { ordered: false, .. } => {
+                format!("<ul>{}</ul>", self.render_nodes(subnodes))
+            }
+            NodeType::List { start, .. } => {
+                let start = match start {
+                    Some(1) | None => String::new(),
+                    Some(start) => format!(" start=\"{start}\""),
+                };
+                format!(
+                    "<ol{start}>{}</ol>",
+                    self.render_nodes(subnodes)
+                )
+            }
+            NodeType::ListItem => {
+                format!("<li>{}</li>", self.render_nodes(subnodes))
+            }
+            NodeType::CodeBlock { info, code } => {
+                code_block_to_html(info.as_deref(), code)
+            }
+            NodeType::Table { alignments } => {
+                self.render_table(alignments, subnodes)
+            }
+            NodeType::TableRow { .. } => {
+                self.render_table_row(&[], node)
+            }
+            NodeType::FrontMatter { .. } => String::new(),
+            NodeType::Html(html) => sanitize_html(html.trim_end()),
+            NodeType::Rule => "<hr>".to_string(),
+            NodeType::BlockQuote => format!(
+                "<blockquote>{}</blockquote>",
+                self.render_nodes(subnodes)
+            ),
+        }
+    }
+
+    fn render_table_row(
+        &self,
+        alignments: &[Alignment],
+        row: &Node,
+    ) -> String {
+        let cell_tag = match row.node_type() {
+            NodeType::TableRow { header: true } => "th",
+            _ => "td",
+        };
+        let cells = row
+            .subnodes()
+            .iter()
+            .enumerate()
+            .map(|(idx, cell)| {
+                format!(
+                    "<{cell_tag}{}>{}</{cell_tag}>",
+                    alignment_attribute(alignments.get(idx)),
+                    self.render_node(cell)
+                )
+            })
+            .collect::<String>();
+        format!("<tr>{cells}</tr>")
+    }
+
+    fn render_table(
+        &self,
+        alignments: &[Alignment],
+        rows: &[Rc<Node>],
+    ) -> String {
+        let (head, body): (Vec<_>, Vec<_>) =
+            rows.iter().partition(|row| {
+                matches!(
+                    row.node_type(),
+                    NodeType::TableRow { header: true }
+                )
+            });
+        let render_rows = |rows: Vec<&Rc<Node>>| {
+            rows.into_iter()
+                .map(|row| self.render_table_row(alignments, row))
+                .collect::<String>()
+        };
+
+        let mut html = "<table>".to_string();
+        if !head.is_empty() {
+            html += &format!("<thead>{}</thead>", render_rows(head));
+        }
+        if !body.is_empty() {
+            html += &format!("<tbody>{}</tbody>", render_rows(body));
+        }
+        html += "</table>";
+        html
+    }
+}
@@ -1,2 +1,6 @@
 pub mod ast;
+pub mod cloze;
+pub mod code;
+pub mod error;
+pub mod html;
 pub mod util;
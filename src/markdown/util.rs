@@ -20,15 +20,23 @@ pub fn log_markdown_str(text: &str) {
     log_markdown_events(&mut Parser::new_ext(text, Options::all()));
 }
 
+pub fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for chr in text.chars() {
+        match chr {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            chr => escaped.push(chr),
+        }
+    }
+    escaped
+}
+
 pub fn check_matching_tags(tag: &Tag, tag_end: &TagEnd) -> bool {
-    let expected_end = match tag {
-        Tag::Emphasis => TagEnd::Emphasis,
-        Tag::Strikethrough => TagEnd::Strikethrough,
-        Tag::Strong => TagEnd::Strong,
-        _ => todo!(),
-    };
-
-    expected_end == tag_end.clone()
+    tag.to_end() == *tag_end
 }
 
 #[cfg(test)]
@@ -40,4 +48,26 @@ mod tests {
         let text = "# Hello\n## World";
         log_markdown_str(text);
     }
+
+    #[test]
+    fn test_escape_html() {
+        assert_eq!(
+            escape_html(r#"<a href="x">Tom & 'Jerry'</a>"#),
+            "&lt;a href=&quot;x&quot;&gt;Tom &amp; &#39;Jerry&#39;&lt;/a&gt;"
+        );
+    }
+
+    #[test]
+    fn test_check_matching_tags() {
+        assert!(check_matching_tags(&Tag::Strong, &TagEnd::Strong));
+        assert!(check_matching_tags(&Tag::Item, &TagEnd::Item));
+        assert!(!check_matching_tags(
+            &Tag::List(Some(1)),
+            &TagEnd::List(false)
+        ));
+        assert!(!check_matching_tags(
+            &Tag::Emphasis,
+            &TagEnd::BlockQuote(None)
+        ));
+    }
 }
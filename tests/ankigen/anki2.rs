@@ -0,0 +1,275 @@
+use ankimdown::ankigen::anki2::*;
+use ankimdown::ankigen::db_model::card::*;
+use ankimdown::ankigen::db_model::collection::Collection;
+use ankimdown::ankigen::db_model::deck::Deck;
+use ankimdown::ankigen::db_model::model::*;
+use ankimdown::ankigen::db_model::note::{Note, NoteTag};
+use rusqlite::Connection;
+
+fn sample() -> (Collection, Vec<Note>, Vec<Card>) {
+    let model = Model::new(
+        None,
+        vec![
+            ModelField::new("Front".to_string(), 0),
+            ModelField::new("Back".to_string(), 1),
+        ],
+        1700000000000,
+        None,
+        None,
+        "Basic".to_string(),
+        0,
+        vec![ModelTemplate::new(
+            "Card 1".to_string(),
+            "{{Front}}".to_string(),
+            "{{Back}}".to_string(),
+        )],
+        ModelType::FrontBack,
+    );
+    let mut deck = Deck::new("Words".to_string());
+    deck.id = 1700000000001;
+
+    let mut collection = Collection::new();
+    collection.id = 1;
+    collection.models.push((1700000000000, model.clone()));
+    collection.decks.push((1700000000001, deck));
+
+    let note = Note::with_model(
+        1700000000002,
+        "abcdefghij".to_string(),
+        &model,
+        1700000000,
+        vec![NoteTag::new("french").unwrap()],
+        vec!["<b>hello</b>".to_string(), "a greeting".to_string()],
+    )
+    .unwrap();
+    let card = Card::new(
+        1700000000003,
+        1700000000002,
+        1700000000001,
+        0,
+        1700000000,
+        -1,
+        CardType::New,
+        CardQueue::New,
+        1,
+        0,
+        0,
+        0,
+        0,
+        0,
+        0,
+        0,
+        CardFlag::Null,
+    );
+    (collection, vec![note], vec![card])
+}
+
+fn written() -> Connection {
+    let (collection, notes, cards) = sample();
+    let mut conn = Connection::open_in_memory().unwrap();
+    write_collection(&mut conn, &collection, &notes, &cards).unwrap();
+    conn
+}
+
+#[test]
+fn test_schema() {
+    let conn = written();
+    let names = |kind: &str| {
+        let mut stmt = conn
+            .prepare(
+                "select name from sqlite_master \
+                 where type = ?1 and name not like 'sqlite_%' \
+                 order by name",
+            )
+            .unwrap();
+        stmt.query_map([kind], |row| row.get::<_, String>(0))
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap()
+    };
+
+    assert_eq!(
+        names("table"),
+        vec!["cards", "col", "graves", "notes", "revlog"]
+    );
+    assert_eq!(
+        names("index"),
+        vec![
+            "ix_cards_nid",
+            "ix_cards_sched",
+            "ix_cards_usn",
+            "ix_notes_csum",
+            "ix_notes_usn",
+            "ix_revlog_cid",
+            "ix_revlog_usn"
+        ]
+    );
+    let integrity: String = conn
+        .query_row("pragma integrity_check", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(integrity, "ok");
+}
+
+#[test]
+fn test_col_row() {
+    let conn = written();
+    let (ver, models, decks, dconf): (i64, String, String, String) =
+        conn.query_row(
+            "select ver, models, decks, dconf from col",
+            [],
+            |row| {
+                Ok((
+                    row.get(0)?,
+                    row.get(1)?,
+                    row.get(2)?,
+                    row.get(3)?,
+                ))
+            },
+        )
+        .unwrap();
+    assert_eq!(ver, 11);
+
+    let models: serde_json::Value =
+        serde_json::from_str(&models).unwrap();
+    assert_eq!(models["1700000000000"]["name"], "Basic");
+    assert_eq!(models["1700000000000"]["tmpls"][0]["ord"], 0);
+    assert_eq!(models["1700000000000"]["flds"][1]["name"], "Back");
+
+    let decks: serde_json::Value =
+        serde_json::from_str(&decks).unwrap();
+    assert_eq!(decks["1"]["name"], "Default");
+    assert_eq!(decks["1700000000001"]["name"], "Words");
+    assert_eq!(decks["1700000000001"]["conf"], 1);
+
+    let dconf: serde_json::Value =
+        serde_json::from_str(&dconf).unwrap();
+    assert_eq!(dconf["1"]["name"], "Default");
+}
+
+#[test]
+fn test_notes_and_cards() {
+    let conn = written();
+    let (guid, tags, flds, csum): (String, String, String, i64) =
+        conn.query_row(
+            "select guid, tags, flds, csum from notes where id = ?1",
+            [1700000000002_i64],
+            |row| {
+                Ok((
+                    row.get(0)?,
+                    row.get(1)?,
+                    row.get(2)?,
+                    row.get(3)?,
+                ))
+            },
+        )
+        .unwrap();
+    assert_eq!(guid, "abcdefghij");
+    assert_eq!(tags, " french ");
+    assert_eq!(flds, "<b>hello</b>\x1fa greeting");
+    // First 8 hex digits of the SHA-1 of "hello", aaf4c61d.
+    assert_eq!(csum, 0xaaf4c61d);
+
+    let (nid, did, queue): (i64, i64, i64) = conn
+        .query_row("select nid, did, queue from cards", [], |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+        })
+        .unwrap();
+    assert_eq!(nid, 1700000000002);
+    assert_eq!(did, 1700000000001);
+    assert_eq!(queue, 0);
+}
+
+#[test]
+fn test_write_file() {
+    let (collection, notes, cards) = sample();
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("collection.anki2");
+    write_collection_file(&path, &collection, &notes, &cards)
+        .unwrap();
+
+    let conn = Connection::open(&path).unwrap();
+    let count: i64 = conn
+        .query_row("select count(*) from notes", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(count, 1);
+    assert!(write_collection_file(
+        &path,
+        &collection,
+        &notes,
+        &cards
+    )
+    .is_err());
+}
+
+#[test]
+fn test_write_over_other_file() {
+    let (collection, notes, cards) = sample();
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("other.sqlite");
+    Connection::open(&path)
+        .unwrap()
+        .execute("create table mine (a)", [])
+        .unwrap();
+
+    let error =
+        write_collection_file(&path, &collection, &notes, &cards)
+            .unwrap_err();
+    assert!(error.to_string().contains("other.sqlite"));
+    let conn = Connection::open(&path).unwrap();
+    let tables: Vec<String> = conn
+        .prepare(
+            "select name from sqlite_master where type = 'table'",
+        )
+        .unwrap()
+        .query_map([], |row| row.get(0))
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(tables, vec!["mine"]);
+}
+
+#[test]
+fn test_read_back() {
+    let (collection, notes, cards) = sample();
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("collection.anki2");
+    write_collection_file(&path, &collection, &notes, &cards)
+        .unwrap();
+
+    let data = read_collection_file(&path).unwrap();
+    assert_eq!(data.collection.version, SCHEMA_VERSION);
+    assert_eq!(data.collection.config, collection.config);
+    assert_eq!(data.collection.models, collection.models);
+    let deck_names = data
+        .collection
+        .decks
+        .iter()
+        .map(|(id, deck)| (*id, deck.name.as_str()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        deck_names,
+        vec![(1, "Default"), (1700000000001, "Words")]
+    );
+    assert_eq!(data.collection.deck_configs.len(), 1);
+
+    assert_eq!(data.notes.len(), 1);
+    let note = &data.notes[0];
+    assert_eq!(note.global_id, "abcdefghij");
+    assert_eq!(note.fields, notes[0].fields);
+    assert_eq!(note.tags[0].name, "french");
+    assert_eq!(note.sort_field, "hello");
+    assert_eq!(note.checksum, notes[0].checksum);
+
+    assert_eq!(data.cards, cards);
+}
+
+#[test]
+fn test_read_invalid() {
+    let conn = written();
+    conn.execute("update cards set queue = 9", []).unwrap();
+    assert!(read_collection(&conn).is_err());
+    conn.execute("update cards set queue = 0", []).unwrap();
+    conn.execute("update col set models = 'not json'", [])
+        .unwrap();
+    assert!(read_collection(&conn).is_err());
+}
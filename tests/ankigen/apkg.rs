@@ -0,0 +1,133 @@
+use std::io::{Cursor, Read};
+
+use ankimdown::ankigen::apkg::*;
+use ankimdown::ankigen::db_model::collection::Collection;
+use ankimdown::ankigen::db_model::note::Note;
+use ankimdown::ankigen::media::MediaManifest;
+use zip::ZipArchive;
+
+fn note() -> Note {
+    Note::new(
+        1700000000002,
+        "abcdefghij".to_string(),
+        1700000000000,
+        1700000000,
+        -1,
+        vec![],
+        vec!["hello".to_string(), "a greeting".to_string()],
+        "hello".to_string(),
+        0,
+    )
+}
+
+fn read_entry(
+    archive: &mut ZipArchive<Cursor<Vec<u8>>>,
+    name: &str,
+) -> Vec<u8> {
+    let mut content = vec![];
+    archive
+        .by_name(name)
+        .unwrap()
+        .read_to_end(&mut content)
+        .unwrap();
+    content
+}
+
+#[test]
+fn test_package_entries() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("a.png"), b"first image").unwrap();
+    std::fs::write(dir.path().join("b.png"), b"second image")
+        .unwrap();
+    let mut media = MediaManifest::new();
+    let a = media.add(&dir.path().join("a.png")).unwrap();
+    let b = media.add(&dir.path().join("b.png")).unwrap();
+
+    let collection = Collection::new();
+    let notes = vec![note()];
+    let mut buffer = Cursor::new(vec![]);
+    Package::new(&collection, &notes, &[])
+        .with_media(&media)
+        .write_to(&mut buffer)
+        .unwrap();
+
+    let mut archive = ZipArchive::new(buffer).unwrap();
+    let mut names = archive.file_names().collect::<Vec<_>>();
+    names.sort();
+    assert_eq!(names, vec!["0", "1", "collection.anki2", "media"]);
+
+    assert_eq!(read_entry(&mut archive, "0"), b"first image");
+    assert_eq!(read_entry(&mut archive, "1"), b"second image");
+    let manifest: serde_json::Value =
+        serde_json::from_slice(&read_entry(&mut archive, MEDIA))
+            .unwrap();
+    assert_eq!(manifest, serde_json::json!({ "0": a, "1": b }));
+
+    let db_path = dir.path().join("collection.anki2");
+    std::fs::write(
+        &db_path,
+        read_entry(&mut archive, COLLECTION_ANKI2),
+    )
+    .unwrap();
+    let conn = rusqlite::Connection::open(&db_path).unwrap();
+    let guid: String = conn
+        .query_row("select guid from notes", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(guid, "abcdefghij");
+}
+
+#[test]
+fn test_package_anki21() {
+    let collection = Collection::new();
+    let mut buffer = Cursor::new(vec![]);
+    Package::new(&collection, &[], &[])
+        .with_anki21(true)
+        .write_to(&mut buffer)
+        .unwrap();
+
+    let mut archive = ZipArchive::new(buffer).unwrap();
+    assert_eq!(archive.len(), 3);
+    assert_eq!(
+        read_entry(&mut archive, COLLECTION_ANKI21),
+        read_entry(&mut archive, COLLECTION_ANKI2)
+    );
+    assert_eq!(read_entry(&mut archive, MEDIA), b"{}");
+}
+
+#[test]
+fn test_package_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("deck.apkg");
+    let collection = Collection::new();
+    Package::new(&collection, &[], &[]).write(&path).unwrap();
+    // Writing again replaces the package.
+    Package::new(&collection, &[], &[]).write(&path).unwrap();
+
+    let archive =
+        ZipArchive::new(std::fs::File::open(&path).unwrap()).unwrap();
+    assert_eq!(archive.len(), 2);
+}
+
+#[test]
+fn test_read_package() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("deck.apkg");
+    let collection = Collection::new();
+    let notes = vec![note()];
+    Package::new(&collection, &notes, &[])
+        .with_anki21(true)
+        .write(&path)
+        .unwrap();
+
+    let data = read_package(&path).unwrap();
+    assert_eq!(data.notes.len(), 1);
+    assert_eq!(data.notes[0].fields, notes[0].fields);
+    assert!(data.cards.is_empty());
+    assert_eq!(data.collection.config, collection.config);
+
+    std::fs::write(&path, b"not a zip").unwrap();
+    assert!(matches!(
+        read_package(&path).unwrap_err(),
+        PackageError::Zip(_)
+    ));
+}
@@ -0,0 +1,300 @@
+use ankimdown::ankigen::autogen::*;
+use ankimdown::ankigen::deck::{Deck, DeckError};
+
+fn note(id: i64, guid: &str) -> Autogen {
+    Autogen {
+        id: Some(id),
+        guid: Some(guid.to_string()),
+        model_id: Some(1600000000001),
+    }
+}
+
+fn deck_ids(id: i64) -> Autogen {
+    Autogen {
+        id: Some(id),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_write_deck_id() {
+    let source =
+        include_str!("../../examples/markdown_databse/deck.md");
+    let updated =
+        write_autogen(source, &deck_ids(1718000000000), &[]);
+    assert_eq!(
+        updated,
+        source.replace("- id: 0", "- id: 1718000000000")
+    );
+    assert_eq!(
+        Deck::from_markdown(&updated).unwrap().metadata.autogen.id,
+        1718000000000
+    );
+}
+
+#[test]
+fn test_write_sample_database() {
+    let source = include_str!(
+        "../../examples/markdown_databse/foo/sample_database.md"
+    );
+    let updated =
+        write_autogen(source, &deck_ids(10), &[note(20, "abc<d")]);
+    assert_eq!(
+        updated,
+        "# Deck name\n\
+         This is a sample description\n\
+         \n\
+         ## Deck metadata:\n\
+         \n\
+         - Maps:\n\
+         \x20   - Description: Meaning\n\
+         - Autogen:\n\
+         \x20   - id: 10\n\
+         \n\
+         # hello\n\
+         ## Meaning\n\
+         1. a greeting\n\
+         \n\
+         ## Metadata\n\
+         \n\
+         - Templates:\n\
+         \x20   - Simple\n\
+         \x20   - Reverse\n\
+         - Autogen:\n\
+         \x20   - id: 20\n\
+         \x20   - guid: `abc<d`\n\
+         \x20   - model id: 1600000000001\n"
+    );
+
+    let deck = Deck::from_markdown(&updated).unwrap();
+    assert_eq!(deck.metadata.autogen.id, 10);
+    let hello = &deck.information[0];
+    assert_eq!(hello.guid(&deck.name), "abc<d");
+    assert_eq!(
+        Autogen::from_metadata(&hello.metadata).unwrap(),
+        note(20, "abc<d")
+    );
+
+    // Nothing changes when the ids are already recorded.
+    assert_eq!(
+        write_autogen(&updated, &deck_ids(10), &[note(20, "abc<d")]),
+        updated
+    );
+}
+
+#[test]
+fn test_write_missing_sections() {
+    let source = "# Deck\r\n\r\nAbout it.\r\n\r\n\
+                  # one\r\n## Meaning\r\n- 1\r\n\r\n\
+                  # two\r\n## Metadata\r\nTemplates are below.\r\n\r\n\
+                  ```\r\n# not a heading\r\n```\r\n";
+    let updated = write_autogen(
+        source,
+        &deck_ids(1),
+        &[note(2, "g2"), note(3, "g3")],
+    );
+    assert_eq!(
+        updated,
+        "# Deck\r\n\r\nAbout it.\r\n\r\n\
+         ## Deck metadata\r\n\r\n- Autogen:\r\n    - id: 1\r\n\r\n\
+         # one\r\n## Meaning\r\n- 1\r\n\r\n\
+         ## Metadata\r\n\r\n- Autogen:\r\n    - id: 2\r\n    \
+         - guid: `g2`\r\n    - model id: 1600000000001\r\n\r\n\
+         # two\r\n## Metadata\r\nTemplates are below.\r\n\r\n\
+         ```\r\n# not a heading\r\n```\r\n\r\n\
+         - Autogen:\r\n    - id: 3\r\n    - guid: `g3`\r\n    \
+         - model id: 1600000000001\r\n"
+    );
+    let deck = Deck::from_markdown(&updated).unwrap();
+    assert_eq!(deck.metadata.autogen.id, 1);
+    assert_eq!(deck.information[0].guid(&deck.name), "g2");
+    assert_eq!(deck.information[1].guid(&deck.name), "g3");
+}
+
+#[test]
+fn test_write_guid_round_trip() {
+    let source = "# Deck\n# word\n## Meaning\nx\n";
+    for guid in [
+        "ab*cd*ef",
+        "a`b`c",
+        "``ab",
+        "ab`",
+        "p\\_q",
+        "a&amp;b",
+        "[x](y)",
+        "a~~b~~",
+        "x<b>y",
+        "<k",
+        "#!$%'()+,-./:;=>?@]^{|}",
+    ] {
+        let updated = write_autogen(
+            source,
+            &Autogen::default(),
+            &[note(1, guid)],
+        );
+        let deck = Deck::from_markdown(&updated).unwrap();
+        assert_eq!(deck.information[0].guid(&deck.name), guid);
+    }
+}
+
+#[test]
+fn test_write_keeps_other_entries() {
+    let source = "# Deck\n## Metadata\n* Autogen:\n  * guid:\n  \
+                  * ID: 5 \n  * other: x\n* After: y\n# word\n";
+    assert_eq!(
+        write_autogen(source, &deck_ids(6), &[]),
+        "# Deck\n## Metadata\n* Autogen:\n  * guid:\n  \
+         * ID: 6 \n  * other: x\n* After: y\n# word\n"
+    );
+    let guid = Autogen {
+        guid: Some("g".to_string()),
+        ..Default::default()
+    };
+    assert_eq!(
+        write_autogen(source, &guid, &[Autogen::default()]),
+        "# Deck\n## Metadata\n* Autogen:\n  * guid: `g`\n  \
+         * ID: 5 \n  * other: x\n* After: y\n# word\n"
+    );
+}
+
+#[test]
+fn test_invalid_autogen() {
+    assert_eq!(
+        Deck::from_markdown(
+            "# Deck\n## Metadata\n- Autogen:\n    - model id: -1\n"
+        )
+        .unwrap_err(),
+        DeckError::InvalidMetadata {
+            key: "Autogen.model id".to_string(),
+            value: "-1".to_string()
+        }
+    );
+}
+
+#[test]
+fn test_write_autogen_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("deck.md");
+    std::fs::write(&path, "# Deck\n").unwrap();
+
+    assert!(write_autogen_file(&path, &deck_ids(7), &[]).unwrap());
+    assert_eq!(
+        std::fs::read_to_string(&path).unwrap(),
+        "# Deck\n\n## Deck metadata\n\n- Autogen:\n    - id: 7\n"
+    );
+    assert!(!write_autogen_file(&path, &deck_ids(7), &[]).unwrap());
+}
+
+#[test]
+fn test_write_nested_notes() {
+    let source = "# Course\n## Metadata\n- Note level: 3\n\
+                  ## Unit\n### b\n#### Meaning\nbee\n\
+                  ### Metadata\n- Autogen:\n    - id: 0\n\
+                  ### a\n#### Metadata\n- Autogen:\n    - id: 0\n";
+    let updated = write_autogen(
+        source,
+        &Autogen::default(),
+        &[note(1, "b"), note(2, "a")],
+    );
+    assert_eq!(
+        updated,
+        "# Course\n## Metadata\n- Note level: 3\n\
+         ## Unit\n### b\n#### Meaning\nbee\n\n\
+         #### Metadata\n\n- Autogen:\n    - id: 1\n    - guid: `b`\n    \
+         - model id: 1600000000001\n\
+         ### Metadata\n- Autogen:\n    - id: 0\n\
+         ### a\n#### Metadata\n- Autogen:\n    - id: 2\n    \
+         - guid: `a`\n    - model id: 1600000000001\n"
+    );
+
+    let deck = Deck::from_markdown(&updated).unwrap();
+    let guids = deck
+        .notes()
+        .into_iter()
+        .map(|(name, information)| information.guid(&name))
+        .collect::<Vec<_>>();
+    assert_eq!(guids, vec!["b", "a"]);
+    assert_eq!(deck.subdecks[0].metadata.autogen.id, 0);
+}
+
+#[test]
+fn test_write_yaml_front_matter() {
+    let source = "---\n\
+                  name: Kanji\n\
+                  autogen:\n  id: 0\n\
+                  ---\n\
+                  # Deck\n\
+                  # 水\n\
+                  ---\n\
+                  model: Basic\n\
+                  ---\n\
+                  ## Meaning\n- water\n\
+                  # 火\n## Meaning\n- fire\n";
+    let updated = write_autogen(
+        source,
+        &deck_ids(9),
+        &[note(1, "a'b"), note(2, "c")],
+    );
+    assert_eq!(
+        updated,
+        "---\n\
+         name: Kanji\n\
+         autogen:\n  id: 9\n\
+         ---\n\
+         # Deck\n\
+         # 水\n\
+         ---\n\
+         model: Basic\n\
+         autogen:\n  id: 1\n  guid: 'a''b'\n  model_id: 1600000000001\n\
+         ---\n\
+         ## Meaning\n- water\n\
+         # 火\n## Meaning\n- fire\n\n\
+         ## Metadata\n\n\
+         - Autogen:\n    - id: 2\n    - guid: `c`\n    \
+         - model id: 1600000000001\n"
+    );
+
+    let deck = Deck::from_markdown(&updated).unwrap();
+    assert_eq!(deck.metadata.autogen.id, 9);
+    assert_eq!(deck.information[0].guid("Kanji"), "a'b");
+    assert_eq!(
+        write_autogen(
+            &updated,
+            &deck_ids(9),
+            &[note(1, "a'b"), note(2, "c")]
+        ),
+        updated
+    );
+}
+
+#[test]
+fn test_write_toml_front_matter() {
+    let source = "+++\r\n\
+                  name = \"Kanji\"\r\n\
+                  \r\n\
+                  [autogen]\r\n\
+                  guid = \"old\"\r\n\
+                  +++\r\n\
+                  # Deck\r\n";
+    let updated = write_autogen(source, &note(4, "x\\y"), &[]);
+    assert_eq!(
+        updated,
+        "+++\r\n\
+         name = \"Kanji\"\r\n\
+         \r\n\
+         [autogen]\r\n\
+         guid = \"x\\\\y\"\r\n\
+         id = 4\r\n\
+         model_id = 1600000000001\r\n\
+         +++\r\n\
+         # Deck\r\n"
+    );
+    assert_eq!(
+        write_autogen(
+            "+++\nname = \"Kanji\"\n+++\n# Deck\n",
+            &deck_ids(4),
+            &[]
+        ),
+        "+++\nname = \"Kanji\"\n\n[autogen]\nid = 4\n+++\n# Deck\n"
+    );
+}
@@ -0,0 +1,204 @@
+use ankimdown::ankigen::anki2::{
+    read_collection, write_collection, CollectionData, DEFAULT_ID,
+};
+use ankimdown::ankigen::builder::*;
+use ankimdown::ankigen::db_model::deck::{Deck, DeckConfig};
+use ankimdown::ankigen::db_model::note::Note;
+use ankimdown::ankigen::stock::{
+    StockModel, BASIC_AND_REVERSED_MODEL_ID,
+};
+use rusqlite::Connection;
+
+const NOW: i64 = 1_700_000_123_456;
+
+fn note(front: &str, back: &str) -> Note {
+    Note::with_model(
+        0,
+        format!("guid {front}"),
+        &StockModel::BasicAndReversed.model(),
+        0,
+        vec![],
+        vec![front.to_string(), back.to_string()],
+    )
+    .unwrap()
+}
+
+fn note_ids(data: &CollectionData) -> Vec<(i64, i64)> {
+    data.notes
+        .iter()
+        .map(|note| (note.id, note.modified))
+        .collect()
+}
+
+fn build() -> CollectionData {
+    let mut builder =
+        CollectionBuilder::new().with_clock(FixedClock(NOW));
+    builder
+        .add_model(StockModel::BasicAndReversed.model())
+        .unwrap();
+    let config_id = builder
+        .add_deck_config(DeckConfig::new("Slow".to_string()))
+        .unwrap();
+    let mut deck = Deck::new("French".to_string());
+    deck.config_id = Some(config_id);
+    let deck_id = builder.add_deck(deck).unwrap();
+    builder.add_note(note("bonjour", "hello"), deck_id).unwrap();
+    builder.add_note(note("merci", "thanks"), deck_id).unwrap();
+    builder.build()
+}
+
+#[test]
+fn test_unique_ids() {
+    let data = build();
+    let (config_id, config) = &data.collection.deck_configs[0];
+    let (deck_id, deck) = &data.collection.decks[0];
+    assert_eq!(*config_id as i64, NOW);
+    assert_eq!(config.id, Some(*config_id));
+    assert_eq!(*deck_id as i64, NOW + 1);
+    assert_eq!(deck.id, NOW + 1);
+
+    let mut ids = vec![NOW, NOW + 1];
+    for (note, cards) in data.notes.iter().zip(data.cards.chunks(2)) {
+        ids.push(note.id);
+        ids.extend(cards.iter().map(|card| card.id));
+    }
+    assert_eq!(ids, (NOW..NOW + 8).collect::<Vec<_>>());
+}
+
+#[test]
+fn test_cards_wired() {
+    let data = build();
+    let deck_id = data.collection.decks[0].0;
+    assert_eq!(data.notes.len(), 2);
+    assert_eq!(data.cards.len(), 4);
+    for (idx, card) in data.cards.iter().enumerate() {
+        let note = &data.notes[idx / 2];
+        assert_eq!(card.note_id, note.id as usize);
+        assert_eq!(card.deck_id, deck_id);
+        assert_eq!(card.ordinal, idx as u64 % 2);
+        assert_eq!(card.due, idx as i64 / 2);
+        assert_eq!(card.modified, NOW / 1000);
+    }
+    assert_eq!(data.collection.config.next_position, 2);
+}
+
+#[test]
+fn test_timestamps() {
+    let data = build();
+    let collection = &data.collection;
+    assert_eq!(collection.created, 1_699_920_000);
+    assert_eq!(collection.created % 86400, 0);
+    assert_eq!(collection.modified, NOW);
+    assert_eq!(collection.scheme_mod_time, NOW);
+    assert_eq!(collection.models[0].1.modification, NOW / 1000);
+    assert_eq!(collection.decks[0].1.modified, NOW / 1000);
+    assert!(data
+        .notes
+        .iter()
+        .all(|note| note.modified == NOW / 1000));
+}
+
+#[test]
+fn test_registered() {
+    let data = build();
+    let config = &data.collection.config;
+    assert_eq!(data.collection.models.len(), 1);
+    assert_eq!(
+        data.collection.models[0].0 as u64,
+        BASIC_AND_REVERSED_MODEL_ID
+    );
+    assert_eq!(
+        config.current_model,
+        BASIC_AND_REVERSED_MODEL_ID.to_string()
+    );
+    assert_eq!(
+        config.current_deck,
+        data.collection.decks[0].0 as i64
+    );
+    assert_eq!(
+        data.collection.decks[0].1.config_id,
+        Some(data.collection.deck_configs[0].0)
+    );
+}
+
+#[test]
+fn test_reproducible() {
+    let first = build();
+    let second = build();
+    assert_eq!(first.collection, second.collection);
+    assert_eq!(note_ids(&first), note_ids(&second));
+    assert_eq!(first.cards, second.cards);
+}
+
+#[test]
+fn test_given_ids_kept() {
+    let mut builder =
+        CollectionBuilder::new().with_clock(FixedClock(NOW));
+    let mut deck = Deck::new("Given".to_string());
+    deck.id = NOW + 1;
+    assert_eq!(builder.add_deck(deck), Ok(NOW + 1));
+    assert_eq!(builder.next_id(), NOW);
+    assert_eq!(builder.next_id(), NOW + 2);
+}
+
+#[test]
+fn test_id_in_use() {
+    let mut builder =
+        CollectionBuilder::new().with_clock(FixedClock(NOW));
+    let deck_id =
+        builder.add_deck(Deck::new("A".to_string())).unwrap();
+    let mut config = DeckConfig::new("Taken".to_string());
+    config.id = Some(deck_id as usize);
+    assert_eq!(
+        builder.add_deck_config(config),
+        Err(BuilderError::IdInUse { id: deck_id })
+    );
+    let mut model = StockModel::Basic.model();
+    model.model_id = deck_id as u64;
+    assert_eq!(
+        builder.add_model(model),
+        Err(BuilderError::IdInUse { id: deck_id })
+    );
+
+    // A deck of the same id replaces the first one.
+    let mut deck = Deck::new("B".to_string());
+    deck.id = deck_id;
+    assert_eq!(builder.add_deck(deck), Ok(deck_id));
+    let data = builder.build();
+    assert_eq!(data.collection.decks.len(), 1);
+    assert_eq!(data.collection.decks[0].1.name, "B");
+    assert!(data.collection.deck_configs.is_empty());
+    assert!(data.collection.models.is_empty());
+}
+
+#[test]
+fn test_unknown_entries() {
+    let mut builder =
+        CollectionBuilder::new().with_clock(FixedClock(NOW));
+    assert_eq!(
+        builder.add_note(note("a", "b"), 42),
+        Err(BuilderError::UnknownDeck { id: 42 })
+    );
+    let model_id = StockModel::BasicAndReversed.model_id() as usize;
+    assert_eq!(
+        builder.add_note(note("a", "b"), DEFAULT_ID as i64),
+        Err(BuilderError::UnknownModel { id: model_id })
+    );
+}
+
+#[test]
+fn test_written() {
+    let data = build();
+    let mut conn = Connection::open_in_memory().unwrap();
+    write_collection(
+        &mut conn,
+        &data.collection,
+        &data.notes,
+        &data.cards,
+    )
+    .unwrap();
+    let read = read_collection(&conn).unwrap();
+    assert_eq!(read.collection.created, data.collection.created);
+    assert_eq!(note_ids(&read), note_ids(&data));
+    assert_eq!(read.cards, data.cards);
+}
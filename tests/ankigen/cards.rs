@@ -0,0 +1,118 @@
+use ankimdown::ankigen::cards::*;
+use ankimdown::ankigen::db_model::card::{CardQueue, CardType};
+use ankimdown::ankigen::db_model::model::*;
+use ankimdown::ankigen::db_model::note::Note;
+
+fn model(model_type: ModelType, templates: &[(&str, &str)]) -> Model {
+    Model::new(
+        None,
+        vec![
+            ModelField::new("Front".to_string(), 0),
+            ModelField::new("Back".to_string(), 1),
+        ],
+        7,
+        None,
+        None,
+        "Test".to_string(),
+        0,
+        templates
+            .iter()
+            .map(|(name, question)| {
+                ModelTemplate::new(
+                    name.to_string(),
+                    question.to_string(),
+                    "{{FrontSide}}".to_string(),
+                )
+            })
+            .collect(),
+        model_type,
+    )
+}
+
+fn note(model: &Model, front: &str, back: &str) -> Note {
+    Note::with_model(
+        100,
+        "guid".to_string(),
+        model,
+        5,
+        vec![],
+        vec![front.to_string(), back.to_string()],
+    )
+    .unwrap()
+}
+
+#[test]
+fn test_cloze_ordinals() {
+    let ordinals = cloze_ordinals(
+        "{{c2::Paris}} is in {{c1::France}}, {{c2::capital}} {{c0::x}} {{cx::y}}",
+    );
+    assert_eq!(ordinals.into_iter().collect::<Vec<_>>(), vec![1, 2]);
+}
+
+#[test]
+fn test_field_is_empty() {
+    assert!(field_is_empty(""));
+    assert!(field_is_empty("<br>&nbsp; "));
+    assert!(!field_is_empty("<b>x</b>"));
+}
+
+#[test]
+fn test_front_back_cards() {
+    let model = model(
+        ModelType::FrontBack,
+        &[
+            ("Card 1", "{{Front}}"),
+            ("Card 2", "{{#Back}}{{Back}}{{/Back}}"),
+        ],
+    );
+
+    let both = note(&model, "bonjour", "hello");
+    let cards = generate_cards(&both, &model, 3, 12, 1000).unwrap();
+    assert_eq!(cards.len(), 2);
+    assert_eq!(
+        cards.iter().map(|card| card.ordinal).collect::<Vec<_>>(),
+        vec![0, 1]
+    );
+    assert_eq!(
+        cards.iter().map(|card| card.id).collect::<Vec<_>>(),
+        vec![1000, 1001]
+    );
+    for card in &cards {
+        assert_eq!(card.note_id, 100);
+        assert_eq!(card.deck_id, 3);
+        assert_eq!(card.due, 12);
+        assert_eq!(card.modified, 5);
+        assert_eq!(card.card_type, CardType::New);
+        assert_eq!(card.queue, CardQueue::New);
+    }
+
+    let front_only = note(&model, "bonjour", "<br>");
+    assert_eq!(card_ordinals(&front_only, &model).unwrap(), vec![0]);
+}
+
+#[test]
+fn test_cloze_cards() {
+    let model =
+        model(ModelType::Cloze, &[("Cloze", "{{cloze:Front}}")]);
+    let note = note(
+        &model,
+        "{{c1::Paris}} is the capital of {{c3::France}}",
+        "{{c2::ignored}}",
+    );
+    assert_eq!(card_ordinals(&note, &model).unwrap(), vec![0, 2]);
+
+    let no_deletion = self::note(&model, "plain text", "");
+    assert_eq!(card_ordinals(&no_deletion, &model).unwrap(), vec![0]);
+}
+
+#[test]
+fn test_invalid_template() {
+    let model =
+        model(ModelType::FrontBack, &[("Broken", "{{#Front}}")]);
+    let error =
+        card_ordinals(&note(&model, "a", "b"), &model).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "template Broken of Test: missing {{/Front}}"
+    );
+}
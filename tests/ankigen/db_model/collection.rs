@@ -48,15 +48,15 @@ fn test_collection_config() {
     assert_eq!(config.new_spread, NewSpread::NewCardsDistribute);
     assert_eq!(config.collapse_time, 0);
     assert_eq!(config.time_limit, 0);
-    assert_eq!(config.estimated_times, false);
-    assert_eq!(config.due_counts, false);
+    assert!(!config.estimated_times);
+    assert!(!config.due_counts);
     assert_eq!(config.current_model, "");
     assert_eq!(config.next_position, 0);
     assert_eq!(config.sort_type, "");
-    assert_eq!(config.sort_backwards, false);
-    assert_eq!(config.add_to_current, false);
-    assert_eq!(config.day_learn_first, false);
-    assert_eq!(config.new_bury, true);
+    assert!(!config.sort_backwards);
+    assert!(!config.add_to_current);
+    assert!(!config.day_learn_first);
+    assert!(config.new_bury);
 }
 
 #[test]
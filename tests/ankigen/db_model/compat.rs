@@ -0,0 +1,157 @@
+use ankimdown::ankigen::db_model::collection::CollectionConfig;
+use ankimdown::ankigen::db_model::deck::{DeckConfig, LeechAction};
+use serde_json::{Map, Value};
+
+const DCONF_LEGACY: &str =
+    include_str!("../../fixtures/anki/dconf_legacy.json");
+const DCONF_MODERN: &str =
+    include_str!("../../fixtures/anki/dconf_modern.json");
+const COL_CONF_LEGACY: &str =
+    include_str!("../../fixtures/anki/col_conf_legacy.json");
+const COL_CONF_MODERN: &str =
+    include_str!("../../fixtures/anki/col_conf_modern.json");
+
+/// Whether `written` holds everything of `original`. Numbers are
+/// compared by value, `1` and `1.0` being the same for Anki, and so
+/// is an id written as a number or a string.
+fn is_compatible(original: &Value, written: &Value) -> bool {
+    match (original, written) {
+        (Value::Number(a), Value::Number(b)) => {
+            a.as_f64() == b.as_f64()
+        }
+        (Value::Number(a), Value::String(b))
+        | (Value::String(b), Value::Number(a)) => {
+            b.parse::<f64>().ok() == a.as_f64()
+        }
+        (Value::Array(a), Value::Array(b)) => {
+            a.len() == b.len()
+                && a.iter().zip(b).all(|(a, b)| is_compatible(a, b))
+        }
+        (Value::Object(a), Value::Object(b)) => {
+            a.iter().all(|(key, a)| {
+                b.get(key).is_some_and(|b| is_compatible(a, b))
+            })
+        }
+        (a, b) => a == b,
+    }
+}
+
+fn dconf(source: &str) -> Vec<(Value, DeckConfig)> {
+    let configs: Map<String, Value> =
+        serde_json::from_str(source).unwrap();
+    configs
+        .into_values()
+        .map(|value| {
+            let config =
+                serde_json::from_value(value.clone()).unwrap();
+            (value, config)
+        })
+        .collect()
+}
+
+fn assert_round_trip<T>(original: &Value, parsed: &T)
+where
+    T: serde::Serialize + serde::de::DeserializeOwned + PartialEq,
+{
+    let written = serde_json::to_value(parsed).unwrap();
+    assert!(
+        is_compatible(original, &written),
+        "{original}\nwritten as\n{written}"
+    );
+    let reparsed: T = serde_json::from_value(written).unwrap();
+    assert!(reparsed == *parsed);
+}
+
+#[test]
+fn test_dconf_legacy() {
+    let configs = dconf(DCONF_LEGACY);
+    assert_eq!(configs.len(), 1);
+    let (original, config) = &configs[0];
+    assert_eq!(config.name, "Default");
+    assert_eq!(config.new_config.delays, vec![1.0, 10.0]);
+    assert_eq!(config.lapse_config.interval_increase, 0.0);
+    assert_eq!(
+        config.lapse_config.leech_action,
+        LeechAction::Suspend
+    );
+    assert_eq!(config.review_config.interval_factor, 1.0);
+    assert_eq!(config.review_config.fuzz, 0.05);
+    assert!(!config.timer);
+    assert_eq!(config.filtered, Some(false));
+    assert_round_trip(original, config);
+}
+
+#[test]
+fn test_dconf_modern() {
+    let configs = dconf(DCONF_MODERN);
+    assert_eq!(configs.len(), 2);
+    for (original, config) in &configs {
+        assert_round_trip(original, config);
+    }
+
+    let (_, exam) = configs
+        .iter()
+        .find(|(_, config)| config.name == "Exam")
+        .unwrap();
+    assert_eq!(exam.id, Some(1697040123456));
+    assert_eq!(exam.update_seq_number, -1);
+    assert!(exam.timer);
+    assert_eq!(exam.new_config.delays, vec![0.5, 5.0, 60.0]);
+    assert_eq!(exam.lapse_config.delays, vec![2.5, 30.0]);
+    assert_eq!(exam.lapse_config.interval_increase, 0.5);
+    assert_eq!(exam.review_config.interval_factor, 0.85);
+    assert_eq!(exam.review_config.other["hardFactor"], 1.1);
+    assert_eq!(exam.other["desiredRetention"], 0.85);
+
+    // Settings modern Anki leaves out take their defaults
+    let defaults = DeckConfig::new("Exam".to_string());
+    assert_eq!(
+        exam.new_config.separate,
+        defaults.new_config.separate
+    );
+    assert_eq!(exam.review_config.fuzz, defaults.review_config.fuzz);
+    assert_eq!(
+        exam.review_config.min_space,
+        defaults.review_config.min_space
+    );
+}
+
+#[test]
+fn test_col_conf_legacy() {
+    let original: Value =
+        serde_json::from_str(COL_CONF_LEGACY).unwrap();
+    let config: CollectionConfig =
+        serde_json::from_value(original.clone()).unwrap();
+    assert_eq!(config.current_model, "1342697561419");
+    assert_eq!(config.current_deck, 1);
+    assert_eq!(config.collapse_time, 1200);
+    assert!(!config.day_learn_first);
+    assert!(config.other.is_empty());
+    assert_round_trip(&original, &config);
+}
+
+#[test]
+fn test_col_conf_modern() {
+    let original: Value =
+        serde_json::from_str(COL_CONF_MODERN).unwrap();
+    let config: CollectionConfig =
+        serde_json::from_value(original.clone()).unwrap();
+    assert_eq!(config.current_model, "1697040000123");
+    assert_eq!(
+        config.active_decks,
+        vec![1697040123457, 1697040123458]
+    );
+    assert_eq!(config.next_position, 42);
+    assert_eq!(config.last_unburied, Some(19642));
+    assert!(config.new_bury);
+    assert_eq!(config.other["schedVer"], 2);
+    assert_eq!(config.other["creationOffset"], -120);
+    assert_round_trip(&original, &config);
+}
+
+#[test]
+fn test_col_conf_empty() {
+    let config: CollectionConfig =
+        serde_json::from_str("{}").unwrap();
+    assert_eq!(config, CollectionConfig::new());
+}
@@ -1,4 +1,6 @@
 mod card;
 mod collection;
+mod compat;
 mod deck;
 mod model;
+mod note;
@@ -0,0 +1,100 @@
+use ankimdown::ankigen::db_model::model::*;
+use ankimdown::ankigen::db_model::note::*;
+use ankimdown::ankigen::util::field_checksum;
+
+fn model(sort_field_index: usize) -> Model {
+    Model::new(
+        None,
+        vec![
+            ModelField::new("Front".to_string(), 0),
+            ModelField::new("Back".to_string(), 1),
+        ],
+        42,
+        None,
+        None,
+        "Basic".to_string(),
+        sort_field_index,
+        vec![],
+        ModelType::FrontBack,
+    )
+}
+
+#[test]
+fn test_note_with_model() {
+    let note = Note::with_model(
+        1,
+        "guid".to_string(),
+        &model(1),
+        2,
+        vec![],
+        vec![
+            "<i>bonjour</i>".to_string(),
+            "<p>hello &amp; welcome</p>".to_string(),
+        ],
+    )
+    .unwrap();
+
+    assert_eq!(note.model_id, 42);
+    assert_eq!(note.sort_field, "hello & welcome");
+    assert_eq!(note.checksum, field_checksum("bonjour"));
+    assert_eq!(note.update_seq_number, -1);
+}
+
+#[test]
+fn test_note_with_model_field_count() {
+    let error = Note::with_model(
+        1,
+        "guid".to_string(),
+        &model(0),
+        2,
+        vec![],
+        vec!["only one".to_string()],
+    )
+    .unwrap_err();
+    assert_eq!(error, "Model Basic has 2 fields, got 1");
+}
+
+#[test]
+fn test_note_tag_hierarchy() {
+    let tag = NoteTag::new("lang::french::verbs").unwrap();
+    assert_eq!(
+        tag.levels().collect::<Vec<_>>(),
+        vec!["lang", "french", "verbs"]
+    );
+    assert_eq!(tag.parent().unwrap().name, "lang::french");
+    assert!(NoteTag::new("lang").unwrap().parent().is_none());
+    assert!(tag.is_under(&NoteTag::new("Lang::French").unwrap()));
+    assert!(!tag.is_under(&NoteTag::new("lang::fr").unwrap()));
+
+    assert_eq!(
+        NoteTag::new("lang::::verbs").unwrap_err(),
+        "Tag name cannot have an empty level"
+    );
+    assert!(NoteTag::new("::lang").is_err());
+    assert!(NoteTag::new("a b").is_err());
+}
+
+#[test]
+fn test_note_tags_column() {
+    let tags = vec![
+        NoteTag::new("french").unwrap(),
+        NoteTag::new("lang::verbs").unwrap(),
+    ];
+    assert_eq!(tags_to_string(&tags), " french lang::verbs ");
+    assert_eq!(tags_to_string(&[]), "");
+    assert_eq!(tags_from_str(" french  lang::verbs "), tags);
+
+    let note = Note::with_model(
+        1,
+        "guid".to_string(),
+        &model(0),
+        2,
+        tags,
+        vec!["a".to_string(), "b".to_string()],
+    )
+    .unwrap();
+    let json = serde_json::to_value(&note).unwrap();
+    assert_eq!(json["tags"], " french lang::verbs ");
+    let read: Note = serde_json::from_value(json).unwrap();
+    assert_eq!(read.tags, note.tags);
+}
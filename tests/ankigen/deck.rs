@@ -0,0 +1,382 @@
+use ankimdown::ankigen::db_model::note::NoteTag;
+use ankimdown::ankigen::deck::{Deck, DeckError};
+use ankimdown::ankigen::stock::StockModel;
+
+#[test]
+fn test_deck_from_example() {
+    let deck = Deck::from_markdown(include_str!(
+        "../../examples/markdown_databse/deck.md"
+    ))
+    .unwrap();
+
+    assert_eq!(deck.name, "Deck name");
+    assert_eq!(deck.description, "Deck description");
+    assert_eq!(deck.metadata.autogen.id, 0);
+    assert!(deck.metadata.settings.get("Autogen").is_some());
+    assert!(deck.information.is_empty());
+}
+
+#[test]
+fn test_deck_with_information() {
+    let deck = Deck::from_markdown(include_str!(
+        "../../examples/markdown_databse/foo/sample_database.md"
+    ))
+    .unwrap();
+
+    assert_eq!(deck.name, "Deck name");
+    assert_eq!(deck.description, "This is a sample description");
+    let maps = deck.metadata.settings.get("Maps").unwrap();
+    assert_eq!(maps.get("Description").unwrap().value, "Meaning");
+
+    assert_eq!(deck.information.len(), 1);
+    let hello = &deck.information[0];
+    assert_eq!(hello.word, "hello");
+    assert_eq!(hello.definitions, vec!["a greeting"]);
+
+    let templates = hello.metadata.get("Templates").unwrap();
+    let names = templates
+        .children
+        .iter()
+        .map(|entry| entry.key.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(names, vec!["Simple", "Reverse"]);
+    assert_eq!(
+        hello.metadata.get_path(&["Autogen", "id"]).unwrap().value,
+        "0"
+    );
+}
+
+#[test]
+fn test_deck_definitions() {
+    let deck = Deck::from_markdown(
+        "# Deck\n\nFirst *line*.\n\nSecond.\n\n\
+         # bonjour\n## Meaning\n- hello\n- good **day**\n\n\
+         # merci\n## meaning:\nthank you\n",
+    )
+    .unwrap();
+
+    assert_eq!(deck.description, "First _line_.\n\nSecond.");
+    assert_eq!(deck.information.len(), 2);
+    assert_eq!(
+        deck.information[0].definitions,
+        vec!["hello", "good **day**"]
+    );
+    assert_eq!(deck.information[1].word, "merci");
+    assert_eq!(deck.information[1].definitions, vec!["thank you"]);
+}
+
+#[test]
+fn test_deck_errors() {
+    assert_eq!(
+        Deck::from_markdown("just text\n").unwrap_err(),
+        DeckError::MissingName
+    );
+    assert_eq!(
+        Deck::from_markdown(
+            "# Deck\n## Metadata\n- Autogen:\n    - id: abc\n"
+        )
+        .unwrap_err(),
+        DeckError::InvalidMetadata {
+            key: "Autogen.id".to_string(),
+            value: "abc".to_string()
+        }
+    );
+    // Quotes, rules and raw HTML no longer reject the whole file.
+    assert!(
+        Deck::from_markdown("# Deck\n> quote\n\n---\n<br>\n").is_ok()
+    );
+}
+
+const COURSE: &str = "# Japanese\n\nA course.\n\n\
+    ### 日本\n#### Meaning\n- Japan\n\
+    ## Metadata\n- Note level: 3\n\
+    ## Note types\n### Kanji\n#### Fields\n- Kanji\n\
+    #### Templates\n##### Card\n```\n{{Kanji}}\n```\n\
+    ## Lesson 1\nThe basics.\n\
+    ### Metadata\n- Autogen:\n    - id: 7\n\
+    ### 水\n#### Meaning\n- water\n\
+    ### 火\n#### Meaning\n- fire\n\
+    ## Lesson 2\n\
+    ### Numbers\n#### 一\n##### Meaning\n- one\n\
+    ### 人\n#### Meaning\n- person\n";
+
+#[test]
+fn test_nested_decks() {
+    let deck = Deck::from_markdown(COURSE).unwrap();
+    assert_eq!(deck.name, "Japanese");
+    assert_eq!(deck.description, "A course.");
+    assert_eq!(deck.metadata.note_level, 3);
+    assert_eq!(deck.note_types.len(), 1);
+    assert_eq!(deck.information.len(), 1);
+    assert_eq!(deck.information[0].definitions, vec!["Japan"]);
+
+    let lesson = &deck.subdecks[0];
+    assert_eq!(lesson.name, "Lesson 1");
+    assert_eq!(lesson.description, "The basics.");
+    assert_eq!(lesson.metadata.autogen.id, 7);
+    let words = lesson
+        .information
+        .iter()
+        .map(|information| information.word.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(words, vec!["水", "火"]);
+
+    let names = deck
+        .all_subdecks()
+        .into_iter()
+        .map(|(name, _)| name)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        names,
+        vec!["Japanese::Lesson 1", "Japanese::Lesson 2"]
+    );
+    let notes = deck
+        .notes()
+        .into_iter()
+        .map(|(name, information)| {
+            format!("{name} > {}", information.word)
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        notes,
+        vec![
+            "Japanese > 日本",
+            "Japanese::Lesson 1 > 水",
+            "Japanese::Lesson 1 > 火",
+            "Japanese::Lesson 2 > Numbers",
+            "Japanese::Lesson 2 > 人",
+        ]
+    );
+    assert_eq!(deck.guids().unwrap().len(), 5);
+}
+
+#[test]
+fn test_note_level_4() {
+    let deck = Deck::from_markdown(
+        &COURSE.replace("Note level: 3", "Note level: 4"),
+    )
+    .unwrap();
+    assert!(deck.information.is_empty());
+    let names = deck
+        .all_subdecks()
+        .into_iter()
+        .map(|(name, _)| name)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        names,
+        vec![
+            "Japanese::日本",
+            "Japanese::Lesson 1",
+            "Japanese::Lesson 1::水",
+            "Japanese::Lesson 1::火",
+            "Japanese::Lesson 2",
+            "Japanese::Lesson 2::Numbers",
+            "Japanese::Lesson 2::人",
+        ]
+    );
+    let numbers = &deck.subdecks[2].subdecks[0];
+    assert_eq!(numbers.information[0].word, "一");
+    assert_eq!(numbers.information[0].definitions, vec!["one"]);
+}
+
+#[test]
+fn test_note_level_errors() {
+    assert_eq!(
+        Deck::from_markdown(
+            "# A\n## Metadata\n- Note level: 2\n# B\n"
+        )
+        .unwrap_err()
+        .to_string(),
+        "level 1 heading B starts a second deck, notes are below \
+         level 1"
+    );
+    assert_eq!(
+        Deck::from_markdown("# A\n## Metadata\n- Note level: 7\n")
+            .unwrap_err(),
+        DeckError::InvalidMetadata {
+            key: "Note level".to_string(),
+            value: "7".to_string()
+        }
+    );
+}
+
+#[test]
+fn test_yaml_front_matter() {
+    let deck = Deck::from_markdown(
+        "---\n\
+         name: Kanji\n\
+         description: Characters.\n\
+         note_level: 2\n\
+         tags: [japanese, kanji]\n\
+         config: Slow\n\
+         autogen:\n  id: 12\n\
+         ---\n\
+         # Deck\n\nIgnored.\n\n\
+         ## 水\n\
+         ---\n\
+         model: Basic (and reversed card)\n\
+         autogen: {id: 3, guid: 'a''b', model-id: 4}\n\
+         ---\n\
+         ### Meaning\n- water\n\
+         ## 火\n### Meaning\n- fire\n",
+    )
+    .unwrap();
+
+    assert_eq!(deck.name, "Kanji");
+    assert_eq!(deck.description, "Characters.");
+    assert_eq!(deck.metadata.note_level, 2);
+    assert_eq!(deck.metadata.autogen.id, 12);
+    let settings = &deck.metadata.settings;
+    let tags = &settings.get("Tags").unwrap().children;
+    assert_eq!(tags[1].key, "kanji");
+    assert_eq!(settings.get("Config").unwrap().value, "Slow");
+
+    let water = &deck.information[0];
+    assert_eq!(water.definitions, vec!["water"]);
+    assert_eq!(water.model_name(), Some("Basic (and reversed card)"));
+    assert_eq!(
+        water.stock_model(),
+        Some(StockModel::BasicAndReversed)
+    );
+    assert_eq!(water.guid("Kanji"), "a'b");
+    assert_eq!(
+        water
+            .metadata
+            .get_path(&["Autogen", "model id"])
+            .unwrap()
+            .value,
+        "4"
+    );
+    assert_eq!(
+        deck.information[1].stock_model(),
+        Some(StockModel::Basic)
+    );
+}
+
+#[test]
+fn test_toml_front_matter() {
+    let deck = Deck::from_markdown(
+        "+++\n\
+         name = \"Kanji\"\n\
+         [autogen]\n\
+         id = 5\n\
+         +++\n\
+         # Deck\n\
+         # 水\n\
+         +++\n\
+         model = \"Cloze\"\n\
+         +++\n\
+         ## Meaning\n- {{c1::water}}\n",
+    )
+    .unwrap();
+
+    assert_eq!(deck.name, "Kanji");
+    assert_eq!(deck.metadata.autogen.id, 5);
+    assert_eq!(
+        deck.information[0].stock_model(),
+        Some(StockModel::Cloze)
+    );
+}
+
+#[test]
+fn test_front_matter_over_lists() {
+    let deck = Deck::from_markdown(
+        "# Deck\n\
+         ---\n\
+         autogen:\n  id: 1\n\
+         ---\n\
+         ## Metadata\n\
+         - Autogen:\n    - id: 2\n\
+         - Note level: 1\n\
+         # 水\n\
+         ## Metadata\n- Model: Unknown\n",
+    )
+    .unwrap();
+
+    assert_eq!(deck.metadata.autogen.id, 1);
+    assert!(deck.metadata.settings.get("Note level").is_some());
+    assert_eq!(deck.information[0].stock_model(), None);
+}
+
+#[test]
+fn test_front_matter_errors() {
+    let error = Deck::from_markdown("---\nname: [\n---\n# Deck\n")
+        .unwrap_err();
+    assert!(matches!(error, DeckError::FrontMatter(_)));
+    assert!(error
+        .to_string()
+        .starts_with("invalid YAML front matter"));
+
+    let error = Deck::from_markdown("# Deck\n# 水\n---\n- a\n---\n")
+        .unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "invalid YAML front matter: not a mapping"
+    );
+
+    assert!(matches!(
+        Deck::from_markdown("+++\nname = \n+++\n# Deck\n")
+            .unwrap_err(),
+        DeckError::FrontMatter(_)
+    ));
+    assert_eq!(
+        Deck::from_markdown("---\nautogen:\n  id: x\n---\n# Deck\n")
+            .unwrap_err(),
+        DeckError::InvalidMetadata {
+            key: "Autogen.id".to_string(),
+            value: "x".to_string()
+        }
+    );
+}
+
+#[test]
+fn test_tags() {
+    let deck = Deck::from_markdown(
+        "---\ntags: [japanese]\n---\n\
+         #course\n\n\
+         # Japanese\n\nA course.\n\n#lang::ja\n\n\
+         ## Metadata\n- Note level: 3\n- Tags: jlpt\n\
+         ## Lesson 1\n#lesson1\n\
+         ### 水\n#nature #Japanese\n#### Meaning\n- water\n\
+         ### 火\n#### Metadata\n- Tags:\n    - nature::fire\n\
+         ## Lesson 2\n### 人\n",
+    )
+    .unwrap();
+
+    let names = |tags: &[NoteTag]| {
+        tags.iter().map(|tag| tag.name.clone()).collect::<Vec<_>>()
+    };
+    assert_eq!(deck.description, "A course.");
+    assert_eq!(
+        names(&deck.metadata.tags),
+        vec!["japanese", "course", "lang::ja", "jlpt"]
+    );
+    let notes = deck.notes();
+    assert_eq!(
+        names(&notes[0].1.tags),
+        vec![
+            "japanese", "course", "lang::ja", "jlpt", "lesson1",
+            "nature"
+        ]
+    );
+    assert_eq!(
+        names(&notes[1].1.tags)[4..],
+        vec!["lesson1", "nature::fire"]
+    );
+    assert_eq!(names(&notes[2].1.tags).len(), 4);
+
+    assert_eq!(
+        Deck::from_markdown("# Deck\n# word\n- Tags: a__b\n")
+            .unwrap()
+            .information[0]
+            .tags,
+        vec![]
+    );
+    assert!(matches!(
+        Deck::from_markdown(
+            "# Deck\n# word\n## Metadata\n- Tags: a__b\n"
+        )
+        .unwrap_err(),
+        DeckError::Tag(_)
+    ));
+}
@@ -0,0 +1,250 @@
+use ankimdown::ankigen::db_model::model::{Model, ModelType};
+use ankimdown::ankigen::deck::Deck;
+use ankimdown::ankigen::field_map::*;
+use ankimdown::ankigen::media::MediaManifest;
+use ankimdown::ankigen::note_type::parse_note_types;
+use ankimdown::ankigen::stock::StockModel;
+use ankimdown::markdown::ast::Node;
+
+fn vocabulary() -> Model {
+    parse_note_types(include_str!(
+        "../../examples/markdown_databse/models.md"
+    ))
+    .unwrap()
+    .remove(0)
+}
+
+#[test]
+fn test_default_map() {
+    let deck = Deck::from_markdown(
+        "# Deck\n# hello\n## Meaning\n- a **greeting**\n\
+         ## Metadata\n- id: 1\n",
+    )
+    .unwrap();
+    let model = StockModel::Basic.model();
+    let map = deck.field_map(&model).unwrap();
+    assert_eq!(map, FieldMap::simple_information(&model));
+    assert_eq!(map.mappings[0].source, FieldSource::Heading);
+
+    let mapped = map.fields(&deck.information[0]);
+    assert_eq!(
+        mapped.fields,
+        vec![
+            "hello",
+            "<ul><li>a <strong>greeting</strong></li></ul>"
+        ]
+    );
+    assert!(mapped.diagnostics.is_empty());
+}
+
+#[test]
+fn test_default_cloze_map() {
+    let deck = Deck::from_markdown(
+        "# Deck\n# capital\n## Meaning\n==Paris==\n",
+    )
+    .unwrap();
+    let model = StockModel::Cloze.model();
+    assert_eq!(model.model_type, ModelType::Cloze);
+    let mapped =
+        deck.field_map(&model).unwrap().fields(&deck.information[0]);
+    assert_eq!(mapped.fields, vec!["<p>{{c1::Paris}}</p>", ""]);
+    assert!(mapped.diagnostics.is_empty());
+}
+
+#[test]
+fn test_sections_fill_fields_of_the_same_name() {
+    let deck = Deck::from_markdown(
+        "# Deck\n# 水\n## Reading\nみず\n## Meaning\nwater\n\
+         ## Example\n水を飲む\n",
+    )
+    .unwrap();
+    let mapped = deck
+        .field_map(&vocabulary())
+        .unwrap()
+        .fields(&deck.information[0]);
+    assert_eq!(
+        mapped.fields,
+        vec![
+            "水",
+            "<p>みず</p>",
+            "<p>water</p>",
+            "<p>水を飲む</p>",
+            ""
+        ]
+    );
+    assert!(mapped.diagnostics.is_empty());
+}
+
+#[test]
+fn test_maps_metadata() {
+    let deck = Deck::from_markdown(
+        "# Deck\n## Deck metadata\n- Maps:\n    - Front: Question\n\
+         \x20   - Back: heading\n\
+         # hello\n## Question\nWhat do you say?\n## Notes\ninformal\n",
+    )
+    .unwrap();
+    let map = deck.field_map(&StockModel::Basic.model()).unwrap();
+    assert_eq!(
+        map.mappings[0].source,
+        FieldSource::Section("Question".to_string())
+    );
+    assert_eq!(map.mappings[1].source, FieldSource::Heading);
+
+    let mapped = map.fields(&deck.information[0]);
+    assert_eq!(
+        mapped.fields,
+        vec!["<p>What do you say?</p>", "hello"]
+    );
+    assert_eq!(
+        mapped.diagnostics,
+        vec![FieldMapDiagnostic::UnmappedSection {
+            word: "hello".to_string(),
+            section: "Notes".to_string()
+        }]
+    );
+    assert_eq!(
+        mapped.diagnostics[0].to_string(),
+        "section Notes of hello is not mapped to a field"
+    );
+}
+
+#[test]
+fn test_sample_database_maps() {
+    let deck = Deck::from_markdown(include_str!(
+        "../../examples/markdown_databse/foo/sample_database.md"
+    ))
+    .unwrap();
+    let hello = &deck.information[0];
+    let stock = hello.stock_model().unwrap();
+    assert_eq!(stock, StockModel::BasicAndReversed);
+
+    // `Description: Meaning` applies to Back, which takes Meaning.
+    let map = deck.field_map(&stock.model()).unwrap();
+    assert_eq!(map.mappings.len(), 2);
+    assert_eq!(map.mappings[0].source, FieldSource::Heading);
+    assert_eq!(map.mappings[1].field, "Back");
+    assert_eq!(
+        map.mappings[1].source,
+        FieldSource::Section("Meaning".to_string())
+    );
+    assert!(map.mappings[1].required);
+    assert_eq!(
+        deck.field_map(&StockModel::Basic.model()).unwrap().mappings,
+        map.mappings
+    );
+    let mapped = map.fields(hello);
+    assert_eq!(
+        mapped.fields,
+        vec!["hello", "<ol><li>a greeting</li></ol>"]
+    );
+    assert!(mapped.diagnostics.is_empty());
+}
+
+#[test]
+fn test_unknown_field() {
+    let deck = Deck::from_markdown(
+        "# Deck\n## Deck metadata\n- Maps:\n    - Description: Usage\n\
+         # hello\n## Usage\n1. a greeting\n",
+    )
+    .unwrap();
+
+    let error =
+        deck.field_map(&StockModel::Basic.model()).unwrap_err();
+    assert_eq!(
+        error,
+        FieldMapError::UnknownField {
+            model: "Basic".to_string(),
+            field: "Description".to_string()
+        }
+    );
+    assert_eq!(
+        error.to_string(),
+        "Maps names field Description, which note type Basic does \
+         not have"
+    );
+
+    let model = parse_note_types(
+        "# Word\n## Fields\n- Word\n- Description\n\
+         ## Templates\n### Card\n```\n{{Word}}\n```\n\
+         ```\n{{Description}}\n```\n",
+    )
+    .unwrap()
+    .remove(0);
+    let mapped =
+        deck.field_map(&model).unwrap().fields(&deck.information[0]);
+    assert_eq!(
+        mapped.fields,
+        vec!["hello", "<ol><li>a greeting</li></ol>"]
+    );
+    assert!(mapped.diagnostics.is_empty());
+}
+
+#[test]
+fn test_field_images_use_media_names() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("diagram.png"), b"png").unwrap();
+    let source =
+        "# Deck\n# hello\n## Meaning\n![flow](diagram.png)\n";
+    let mut manifest = MediaManifest::new();
+    manifest
+        .collect(&Node::parse_document(source).unwrap(), dir.path())
+        .unwrap();
+    let name = manifest.name_for(dir.path(), "diagram.png").unwrap();
+    assert_ne!(name, "diagram.png");
+
+    let deck = Deck::from_markdown(source).unwrap();
+    let map = deck.field_map(&StockModel::Basic.model()).unwrap();
+    let image_source = manifest.image_source(dir.path());
+    let mapped =
+        map.fields_with_images(&deck.information[0], &image_source);
+    assert_eq!(
+        mapped.fields[1],
+        format!("<p><img src=\"{name}\" alt=\"flow\"></p>")
+    );
+    assert_eq!(
+        map.fields(&deck.information[0]).fields[1],
+        "<p><img src=\"diagram.png\" alt=\"flow\"></p>"
+    );
+}
+
+#[test]
+fn test_raw_html_in_fields() {
+    let deck = Deck::from_markdown(
+        "# Deck\n# hello\n## Meaning\n\
+         a <span class=\"jp\" onmouseover=\"x()\">greeting</span><br>\n\
+         \n---\n\n> informal\n",
+    )
+    .unwrap();
+    let mapped = deck
+        .field_map(&StockModel::Basic.model())
+        .unwrap()
+        .fields(&deck.information[0]);
+    assert_eq!(
+        mapped.fields[1],
+        "<p>a <span class=\"jp\">greeting</span><br></p><hr>\
+         <blockquote><p>informal</p></blockquote>"
+    );
+}
+
+#[test]
+fn test_missing_section() {
+    let deck =
+        Deck::from_markdown("# Deck\n# hello\n## Meanings\n- hi\n")
+            .unwrap();
+    let mapped = deck
+        .field_map(&StockModel::Basic.model())
+        .unwrap()
+        .fields(&deck.information[0]);
+    assert_eq!(mapped.fields, vec!["hello", ""]);
+    assert_eq!(
+        mapped
+            .diagnostics
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>(),
+        vec![
+            "hello has no section Meaning, field Back is left empty",
+            "section Meanings of hello is not mapped to a field",
+        ]
+    );
+}
@@ -0,0 +1,95 @@
+use ankimdown::ankigen::deck::Deck;
+use ankimdown::ankigen::guid::{GuidCollision, GuidRegistry};
+use ankimdown::ankigen::util::base91_encode;
+
+#[test]
+fn test_guid_from_deck_and_word() {
+    let deck = Deck::from_markdown(
+        "# French\n# bonjour\n## Meaning\n- hello\n",
+    )
+    .unwrap();
+    let guids = deck.guids().unwrap();
+    assert_eq!(
+        guids,
+        vec![base91_encode(&[
+            "French".to_string(),
+            "bonjour".to_string()
+        ])]
+    );
+}
+
+#[test]
+fn test_guid_stable_across_edits() {
+    let before = Deck::from_markdown(
+        "# French\n# bonjour\n## Meaning\n- hello\n",
+    )
+    .unwrap();
+    let after = Deck::from_markdown(
+        "# French\nNew description.\n\
+         # bonjour\n## Meaning\n- hello\n- good morning\n",
+    )
+    .unwrap();
+    assert_eq!(before.guids(), after.guids());
+}
+
+#[test]
+fn test_guid_from_explicit_id() {
+    let renamed = Deck::from_markdown(
+        "# French\n# bonjour\n## Meaning\n- hello\n\
+         ## Metadata\n- id: greeting\n",
+    )
+    .unwrap();
+    let moved = Deck::from_markdown(
+        "# Greetings\n# salut\n## Meaning\n- hi\n\
+         ## Metadata\n- id: greeting\n",
+    )
+    .unwrap();
+    assert_eq!(
+        renamed.guids().unwrap(),
+        vec![base91_encode(&["greeting".to_string()])]
+    );
+    assert_eq!(renamed.guids(), moved.guids());
+}
+
+#[test]
+fn test_guid_collisions() {
+    let deck = Deck::from_markdown(
+        "# French\n# bonjour\n## Meaning\n- hello\n\
+         # bonjour\n## Meaning\n- good day\n",
+    )
+    .unwrap();
+    let collisions = deck.guids().unwrap_err();
+    assert_eq!(collisions.len(), 1);
+    assert_eq!(collisions[0].first, "French > bonjour");
+    assert_eq!(
+        collisions[0].to_string(),
+        format!(
+            "French > bonjour has the same GUID {} as French > bonjour",
+            collisions[0].guid
+        )
+    );
+}
+
+#[test]
+fn test_guid_registry_across_decks() {
+    let first = Deck::from_markdown(
+        "# A\n# word\n## Metadata\n- id: shared\n",
+    )
+    .unwrap();
+    let second = Deck::from_markdown(
+        "# B\n# other\n## Metadata\n- id: shared\n",
+    )
+    .unwrap();
+
+    let mut registry = GuidRegistry::new();
+    first.register_guids(&mut registry);
+    second.register_guids(&mut registry);
+    assert_eq!(
+        registry.into_result().unwrap_err(),
+        vec![GuidCollision {
+            guid: base91_encode(&["shared".to_string()]),
+            first: "A > word".to_string(),
+            second: "B > other".to_string(),
+        }]
+    );
+}
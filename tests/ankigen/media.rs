@@ -0,0 +1,97 @@
+use ankimdown::ankigen::media::*;
+use ankimdown::markdown::ast::Node;
+use ankimdown::markdown::html::HtmlRenderer;
+
+fn write_file(dir: &std::path::Path, name: &str, content: &[u8]) {
+    let path = dir.join(name);
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(path, content).unwrap();
+}
+
+#[test]
+fn test_is_local_reference() {
+    assert!(is_local_reference("diagram.png"));
+    assert!(is_local_reference("../img/diagram.png"));
+    assert!(!is_local_reference("https://example.com/a.png"));
+    assert!(!is_local_reference("data:image/png;base64,AAAA"));
+    assert!(!is_local_reference(""));
+}
+
+#[test]
+fn test_image_urls() {
+    let nodes = Node::parse_document(
+        "# ![a](a.png)\n\n- **![b](b.png)**\n- [![c](c.png)](x)\n",
+    )
+    .unwrap();
+    assert_eq!(image_urls(&nodes), vec!["a.png", "b.png", "c.png"]);
+}
+
+#[test]
+fn test_collect() {
+    let dir = tempfile::tempdir().unwrap();
+    write_file(dir.path(), "deck/diagram.png", b"first");
+    write_file(dir.path(), "deck/sub/diagram.png", b"second");
+    write_file(dir.path(), "deck/copy.png", b"first");
+    let nodes = Node::parse_document(
+        "![one](diagram.png) ![two](sub/diagram.png) \
+         ![three](copy.png) ![web](https://example.com/a.png)\n",
+    )
+    .unwrap();
+
+    let base_dir = dir.path().join("deck");
+    let mut manifest = MediaManifest::new();
+    manifest.collect(&nodes, &base_dir).unwrap();
+
+    let names = manifest
+        .files()
+        .iter()
+        .map(|file| file.name.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(names.len(), 3);
+    assert!(names[0].starts_with("diagram-"));
+    assert!(names[0].ends_with(".png"));
+    assert_ne!(names[0], names[1]);
+    assert!(names[2].starts_with("copy-"));
+    assert_eq!(
+        manifest.name_for(&base_dir, "diagram.png"),
+        Some(names[0])
+    );
+    assert_eq!(manifest.name_for(&base_dir, "missing.png"), None);
+
+    let image_source = manifest.image_source(&base_dir);
+    let html = HtmlRenderer::new()
+        .with_image_source(&image_source)
+        .render_nodes(&nodes);
+    assert!(html.contains(&format!(
+        "<img src=\"{}\" alt=\"one\">",
+        names[0]
+    )));
+
+    let media: serde_json::Value =
+        serde_json::from_str(&manifest.to_json()).unwrap();
+    assert_eq!(media["0"], names[0]);
+    assert_eq!(media["2"], names[2]);
+}
+
+#[test]
+fn test_name_is_stable() {
+    let dir = tempfile::tempdir().unwrap();
+    write_file(dir.path(), "a/img.png", b"same");
+    write_file(dir.path(), "b/img.png", b"same");
+
+    let mut manifest = MediaManifest::new();
+    let first = manifest.add(&dir.path().join("a/img.png")).unwrap();
+    let second = manifest.add(&dir.path().join("b/img.png")).unwrap();
+    assert_eq!(first, second);
+    assert_eq!(manifest.files().len(), 1);
+}
+
+#[test]
+fn test_missing_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let nodes = Node::parse_document("![x](missing.png)\n").unwrap();
+    let error = MediaManifest::new()
+        .collect(&nodes, dir.path())
+        .unwrap_err();
+    assert!(error.to_string().contains("missing.png"));
+}
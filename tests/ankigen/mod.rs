@@ -1 +1,17 @@
+mod anki2;
+mod apkg;
+mod autogen;
+mod builder;
+mod cards;
 mod db_model;
+mod deck;
+mod field_map;
+mod guid;
+mod media;
+mod note_type;
+mod preset;
+mod stock;
+mod tags;
+mod template;
+mod util;
+mod workspace;
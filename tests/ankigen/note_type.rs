@@ -0,0 +1,145 @@
+use ankimdown::ankigen::db_model::model::ModelType;
+use ankimdown::ankigen::deck::{Deck, DeckError};
+use ankimdown::ankigen::note_type::*;
+use ankimdown::ankigen::template::TemplateError;
+
+#[test]
+fn test_models_example() {
+    let models = parse_note_types(include_str!(
+        "../../examples/markdown_databse/models.md"
+    ))
+    .unwrap();
+    assert_eq!(models.len(), 1);
+    let model = &models[0];
+
+    assert_eq!(model.name, "Vocabulary");
+    assert_eq!(model.model_id, note_type_id("Vocabulary"));
+    assert_eq!(model.model_type, ModelType::FrontBack);
+    assert_eq!(model.sort_field_index, 0);
+    assert!(model.css.contains(".word {"));
+
+    let names = model
+        .fields
+        .iter()
+        .map(|field| field.name.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        names,
+        vec!["Word", "Reading", "Meaning", "Example", "Audio"]
+    );
+    assert_eq!(model.fields[0].font, "Noto Serif JP");
+    assert_eq!(model.fields[0].font_size, 28);
+    assert!(model.fields[4].sticky);
+    assert_eq!(model.fields[4].ordinal, 4);
+
+    assert_eq!(model.templates.len(), 2);
+    assert_eq!(model.templates[0].name, "Recognition");
+    assert_eq!(
+        model.templates[0].question_format,
+        "<div class=\"word\">{{Word}}</div>"
+    );
+    assert!(model.templates[0]
+        .answer_template
+        .contains("{{/Example}}"));
+    assert_eq!(model.templates[1].name, "Recall");
+    assert_eq!(model.templates[1].question_format, "{{Meaning}}");
+    assert_eq!(model.templates[1].ordinal, 1);
+}
+
+#[test]
+fn test_note_type_metadata() {
+    let models = parse_note_types(
+        "# Fill in\n## Fields\n- Text\n- Extra\n- Source\n\
+         ## Templates\n### Cloze\n```\n{{cloze:Text}}\n```\n\
+         ## Metadata\n- id: 1234\n- Type: Cloze\n- Sort field: Source\n",
+    )
+    .unwrap();
+    assert_eq!(models[0].model_id, 1234);
+    assert_eq!(models[0].model_type, ModelType::Cloze);
+    assert_eq!(models[0].sort_field_index, 2);
+    assert_eq!(models[0].templates[0].answer_template, "");
+}
+
+#[test]
+fn test_note_type_errors() {
+    let error = |source: &str| parse_note_types(source).unwrap_err();
+    let templates = "## Templates\n### Card\n```\n{{Front}}\n```\n";
+
+    assert_eq!(
+        error(&format!("# T\n{templates}")),
+        NoteTypeError::NoFields {
+            model: "T".to_string()
+        }
+    );
+    assert_eq!(
+        error("# T\n## Fields\n- Front\n"),
+        NoteTypeError::NoTemplates {
+            model: "T".to_string()
+        }
+    );
+    assert_eq!(
+        error(&format!(
+            "# T\n## Fields\n- Front\n- Front\n{templates}"
+        )),
+        NoteTypeError::DuplicateField {
+            model: "T".to_string(),
+            field: "Front".to_string()
+        }
+    );
+    assert_eq!(
+        error(&format!("# T\n## Fields\n- Word\n{templates}")),
+        NoteTypeError::UnknownField {
+            model: "T".to_string(),
+            template: "Card".to_string(),
+            field: "Front".to_string()
+        }
+    );
+    assert_eq!(
+        error(&format!(
+            "# T\n## Fields\n- Front\n    - size: big\n{templates}"
+        ))
+        .to_string(),
+        "invalid value \"big\" for size of note type T"
+    );
+    assert_eq!(
+        error("# T\n## Fields\n- Front\n## Templates\n### Card\n```\n{{#Front}}\n```\n"),
+        NoteTypeError::Template {
+            model: "T".to_string(),
+            template: "Card".to_string(),
+            error: TemplateError::UnclosedConditional {
+                field: "Front".to_string()
+            }
+        }
+    );
+    assert_eq!(
+        error(&format!(
+            "# T\n## Fields\n- Front\n{templates}## Metadata\n- type: cloze\n"
+        )),
+        NoteTypeError::NoClozeField {
+            model: "T".to_string()
+        }
+    );
+}
+
+#[test]
+fn test_deck_note_types() {
+    let deck = Deck::from_markdown(
+        "# Kanji\n\n## Note types\n### Kanji card\n#### Fields\n- Kanji\n- Meaning\n\
+         #### Templates\n##### Card 1\n```\n{{Kanji}}\n```\n```\n{{Meaning}}\n```\n\
+         # 水\n## Meaning\n- water\n",
+    )
+    .unwrap();
+    assert_eq!(deck.note_types.len(), 1);
+    assert_eq!(deck.note_types[0].name, "Kanji card");
+    assert_eq!(
+        deck.note_types[0].templates[0].answer_template,
+        "{{Meaning}}"
+    );
+    assert_eq!(deck.information.len(), 1);
+
+    assert!(matches!(
+        Deck::from_markdown("# D\n## Note types\n### Empty\n")
+            .unwrap_err(),
+        DeckError::NoteType(NoteTypeError::NoFields { .. })
+    ));
+}
@@ -0,0 +1,150 @@
+use ankimdown::ankigen::db_model::deck::{DeckConfig, LeechAction};
+use ankimdown::ankigen::deck::{Deck, DeckError};
+use ankimdown::ankigen::preset::*;
+
+const PRESETS: &str = "# Slow\n\
+    - New per day: 5\n\
+    - Learning steps: 1m 10m 1h\n\
+    - Relearning steps:\n    - 10\n    - 1d\n\
+    - Graduating interval: 2d\n\
+    - Easy interval: 5\n\
+    - Maximum interval: 365\n\
+    - Leech threshold: 4\n\
+    - Leech action: Suspend\n\
+    - Bury new: yes\n\
+    - Bury reviews: true\n\
+    - Reviews per day: 50\n\
+    # Exam\n\
+    ---\n\
+    new_per_day: 100\n\
+    learning_steps: [30s, 5m]\n\
+    ---\n";
+
+#[test]
+fn test_parse_presets() {
+    let presets = parse_presets(PRESETS).unwrap();
+    assert_eq!(presets.len(), 2);
+
+    let slow = &presets[0];
+    assert_eq!(slow.name, "Slow");
+    assert_eq!(slow.id, Some(preset_id("Slow")));
+    assert_eq!(slow.new_config.per_day, 5);
+    assert_eq!(slow.new_config.delays, vec![1.0, 10.0, 60.0]);
+    assert_eq!(slow.lapse_config.delays, vec![10.0, 1440.0]);
+    assert_eq!(slow.new_config.intervals, vec![2, 5, 7]);
+    assert_eq!(slow.review_config.max_interval, 365);
+    assert_eq!(slow.review_config.cards_daily, 50);
+    assert_eq!(slow.lapse_config.leech_fails, 4);
+    assert_eq!(slow.lapse_config.leech_action, LeechAction::Suspend);
+    assert!(slow.new_config.bury);
+    assert!(slow.review_config.bury);
+
+    let exam = &presets[1];
+    assert_eq!(exam.new_config.per_day, 100);
+    assert_eq!(exam.new_config.delays, vec![0.5, 5.0]);
+    let defaults = DeckConfig::new("Exam".to_string());
+    assert_eq!(exam.review_config, defaults.review_config);
+    assert_eq!(exam.lapse_config, defaults.lapse_config);
+}
+
+#[test]
+fn test_preset_errors() {
+    assert_eq!(
+        parse_presets("# Slow\n- New per week: 5\n").unwrap_err(),
+        PresetError::UnknownSetting {
+            preset: "Slow".to_string(),
+            key: "New per week".to_string()
+        }
+    );
+    let error = parse_presets("# Slow\n- Learning steps: 1m 1y\n")
+        .unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "invalid Learning steps of preset Slow: 1m 1y"
+    );
+    assert!(parse_presets("# Slow\n- Bury new: maybe\n").is_err());
+    assert!(
+        parse_presets("# Slow\n- Leech action: delete\n").is_err()
+    );
+}
+
+#[test]
+fn test_deck_presets() {
+    let deck = Deck::from_markdown(
+        "---\nconfig:\n  name: Fast\n  new_per_day: 50\n---\n\
+         # Japanese\n\
+         ## Metadata\n- Note level: 3\n\
+         ## Lesson 1\n### Metadata\n- Config: Slow\n\
+         ## Lesson 2\n### Metadata\n- Config: Fast\n",
+    )
+    .unwrap();
+
+    let preset = deck.metadata.preset.as_ref().unwrap();
+    assert_eq!(preset.name(), "Fast");
+    assert_eq!(preset.config_id(), preset_id("Fast"));
+    let declared = deck.presets();
+    assert_eq!(declared.len(), 1);
+    assert_eq!(declared[0].new_config.per_day, 50);
+    assert_eq!(
+        deck.subdecks[0].metadata.preset,
+        Some(DeckPreset::Named("Slow".to_string()))
+    );
+
+    let mut presets = Presets::new();
+    for config in declared {
+        presets.add(config.clone()).unwrap();
+    }
+    let lesson_2 = deck.subdecks[1].metadata.preset.as_ref().unwrap();
+    assert_eq!(
+        presets.resolve(lesson_2).unwrap().new_config.per_day,
+        50
+    );
+    assert_eq!(
+        presets
+            .resolve(
+                deck.subdecks[0].metadata.preset.as_ref().unwrap()
+            )
+            .unwrap_err(),
+        PresetError::UnknownPreset {
+            name: "Slow".to_string()
+        }
+    );
+
+    assert_eq!(
+        Deck::from_markdown(
+            "---\nconfig:\n  new_per_day: 5\n---\n# D\n"
+        )
+        .unwrap_err(),
+        DeckError::Preset(PresetError::MissingName)
+    );
+}
+
+#[test]
+fn test_presets_registry() {
+    let mut presets = Presets::new();
+    assert_eq!(presets.configs.len(), 1);
+    assert_eq!(presets.get("default").unwrap().id, Some(1));
+    assert_eq!(preset_id("Default"), 1);
+
+    let slow = parse_presets(PRESETS).unwrap().remove(0);
+    presets.add(slow.clone()).unwrap();
+    presets.add(slow.clone()).unwrap();
+    let mut other = slow;
+    other.new_config.per_day = 1;
+    assert_eq!(
+        presets.add(other).unwrap_err(),
+        PresetError::DuplicatePreset {
+            name: "Slow".to_string()
+        }
+    );
+
+    let default = parse_presets("# Default\n- New per day: 30\n")
+        .unwrap()
+        .remove(0);
+    presets.add(default).unwrap();
+    assert_eq!(presets.configs.len(), 2);
+    assert_eq!(
+        presets.get("Default").unwrap().new_config.per_day,
+        30
+    );
+}
@@ -0,0 +1,161 @@
+use ankimdown::ankigen::cards::card_ordinals;
+use ankimdown::ankigen::db_model::model::ModelType;
+use ankimdown::ankigen::db_model::note::Note;
+use ankimdown::ankigen::deck::Deck;
+use ankimdown::ankigen::stock::*;
+use ankimdown::ankigen::template::{
+    parse_template, referenced_fields,
+};
+
+fn note(model: &StockModel, fields: &[&str]) -> Note {
+    Note::with_model(
+        1,
+        "guid".to_string(),
+        &model.model(),
+        0,
+        vec![],
+        fields.iter().map(|field| field.to_string()).collect(),
+    )
+    .unwrap()
+}
+
+#[test]
+fn test_stock_models() {
+    let mut ids = vec![];
+    for stock in StockModel::ALL {
+        let model = stock.model();
+        assert_eq!(model.model_id, stock.model_id());
+        assert_eq!(model.name, stock.name());
+        assert_eq!(
+            model,
+            stock.model(),
+            "{} is not stable",
+            stock.name()
+        );
+        ids.push(model.model_id);
+
+        // Every template only refers to fields of the model.
+        for template in &model.templates {
+            for format in
+                [&template.question_format, &template.answer_template]
+            {
+                let nodes = parse_template(format).unwrap();
+                for field in referenced_fields(&nodes) {
+                    assert!(
+                        field == "FrontSide"
+                            || model
+                                .fields
+                                .iter()
+                                .any(|f| f.name == field),
+                        "{} refers to {field}",
+                        model.name
+                    );
+                }
+            }
+        }
+    }
+    ids.sort();
+    ids.dedup();
+    assert_eq!(ids.len(), StockModel::ALL.len());
+}
+
+#[test]
+fn test_stock_req() {
+    let model = StockModel::BasicOptionalReversed.model();
+    assert_eq!(
+        model.req,
+        vec![
+            (0, "any".to_string(), vec![0]),
+            (1, "all".to_string(), vec![1, 2])
+        ]
+    );
+    assert!(StockModel::Cloze.model().req.is_empty());
+    assert_eq!(
+        StockModel::Cloze.model().model_type,
+        ModelType::Cloze
+    );
+}
+
+#[test]
+fn test_stock_cards() {
+    let reversed = StockModel::BasicAndReversed;
+    let model = reversed.model();
+    assert_eq!(
+        card_ordinals(&note(&reversed, &["a", "b"]), &model).unwrap(),
+        vec![0, 1]
+    );
+
+    let optional = StockModel::BasicOptionalReversed;
+    let model = optional.model();
+    assert_eq!(
+        card_ordinals(&note(&optional, &["a", "b", ""]), &model)
+            .unwrap(),
+        vec![0]
+    );
+    assert_eq!(
+        card_ordinals(&note(&optional, &["a", "b", "y"]), &model)
+            .unwrap(),
+        vec![0, 1]
+    );
+}
+
+#[test]
+fn test_from_templates() {
+    assert_eq!(
+        StockModel::from_templates(&["Simple"]),
+        Some(StockModel::Basic)
+    );
+    assert_eq!(
+        StockModel::from_templates(&["reverse", "Simple"]),
+        Some(StockModel::BasicAndReversed)
+    );
+    assert_eq!(
+        StockModel::from_templates(&["Simple", "Optional reverse"]),
+        Some(StockModel::BasicOptionalReversed)
+    );
+    assert_eq!(
+        StockModel::from_templates(&["Type"]),
+        Some(StockModel::BasicTypeIn)
+    );
+    assert_eq!(
+        StockModel::from_templates(&["Cloze"]),
+        Some(StockModel::Cloze)
+    );
+    assert_eq!(StockModel::from_templates(&["Reverse"]), None);
+    assert_eq!(StockModel::from_templates(&["Unknown"]), None);
+    assert_eq!(StockModel::from_templates::<&str>(&[]), None);
+}
+
+#[test]
+fn test_sample_deck_templates() {
+    let deck = Deck::from_markdown(include_str!(
+        "../../examples/markdown_databse/foo/sample_database.md"
+    ))
+    .unwrap();
+    assert_eq!(
+        deck.information[0].stock_model(),
+        Some(StockModel::BasicAndReversed)
+    );
+
+    let deck = Deck::from_markdown(
+        "# Deck\n# a\n## Meaning\n- x\n\
+         # b\n## Meaning\n- ==y==\n\
+         # c\n## Metadata\n- Templates: Type\n\
+         # d\n## Metadata\n- Templates:\n    - Reverse\n",
+    )
+    .unwrap();
+    let models = deck
+        .information
+        .iter()
+        .map(|information| information.stock_model())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        models,
+        vec![
+            Some(StockModel::Basic),
+            Some(StockModel::Cloze),
+            Some(StockModel::BasicTypeIn),
+            None
+        ]
+    );
+}
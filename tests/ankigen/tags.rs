@@ -0,0 +1,63 @@
+use ankimdown::ankigen::db_model::note::NoteTag;
+use ankimdown::ankigen::metadata::Metadata;
+use ankimdown::ankigen::tags::*;
+use ankimdown::markdown::ast::Node;
+
+fn tags(names: &[&str]) -> Vec<NoteTag> {
+    names
+        .iter()
+        .map(|name| NoteTag::new(name).unwrap())
+        .collect()
+}
+
+#[test]
+fn test_tag_line() {
+    let nodes = Node::parse_document(
+        "#french #lang::verbs\n\n#1 priority\n\n# heading\n",
+    )
+    .unwrap();
+    assert_eq!(
+        tag_line(&nodes[0]),
+        Some(vec!["french".to_string(), "lang::verbs".to_string()])
+    );
+    assert_eq!(tag_line(&nodes[1]), None);
+    assert_eq!(tag_line(&nodes[2]), None);
+}
+
+#[test]
+fn test_metadata_tags() {
+    let metadata = Metadata::from_front_matter(
+        ankimdown::markdown::ast::FrontMatterFormat::Yaml,
+        "tags: [a, 'b::c']\n",
+    )
+    .unwrap();
+    assert_eq!(
+        metadata_tags(&metadata).unwrap(),
+        tags(&["a", "b::c"])
+    );
+
+    let nodes =
+        Node::parse_document("- Tags: a, #b A\n- tags:\n    - c\n")
+            .unwrap();
+    let metadata = Metadata::from_list(&nodes[0]);
+    assert_eq!(
+        metadata_tags(&metadata).unwrap(),
+        tags(&["a", "b", "c"])
+    );
+
+    let nodes = Node::parse_document("- Tags:\n    - a b\n").unwrap();
+    let error =
+        metadata_tags(&Metadata::from_list(&nodes[0])).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "invalid tag a b: Tag name cannot contain spaces"
+    );
+}
+
+#[test]
+fn test_inherit() {
+    assert_eq!(
+        inherit(&tags(&["a", "b"]), tags(&["B", "c"])),
+        tags(&["a", "b", "c"])
+    );
+}
@@ -0,0 +1,88 @@
+use ankimdown::ankigen::template::*;
+
+fn replacement(field: &str, filters: &[&str]) -> TemplateNode {
+    TemplateNode::Replacement {
+        field: field.to_string(),
+        filters: filters
+            .iter()
+            .map(|filter| filter.to_string())
+            .collect(),
+    }
+}
+
+#[test]
+fn test_parse_template() {
+    let nodes = parse_template(
+        "<b>{{Front}}</b>{{#Hint}}{{hint:Hint}}{{/Hint}}",
+    )
+    .unwrap();
+    assert_eq!(
+        nodes,
+        vec![
+            TemplateNode::Text("<b>".to_string()),
+            replacement("Front", &[]),
+            TemplateNode::Text("</b>".to_string()),
+            TemplateNode::Conditional {
+                field: "Hint".to_string(),
+                children: vec![replacement("Hint", &["hint"])],
+            },
+        ]
+    );
+    assert_eq!(
+        referenced_fields(&nodes),
+        vec!["Front", "Hint", "Hint"]
+    );
+}
+
+#[test]
+fn test_parse_template_errors() {
+    assert_eq!(
+        parse_template("{{Front").unwrap_err(),
+        TemplateError::Unterminated { offset: 0 }
+    );
+    assert_eq!(
+        parse_template("{{#Back}}x").unwrap_err(),
+        TemplateError::UnclosedConditional {
+            field: "Back".to_string()
+        }
+    );
+    assert_eq!(
+        parse_template("{{#Back}}x{{/Front}}").unwrap_err(),
+        TemplateError::UnexpectedClose {
+            field: "Front".to_string()
+        }
+    );
+    assert_eq!(
+        parse_template("{{#Back}}x{{/Front}}")
+            .unwrap_err()
+            .to_string(),
+        "unexpected {{/Front}}"
+    );
+}
+
+#[test]
+fn test_renders_with_fields() {
+    let nonempty = |field: &str| field == "Front";
+    let renders = |template: &str| {
+        renders_with_fields(
+            &parse_template(template).unwrap(),
+            &nonempty,
+        )
+    };
+
+    assert!(renders("Q: {{Front}}"));
+    assert!(!renders("Q: {{Back}}"));
+    assert!(!renders("{{FrontSide}}<hr>{{Tags}}"));
+    assert!(renders("{{#Front}}{{Front}}{{/Front}}"));
+    assert!(!renders("{{#Back}}{{Front}}{{/Back}}"));
+    assert!(renders("{{^Back}}{{Front}}{{/Back}}"));
+    assert!(!renders("{{#Front}}only text{{/Front}}"));
+}
+
+#[test]
+fn test_cloze_fields() {
+    let nodes =
+        parse_template("{{cloze:Text}}{{#Extra}}{{Extra}}{{/Extra}}")
+            .unwrap();
+    assert_eq!(cloze_fields(&nodes), vec!["Text"]);
+}
@@ -0,0 +1,29 @@
+use ankimdown::ankigen::util::*;
+
+#[test]
+fn test_strip_html() {
+    assert_eq!(
+        strip_html("<p>a <b>bold</b> word</p>"),
+        "a bold word"
+    );
+    assert_eq!(
+        strip_html("a &lt; b &amp;&nbsp;c&#33;&#x3f;"),
+        "a < b & c!?"
+    );
+    assert_eq!(
+        strip_html("<img src=\"cat.png\" alt=\"x\">cat"),
+        " cat.png cat"
+    );
+    assert_eq!(
+        strip_html("<style>.a{}</style><!-- note -->text<br>"),
+        "text"
+    );
+    assert_eq!(strip_html("1 < 2 &unknown; &"), "1 < 2 &unknown; &");
+}
+
+#[test]
+fn test_field_checksum() {
+    assert_eq!(field_checksum("hello"), 0xaaf4c61d);
+    assert_eq!(field_checksum("<b>hello</b>"), 0xaaf4c61d);
+    assert_ne!(field_checksum("hello "), field_checksum("hello"));
+}
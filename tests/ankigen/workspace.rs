@@ -0,0 +1,278 @@
+use std::path::Path;
+
+use ankimdown::ankigen::deck::DeckError;
+use ankimdown::ankigen::guid::GuidRegistry;
+use ankimdown::ankigen::preset::preset_id;
+use ankimdown::ankigen::util::base91_encode;
+use ankimdown::ankigen::workspace::*;
+
+fn write(root: &Path, path: &str, content: &str) {
+    let path = root.join(path);
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(path, content).unwrap();
+}
+
+fn names(workspace: &Workspace) -> Vec<&str> {
+    workspace
+        .decks
+        .iter()
+        .map(|deck| deck.name.as_str())
+        .collect()
+}
+
+#[test]
+fn test_example_workspace() {
+    let workspace = WorkspaceLoader::new("examples/markdown_databse")
+        .load()
+        .unwrap();
+    assert_eq!(
+        names(&workspace),
+        vec!["Deck name", "Deck name::foo"]
+    );
+    assert_eq!(workspace.note_types.len(), 1);
+    assert_eq!(workspace.note_types[0].name, "Vocabulary");
+
+    let root = &workspace.decks[0];
+    assert!(root.deck_file().is_some());
+    assert_eq!(root.information().count(), 0);
+    let db_deck = root.to_db_deck();
+    assert_eq!(db_deck.name, "Deck name");
+    assert_eq!(db_deck.description, "Deck description");
+
+    let foo = &workspace.decks[1];
+    assert!(foo.deck_file().is_none());
+    assert_eq!(foo.to_db_deck().name, "Deck name::foo");
+    let words = foo
+        .information()
+        .map(|information| information.word.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(words, vec!["hello"]);
+}
+
+#[test]
+fn test_workspace_order_and_ignores() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().join("Languages");
+    write(&root, "z.md", "# Z\n# zebra\n");
+    write(&root, "a.md", "# A\n# apple\n");
+    write(&root, "deck.md", "# Languages\n# language\n");
+    write(&root, "notes.txt", "# not markdown\n");
+    write(&root, "spanish/deck.md", "# Español\n");
+    write(&root, "spanish/verbs/ser.md", "# Verbs\n# ser\n");
+    write(&root, "spanish/old.tmp.md", "# Old\n# viejo\n");
+    write(&root, "french/b.md", "# B\n# bonjour\n");
+    write(&root, "french/skip.md", "# Skip\n# skip\n");
+    write(&root, "drafts/d.md", "# D\n# draft\n");
+    write(&root, ".git/HEAD.md", "# hidden\n");
+    write(&root, "empty/nothing.txt", "");
+    write(&root, ".ankiignore", "# drafts\ndrafts/\n\n*.tmp.md\n");
+
+    let workspace = WorkspaceLoader::new(&root)
+        .with_ignore("/french/skip.md")
+        .load()
+        .unwrap();
+    assert_eq!(
+        names(&workspace),
+        vec![
+            "Languages",
+            "Languages::french",
+            "Languages::Español",
+            "Languages::Español::verbs",
+        ]
+    );
+
+    let words = workspace.decks[0]
+        .information()
+        .map(|information| information.word.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(words, vec!["language", "apple", "zebra"]);
+    assert_eq!(workspace.decks[1].files.len(), 1);
+    assert!(workspace.decks[2].information().next().is_none());
+    assert_eq!(
+        workspace.decks[3].files[0].path,
+        root.join("spanish/verbs/ser.md")
+    );
+
+    let again = WorkspaceLoader::new(&root)
+        .with_ignore("/french/skip.md")
+        .load()
+        .unwrap();
+    assert_eq!(names(&again), names(&workspace));
+}
+
+#[test]
+fn test_workspace_errors() {
+    let dir = tempfile::tempdir().unwrap();
+    write(dir.path(), "a/deck.md", "# Same\n");
+    write(dir.path(), "b/deck.md", "# Same\n");
+    let error = WorkspaceLoader::new(dir.path()).load().unwrap_err();
+    let WorkspaceError::DuplicateDeck {
+        name,
+        first,
+        second,
+    } = error
+    else {
+        panic!("expected a duplicate deck, got {error}");
+    };
+    assert!(name.ends_with("::Same"));
+    assert_eq!(first, dir.path().join("a"));
+    assert_eq!(second, dir.path().join("b"));
+
+    write(dir.path(), "b/deck.md", "no heading\n");
+    let error = WorkspaceLoader::new(dir.path()).load().unwrap_err();
+    assert!(matches!(
+        &error,
+        WorkspaceError::Deck {
+            error: DeckError::MissingName,
+            ..
+        }
+    ));
+    assert_eq!(
+        error.to_string(),
+        format!(
+            "{}: no level 1 heading to name the deck",
+            dir.path().join("b/deck.md").display()
+        )
+    );
+}
+
+#[test]
+fn test_workspace_guids() {
+    let dir = tempfile::tempdir().unwrap();
+    write(dir.path(), "deck.md", "# Root\n");
+    write(dir.path(), "a/words.md", "# Words\n# hello\n");
+    write(dir.path(), "b/words.md", "# Words\n# hello\n");
+    let workspace = WorkspaceLoader::new(dir.path()).load().unwrap();
+    let notes = workspace
+        .notes()
+        .into_iter()
+        .map(|(name, information)| (name, information.word.clone()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        notes,
+        vec![
+            ("Root::a".to_string(), "hello".to_string()),
+            ("Root::b".to_string(), "hello".to_string()),
+        ]
+    );
+    let guids = workspace.register_guids(&mut GuidRegistry::new());
+    assert_eq!(
+        guids,
+        vec![
+            base91_encode(&[
+                "Root::a".to_string(),
+                "hello".to_string()
+            ]),
+            base91_encode(&[
+                "Root::b".to_string(),
+                "hello".to_string()
+            ]),
+        ]
+    );
+    assert!(workspace.guid_collisions.is_empty());
+
+    // Two files of one folder are one deck, whatever their H1.
+    write(dir.path(), "a/more.md", "# More\n# hello\n");
+    let workspace = WorkspaceLoader::new(dir.path()).load().unwrap();
+    assert_eq!(workspace.notes().len(), 3);
+    let collisions = &workspace.guid_collisions;
+    assert_eq!(collisions.len(), 1);
+    assert_eq!(collisions[0].first, "Root::a > hello");
+    assert_eq!(collisions[0].second, "Root::a > hello");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_symlink_loop() {
+    let dir = tempfile::tempdir().unwrap();
+    write(dir.path(), "deck.md", "# Root\n");
+    write(dir.path(), "a/words.md", "# Words\n# hello\n");
+    std::os::unix::fs::symlink(dir.path(), dir.path().join("a/loop"))
+        .unwrap();
+    let workspace = WorkspaceLoader::new(dir.path()).load().unwrap();
+    assert_eq!(names(&workspace), vec!["Root", "Root::a"]);
+}
+
+#[test]
+fn test_glob_match() {
+    assert!(glob_match("*.md", "deck.md"));
+    assert!(!glob_match("*.md", "foo/deck.md"));
+    assert!(glob_match("**/*.md", "deck.md"));
+    assert!(glob_match("**/*.md", "a/b/deck.md"));
+    assert!(glob_match("a/**", "a/b/c"));
+    assert!(glob_match("a/**/c", "a/c"));
+    assert!(glob_match("a/**/c", "a/b/b/c"));
+    assert!(!glob_match("a/**/c", "a/bc"));
+    assert!(glob_match("dec?.md", "deck.md"));
+    assert!(!glob_match("dec?.md", "dec/.md"));
+    assert!(!glob_match("deck", "deck.md"));
+}
+
+#[test]
+fn test_workspace_file_subdecks() {
+    let dir = tempfile::tempdir().unwrap();
+    write(dir.path(), "deck.md", "# Root\n");
+    write(
+        dir.path(),
+        "course/lessons.md",
+        "# Lessons\n## Metadata\n- Note level: 3\n\
+         ## One\n### a\n## Two\n### b\n",
+    );
+    let workspace = WorkspaceLoader::new(dir.path()).load().unwrap();
+    let course = &workspace.decks[1];
+    assert_eq!(course.name, "Root::course");
+    let subdecks = course
+        .file_subdecks()
+        .into_iter()
+        .map(|(name, deck)| (name, deck.information[0].word.clone()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        subdecks,
+        vec![
+            ("Root::course::One".to_string(), "a".to_string()),
+            ("Root::course::Two".to_string(), "b".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn test_workspace_presets() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().join("Languages");
+    write(&root, "presets.md", "# Slow\n- New per day: 5\n");
+    write(&root, "deck.md", "---\nconfig: Slow\n---\n# Languages\n");
+    write(
+        &root,
+        "french/deck.md",
+        "# French\n## Metadata\n- Config: Quick\n    - New per day: 40\n",
+    );
+    write(&root, "spanish/deck.md", "# Spanish\n");
+
+    let workspace = WorkspaceLoader::new(&root).load().unwrap();
+    let names = workspace
+        .presets
+        .configs
+        .iter()
+        .map(|config| config.name.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(names, vec!["Default", "Slow", "Quick"]);
+    let config_ids = workspace
+        .decks
+        .iter()
+        .map(|deck| deck.to_db_deck().config_id)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        config_ids,
+        vec![Some(preset_id("Slow")), Some(preset_id("Quick")), None]
+    );
+
+    write(&root, "spanish/deck.md", "# Spanish\n- Config: Fast\n");
+    assert!(WorkspaceLoader::new(&root).load().is_ok());
+    write(
+        &root,
+        "spanish/deck.md",
+        "# Spanish\n## Metadata\n- Config: Fast\n",
+    );
+    let error = WorkspaceLoader::new(&root).load().unwrap_err();
+    assert!(error.to_string().ends_with("no preset named Fast"));
+}
@@ -0,0 +1,490 @@
+use ankimdown::markdown::ast::*;
+use ankimdown::markdown::error::ParseError;
+
+fn parse(text: &str) -> Vec<std::rc::Rc<Node>> {
+    Node::parse_document(text).unwrap()
+}
+
+#[test]
+fn test_unordered_list() {
+    let nodes = parse("- 1\n- 2\n- 3\n");
+    assert_eq!(nodes.len(), 1);
+    match nodes[0].node_type() {
+        NodeType::List { ordered, start } => {
+            assert!(!ordered);
+            assert_eq!(*start, None);
+        }
+        other => panic!("expected a list, got {other:?}"),
+    }
+    assert_eq!(nodes[0].subnodes().len(), 3);
+    for item in nodes[0].subnodes() {
+        assert!(matches!(item.node_type(), NodeType::ListItem));
+    }
+}
+
+#[test]
+fn test_ordered_list_start() {
+    let nodes = parse("3. three\n4. four\n");
+    match nodes[0].node_type() {
+        NodeType::List { ordered, start } => {
+            assert!(ordered);
+            assert_eq!(*start, Some(3));
+        }
+        other => panic!("expected a list, got {other:?}"),
+    }
+    assert_eq!(format!("{}", nodes[0]), "3. three\n4. four\n");
+}
+
+#[test]
+fn test_nested_list() {
+    let nodes = parse("1. a greeting\n   - nested *it*\n   - two\n");
+    let item = &nodes[0].subnodes()[0];
+    assert!(matches!(item.node_type(), NodeType::ListItem));
+    let sublist = item
+        .subnodes()
+        .iter()
+        .find(|node| {
+            matches!(node.node_type(), NodeType::List { .. })
+        })
+        .unwrap();
+    assert_eq!(sublist.subnodes().len(), 2);
+    let inner = sublist.subnodes()[0].subnodes();
+    assert!(matches!(
+        inner[1].node_type(),
+        NodeType::Text(Text::Italic(children))
+            if children == &[Text::Plain("it".to_string())]
+    ));
+    assert_eq!(
+        format!("{}", nodes[0]),
+        "1. a greeting\n  - nested _it_\n  - two\n"
+    );
+}
+
+#[test]
+fn test_loose_list_item_paragraphs() {
+    let nodes =
+        parse("- Templates:\n\n    - Simple\n    - Reverse\n");
+    let item = &nodes[0].subnodes()[0];
+    assert!(matches!(
+        item.subnodes()[0].node_type(),
+        NodeType::Paragraph
+    ));
+    assert!(matches!(
+        item.subnodes()[1].node_type(),
+        NodeType::List { ordered: false, .. }
+    ));
+    assert_eq!(
+        format!("{}", nodes[0]),
+        "- Templates:\n  - Simple\n  - Reverse\n"
+    );
+}
+
+#[test]
+fn test_heading_sections() {
+    let nodes = parse("# Deck\n\ndescription\n\n# hello\n## Meaning\n1. a greeting\n");
+    assert_eq!(nodes.len(), 2);
+    assert!(matches!(
+        nodes[0].node_type(),
+        NodeType::Heading { level: 1, .. }
+    ));
+    assert!(matches!(
+        nodes[0].subnodes()[0].node_type(),
+        NodeType::Paragraph
+    ));
+    let meaning = &nodes[1].subnodes()[0];
+    assert!(matches!(
+        meaning.node_type(),
+        NodeType::Heading { level: 2, .. }
+    ));
+    assert!(matches!(
+        meaning.subnodes()[0].node_type(),
+        NodeType::List { ordered: true, .. }
+    ));
+}
+
+#[test]
+fn test_sample_database_parses() {
+    let source = include_str!(
+        "../../examples/markdown_databse/foo/sample_database.md"
+    );
+    let nodes = parse(source);
+    assert_eq!(nodes.len(), 2);
+}
+
+/// Parses with every extension of pulldown-cmark, so that events
+/// the AST has no node for come through.
+fn parse_all(
+    source: &str,
+) -> Result<Vec<std::rc::Rc<Node>>, ParseError> {
+    let parser = pulldown_cmark::Parser::new_ext(
+        source,
+        pulldown_cmark::Options::all(),
+    );
+    Node::parse_nodes(&mut parser.into_offset_iter(), source)
+}
+
+#[test]
+fn test_unsupported_construct_error() {
+    let source = "# Deck\n\n[^1]: a note\n";
+    let error = parse_all(source).unwrap_err();
+    match &error {
+        ParseError::Unsupported {
+            construct,
+            location,
+        } => {
+            assert_eq!(*construct, "footnote definition");
+            assert_eq!(location.line, 3);
+            assert_eq!(location.column, 1);
+            assert_eq!(location.range.start, 8);
+        }
+        other => panic!("expected unsupported error, got {other:?}"),
+    }
+    assert_eq!(
+        format!("deck.md:{error}"),
+        "deck.md:3:1: unsupported footnote definition"
+    );
+}
+
+#[test]
+fn test_unsupported_inside_formatting() {
+    let error = parse_all("**a $b$**\n").unwrap_err();
+    assert!(matches!(
+        error,
+        ParseError::Unsupported {
+            construct: "inline math",
+            ..
+        }
+    ));
+}
+
+#[test]
+fn test_inline_html() {
+    assert_eq!(
+        paragraph_text("para <br> html\n"),
+        vec![
+            Text::Plain("para ".to_string()),
+            Text::Html("<br>".to_string()),
+            Text::Plain(" html".to_string()),
+        ]
+    );
+    assert_eq!(
+        paragraph_text("**a <span>b</span>**\n"),
+        vec![Text::Bold(vec![
+            Text::Plain("a ".to_string()),
+            Text::Html("<span>".to_string()),
+            Text::Plain("b".to_string()),
+            Text::Html("</span>".to_string()),
+        ])]
+    );
+}
+
+#[test]
+fn test_html_block() {
+    let nodes = parse("<div class=\"x\">\n*a*\n</div>\n\nafter\n");
+    match nodes[0].node_type() {
+        NodeType::Html(html) => {
+            assert_eq!(html, "<div class=\"x\">\n*a*\n</div>\n")
+        }
+        other => panic!("expected an html block, got {other:?}"),
+    }
+    assert!(matches!(nodes[1].node_type(), NodeType::Paragraph));
+}
+
+#[test]
+fn test_rule() {
+    let nodes = parse("# Deck\na\n\n---\n\nb\n\n***\n");
+    let rules = nodes[0]
+        .subnodes()
+        .iter()
+        .map(|node| matches!(node.node_type(), NodeType::Rule))
+        .collect::<Vec<_>>();
+    assert_eq!(rules, vec![false, true, false, true]);
+}
+
+#[test]
+fn test_block_quote() {
+    let nodes = parse("> quoted\n>\n> - item\n");
+    assert_eq!(nodes.len(), 1);
+    assert!(matches!(nodes[0].node_type(), NodeType::BlockQuote));
+    let quoted = nodes[0].subnodes();
+    assert_eq!(quoted.len(), 2);
+    assert_eq!(
+        quoted[0].inline_content(),
+        vec![Text::Plain("quoted".to_string())]
+    );
+    assert!(matches!(quoted[1].node_type(), NodeType::List { .. }));
+}
+
+fn plain(txt: &str) -> Vec<Text> {
+    vec![Text::Plain(txt.to_string())]
+}
+
+#[test]
+fn test_footnotes_disabled() {
+    let nodes = parse("Text[^1].\n\n[^1]: a note\n");
+    assert_eq!(nodes.len(), 2);
+    assert_eq!(nodes[0].inline_content(), plain("Text[^1]."));
+    assert_eq!(nodes[1].inline_content(), plain("[^1]: a note"));
+}
+
+#[test]
+fn test_task_lists_disabled() {
+    let nodes = parse("- [ ] todo\n- [x] done\n");
+    let items = nodes[0].subnodes();
+    assert_eq!(items[0].inline_content(), plain("[ ] todo"));
+    assert_eq!(items[1].inline_content(), plain("[x] done"));
+}
+
+#[test]
+fn test_smart_punctuation_disabled() {
+    assert_eq!(
+        paragraph_text("\"quoted\" -- it's...\n"),
+        plain("\"quoted\" -- it's...")
+    );
+}
+
+#[test]
+fn test_heading_attributes_disabled() {
+    let nodes = parse("# Title {#id .class}\n");
+    match nodes[0].node_type() {
+        NodeType::Heading { level, content } => {
+            assert_eq!(*level, 1);
+            assert_eq!(*content, plain("Title {#id .class}"));
+        }
+        other => panic!("expected a heading, got {other:?}"),
+    }
+}
+
+#[test]
+fn test_math_disabled() {
+    assert_eq!(
+        paragraph_text("$x^2$ and $$y$$\n"),
+        plain("$x^2$ and $$y$$")
+    );
+}
+
+#[test]
+fn test_blockquote_tags_disabled() {
+    let nodes = parse("> [!NOTE]\n> careful\n");
+    assert!(matches!(nodes[0].node_type(), NodeType::BlockQuote));
+    assert_eq!(
+        nodes[0].subnodes()[0].inline_content(),
+        vec![
+            Text::Plain("[!NOTE]".to_string()),
+            Text::SoftBrake,
+            Text::Plain("careful".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn test_definition_lists_disabled() {
+    assert_eq!(
+        paragraph_text("term\n: definition\n"),
+        vec![
+            Text::Plain("term".to_string()),
+            Text::SoftBrake,
+            Text::Plain(": definition".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn test_superscript_disabled() {
+    assert_eq!(paragraph_text("x^2^\n"), plain("x^2^"));
+}
+
+#[test]
+fn test_subscript_disabled() {
+    assert_eq!(paragraph_text("H~2~O\n"), plain("H~2~O"));
+}
+
+#[test]
+fn test_wikilinks_disabled() {
+    assert_eq!(
+        paragraph_text("[[Page]] and [[Page|label]]\n"),
+        plain("[[Page]] and [[Page|label]]")
+    );
+}
+
+fn paragraph_text(source: &str) -> Vec<Text> {
+    let nodes = parse(source);
+    nodes[0]
+        .subnodes()
+        .iter()
+        .filter_map(|node| match node.node_type() {
+            NodeType::Text(txt) => Some(txt.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn test_nested_inline_formatting() {
+    let text = paragraph_text("**bold _and italic_**\n");
+    assert_eq!(
+        text,
+        vec![Text::Bold(vec![
+            Text::Plain("bold ".to_string()),
+            Text::Italic(vec![Text::Plain("and italic".to_string())]),
+        ])]
+    );
+    assert_eq!(text[0].to_markdown(), "**bold _and italic_**");
+    assert_eq!(
+        text[0].to_html(),
+        "<strong>bold <em>and italic</em></strong>"
+    );
+}
+
+#[test]
+fn test_deeply_nested_inline_formatting() {
+    let text = paragraph_text("~~a **b _c **d** e_ f** g~~\n");
+    assert_eq!(
+        Text::children_to_markdown(&text),
+        "~~a **b _c **d** e_ f** g~~"
+    );
+    assert_eq!(
+        Text::children_to_html(&text),
+        "<del>a <strong>b <em>c <strong>d</strong> e</em> f</strong> g</del>"
+    );
+}
+
+#[test]
+fn test_inline_html_escaping() {
+    let text = paragraph_text("_1 < 2 & 3_\n");
+    assert_eq!(text[0].to_html(), "<em>1 &lt; 2 &amp; 3</em>");
+}
+
+#[test]
+fn test_inline_code() {
+    let text = paragraph_text("call `f(a < b)` **with `x`**\n");
+    assert_eq!(text[1], Text::Code("f(a < b)".to_string()));
+    assert_eq!(text[1].to_markdown(), "`f(a < b)`");
+    assert_eq!(text[1].to_html(), "<code>f(a &lt; b)</code>");
+    assert_eq!(
+        text[3].to_html(),
+        "<strong>with <code>x</code></strong>"
+    );
+    assert_eq!(
+        Text::Code("a`b".to_string()).to_markdown(),
+        "``a`b``"
+    );
+    assert_eq!(
+        Text::Code("`a".to_string()).to_markdown(),
+        "`` `a ``"
+    );
+}
+
+#[test]
+fn test_fenced_code_block() {
+    let nodes = parse("```rust,ignore\nfn main() {}\n```\n");
+    match nodes[0].node_type() {
+        NodeType::CodeBlock { info, code } => {
+            assert_eq!(info.as_deref(), Some("rust,ignore"));
+            assert_eq!(code, "fn main() {}\n");
+        }
+        other => panic!("expected a code block, got {other:?}"),
+    }
+    assert_eq!(
+        format!("{}", nodes[0]),
+        "```rust,ignore\nfn main() {}\n```\n"
+    );
+}
+
+#[test]
+fn test_indented_code_block() {
+    let nodes = parse("text\n\n    let x = 1;\n");
+    assert!(matches!(
+        nodes[1].node_type(),
+        NodeType::CodeBlock { info: None, code } if code == "let x = 1;\n"
+    ));
+}
+
+#[test]
+fn test_link() {
+    let text = paragraph_text(
+        "see [the **docs**](https://x.org/a?b&c \"Docs\")\n",
+    );
+    match &text[1] {
+        Text::Link {
+            url,
+            title,
+            children,
+        } => {
+            assert_eq!(url, "https://x.org/a?b&c");
+            assert_eq!(title, "Docs");
+            assert_eq!(children.len(), 2);
+        }
+        other => panic!("expected a link, got {other:?}"),
+    }
+    assert_eq!(
+        text[1].to_markdown(),
+        "[the **docs**](https://x.org/a?b&c \"Docs\")"
+    );
+    assert_eq!(
+        text[1].to_html(),
+        "<a href=\"https://x.org/a?b&amp;c\" title=\"Docs\">the \
+         <strong>docs</strong></a>"
+    );
+}
+
+#[test]
+fn test_image() {
+    let text =
+        paragraph_text("![a *diagram*](<img/flow chart.png>)\n");
+    assert_eq!(
+        text[0],
+        Text::Image {
+            url: "img/flow chart.png".to_string(),
+            title: String::new(),
+            alt: vec![
+                Text::Plain("a ".to_string()),
+                Text::Italic(vec![Text::Plain(
+                    "diagram".to_string()
+                )]),
+            ],
+        }
+    );
+    assert_eq!(
+        text[0].to_markdown(),
+        "![a _diagram_](<img/flow chart.png>)"
+    );
+    assert_eq!(
+        text[0].to_html(),
+        "<img src=\"img/flow chart.png\" alt=\"a diagram\">"
+    );
+}
+
+#[test]
+fn test_document_starting_below_level_1() {
+    let nodes = parse("### a\ntext\n## b\n#### c\n# d\n");
+    let levels = nodes
+        .iter()
+        .map(|node| node.heading_level())
+        .collect::<Vec<_>>();
+    assert_eq!(levels, vec![Some(3), Some(2), Some(1)]);
+    assert_eq!(nodes[0].subnodes().len(), 1);
+    assert_eq!(nodes[1].subnodes()[0].heading_level(), Some(4));
+}
+
+#[test]
+fn test_front_matter() {
+    let nodes =
+        parse("---\nname: a\n---\n# Deck\n+++\nid = 1\n+++\ntext\n");
+    assert_eq!(nodes.len(), 2);
+    match nodes[0].node_type() {
+        NodeType::FrontMatter { format, content } => {
+            assert_eq!(*format, FrontMatterFormat::Yaml);
+            assert_eq!(content, "name: a\n");
+        }
+        node => panic!("expected front matter, got {node:?}"),
+    }
+    assert!(matches!(
+        nodes[1].subnodes()[0].node_type(),
+        NodeType::FrontMatter {
+            format: FrontMatterFormat::Toml,
+            content,
+        } if content == "id = 1\n"
+    ));
+    assert_eq!(nodes[0].to_string(), "---\nname: a\n---\n");
+}
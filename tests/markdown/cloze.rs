@@ -0,0 +1,209 @@
+use ankimdown::ankigen::cards::card_ordinals;
+use ankimdown::ankigen::db_model::model::ModelType;
+use ankimdown::ankigen::db_model::note::Note;
+use ankimdown::ankigen::information::SimpleInformation;
+use ankimdown::ankigen::stock::{cloze_model, CLOZE_MODEL_ID};
+use ankimdown::markdown::ast::{Node, Text};
+use ankimdown::markdown::html::HtmlRenderer;
+
+fn inline(source: &str) -> Vec<Text> {
+    Node::parse_document(source).unwrap()[0].inline_content()
+}
+
+fn plain(txt: &str) -> Text {
+    Text::Plain(txt.to_string())
+}
+
+fn render(source: &str) -> String {
+    HtmlRenderer::new()
+        .render_nodes(&Node::parse_document(source).unwrap())
+}
+
+#[test]
+fn test_highlight_cloze() {
+    assert_eq!(
+        inline("The capital of France is ==Paris==."),
+        vec![
+            plain("The capital of France is "),
+            Text::Cloze {
+                ordinal: None,
+                children: vec![plain("Paris")],
+                hint: None,
+            },
+            plain("."),
+        ]
+    );
+}
+
+#[test]
+fn test_explicit_cloze_with_hint() {
+    assert_eq!(
+        inline("{{c2::Paris::city}} and {{c1::France}}"),
+        vec![
+            Text::Cloze {
+                ordinal: Some(2),
+                children: vec![plain("Paris")],
+                hint: Some("city".to_string()),
+            },
+            plain(" and "),
+            Text::Cloze {
+                ordinal: Some(1),
+                children: vec![plain("France")],
+                hint: None,
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_cloze_around_formatting() {
+    assert_eq!(
+        inline("a ==**bold** text::hint== b"),
+        vec![
+            plain("a "),
+            Text::Cloze {
+                ordinal: None,
+                children: vec![
+                    Text::Bold(vec![plain("bold")]),
+                    plain(" text")
+                ],
+                hint: Some("hint".to_string()),
+            },
+            plain(" b"),
+        ]
+    );
+    assert_eq!(
+        inline("_in ==italic==_")[0],
+        Text::Italic(vec![
+            plain("in "),
+            Text::Cloze {
+                ordinal: None,
+                children: vec![plain("italic")],
+                hint: None,
+            },
+        ])
+    );
+}
+
+#[test]
+fn test_not_a_cloze() {
+    assert_eq!(
+        inline("a == b and c == d"),
+        vec![plain("a == b and c == d")]
+    );
+    assert_eq!(inline("==open only"), vec![plain("==open only")]);
+    assert_eq!(
+        inline("`==code==`"),
+        vec![Text::Code("==code==".to_string())]
+    );
+    assert_eq!(
+        inline("{{c1::}} {{cx::y}}"),
+        vec![plain("{{c1::}} {{cx::y}}")]
+    );
+}
+
+#[test]
+fn test_cloze_word_boundary() {
+    assert_eq!(inline("a==b and c==d"), vec![plain("a==b and c==d")]);
+    assert_eq!(inline("x==y=="), vec![plain("x==y==")]);
+    assert_eq!(
+        inline("**a**==b== c ==d =="),
+        vec![Text::Bold(vec![plain("a")]), plain("==b== c ==d ==")]
+    );
+    assert_eq!(
+        inline("a\n==b=="),
+        vec![
+            plain("a"),
+            Text::SoftBrake,
+            Text::Cloze {
+                ordinal: None,
+                children: vec![plain("b")],
+                hint: None,
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_cloze_hint_last_separator() {
+    assert_eq!(
+        inline("==std::vec::Vec::type=="),
+        vec![Text::Cloze {
+            ordinal: None,
+            children: vec![plain("std::vec::Vec")],
+            hint: Some("type".to_string()),
+        }]
+    );
+    assert_eq!(
+        inline("{{c1::a::b::hint}}"),
+        vec![Text::Cloze {
+            ordinal: Some(1),
+            children: vec![plain("a::b")],
+            hint: Some("hint".to_string()),
+        }]
+    );
+}
+
+#[test]
+fn test_cloze_markdown_round_trip() {
+    for source in ["a ==b::hint== c", "{{c3::**x**}} y"] {
+        assert_eq!(
+            Text::children_to_markdown(&inline(source)),
+            source
+        );
+    }
+}
+
+#[test]
+fn test_cloze_html() {
+    assert_eq!(
+        render("==Paris== is in ==France::country=="),
+        "<p>{{c1::Paris}} is in {{c2::France::country}}</p>"
+    );
+    assert_eq!(
+        render("{{c3::a}} ==b== {{c1::a & b}}"),
+        "<p>{{c3::a}} {{c4::b}} {{c1::a &amp; b}}</p>"
+    );
+    assert_eq!(
+        render("==**bold**=="),
+        "<p>{{c1::<strong>bold</strong>}}</p>"
+    );
+}
+
+#[test]
+fn test_cloze_model() {
+    let model = cloze_model();
+    assert_eq!(model.model_id, CLOZE_MODEL_ID);
+    assert_eq!(model.model_type, ModelType::Cloze);
+    assert_eq!(
+        model
+            .fields
+            .iter()
+            .map(|field| field.name.as_str())
+            .collect::<Vec<_>>(),
+        vec!["Text", "Back Extra"]
+    );
+    assert!(model.css.contains(".cloze"));
+
+    let note = Note::with_model(
+        1,
+        "guid".to_string(),
+        &model,
+        0,
+        vec![],
+        vec![render("==Paris== is in ==France=="), String::new()],
+    )
+    .unwrap();
+    assert_eq!(card_ordinals(&note, &model).unwrap(), vec![0, 1]);
+}
+
+#[test]
+fn test_information_has_cloze() {
+    let information = SimpleInformation {
+        word: "capital".to_string(),
+        definitions: vec!["The capital is ==Paris==".to_string()],
+        ..Default::default()
+    };
+    assert!(information.has_cloze());
+    assert!(!SimpleInformation::default().has_cloze());
+}
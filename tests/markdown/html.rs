@@ -0,0 +1,152 @@
+use ankimdown::markdown::ast::Node;
+use ankimdown::markdown::html::{sanitize_html, HtmlRenderer};
+
+fn render(source: &str) -> String {
+    HtmlRenderer::new()
+        .render_nodes(&Node::parse_document(source).unwrap())
+}
+
+#[test]
+fn test_paragraph_breaks() {
+    assert_eq!(
+        render("one < b\ntwo  \nthree & four\n"),
+        "<p>one &lt; b<br>two<br>three &amp; four</p>"
+    );
+}
+
+#[test]
+fn test_lists() {
+    assert_eq!(
+        render("- a\n- **b**\n  1. c\n"),
+        "<ul><li>a</li><li><strong>b</strong><ol><li>c</li></ol></li></ul>"
+    );
+    assert_eq!(
+        render("3. three\n\n4. four\n"),
+        "<ol start=\"3\"><li><p>three</p></li><li><p>four</p></li></ol>"
+    );
+}
+
+#[test]
+fn test_heading_section() {
+    assert_eq!(
+        render("# hello\n## Meaning\na _greeting_\n"),
+        "<h1>hello</h1><h2>Meaning</h2><p>a <em>greeting</em></p>"
+    );
+}
+
+#[test]
+fn test_code_block() {
+    assert_eq!(
+        render("```\nif a < b {}\n```\n"),
+        "<pre><code>if a &lt; b {}\n</code></pre>"
+    );
+}
+
+#[test]
+fn test_table() {
+    assert_eq!(
+        render("| a | b |\n| :- | -: |\n| `1` | 2 \\| 3 |\n"),
+        "<table><thead><tr><th style=\"text-align: left\">a</th>\
+         <th style=\"text-align: right\">b</th></tr></thead>\
+         <tbody><tr><td style=\"text-align: left\"><code>1</code></td>\
+         <td style=\"text-align: right\">2 | 3</td></tr></tbody></table>"
+    );
+}
+
+#[test]
+fn test_table_markdown() {
+    let nodes =
+        Node::parse_document("| a | b |\n|---|:-:|\n| 1 | 2 |\n")
+            .unwrap();
+    assert_eq!(
+        format!("{}", nodes[0]),
+        "| a | b |\n| --- | :-: |\n| 1 | 2 |\n"
+    );
+}
+
+#[test]
+fn test_links_and_images() {
+    let image_source = |url: &str| match url {
+        "diagram.png" => Some("diagram-0123.png".to_string()),
+        _ => None,
+    };
+    let nodes = Node::parse_document(
+        "[a \"link\"](https://x.org/?a=1&b=2) ![flow](diagram.png) \
+         ![web](https://x.org/a.png \"Title\")\n",
+    )
+    .unwrap();
+    assert_eq!(
+        HtmlRenderer::new()
+            .with_image_source(&image_source)
+            .render_nodes(&nodes),
+        "<p><a href=\"https://x.org/?a=1&amp;b=2\">a &quot;link&quot;</a> \
+         <img src=\"diagram-0123.png\" alt=\"flow\"> \
+         <img src=\"https://x.org/a.png\" alt=\"web\" title=\"Title\"></p>"
+    );
+}
+
+#[test]
+fn test_raw_html_rules_and_quotes() {
+    assert_eq!(render("para <br> html\n"), "<p>para <br> html</p>");
+    assert_eq!(
+        render("<div class=\"x\">\n*a*\n</div>\n\nb\n"),
+        "<div class=\"x\">\n*a*\n</div><p>b</p>"
+    );
+    assert_eq!(render("a\n\n---\n\nb\n"), "<p>a</p><hr><p>b</p>");
+    assert_eq!(
+        render("> a **b**\n>\n> - c\n"),
+        "<blockquote><p>a <strong>b</strong></p>\
+         <ul><li>c</li></ul></blockquote>"
+    );
+}
+
+#[test]
+fn test_sanitize_html() {
+    for kept in
+        ["<br>", "</SPAN>", "<p data-x=\"a>b\">", "<!-- c -->"]
+    {
+        assert_eq!(sanitize_html(kept), kept);
+    }
+    assert_eq!(
+        sanitize_html("<span class=\"jp\" onclick='x()'>"),
+        "<span class=\"jp\">"
+    );
+    assert_eq!(
+        sanitize_html("<a href=\" JaVaScript:alert(1)\" title=x>"),
+        "<a title=x>"
+    );
+    assert_eq!(sanitize_html("<a href=javascript&#58;x>"), "<a>");
+    assert_eq!(
+        sanitize_html("<div>\n<script>alert('<b>')</script>\n</div>"),
+        "<div>\n\n</div>"
+    );
+    assert_eq!(sanitize_html("<style>p {}</style>"), "");
+    assert_eq!(sanitize_html("a <3 b"), "a &lt;3 b");
+    assert_eq!(sanitize_html("<br/>"), "<br />");
+}
+
+#[test]
+fn test_raw_html_sanitized() {
+    assert_eq!(
+        render("a <img src=\"x.png\" onerror=\"alert(1)\"> b\n"),
+        "<p>a <img src=\"x.png\"> b</p>"
+    );
+    assert_eq!(
+        render("a <script>alert(1)</script> b\n"),
+        "<p>a alert(1) b</p>"
+    );
+    assert_eq!(
+        render(
+            "<div onclick=\"x()\">\n<script>y()</script>\n</div>\n"
+        ),
+        "<div>\n\n</div>"
+    );
+}
+
+#[test]
+fn test_deterministic() {
+    let source = "# deck\n\n| a |\n|---|\n| b |\n\n- x\n- y\n";
+    assert_eq!(render(source), render(source));
+    let nodes = Node::parse_document(source).unwrap();
+    assert_eq!(nodes[0].to_html(), render(source));
+}
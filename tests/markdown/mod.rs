@@ -0,0 +1,3 @@
+mod ast;
+mod cloze;
+mod html;